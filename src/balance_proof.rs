@@ -0,0 +1,301 @@
+#![allow(non_snake_case)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use core::iter;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::{IsIdentity, VartimeMultiscalarMul};
+use rand_core::{CryptoRng, RngCore};
+use serde::de::Visitor;
+use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::errors::ProofError;
+use crate::generators::PedersenGens;
+use crate::transcript::{TranscriptBackend, TranscriptProtocol};
+
+/// A proof that committed inputs balance committed outputs and a public
+/// fee, as in a confidential transaction.
+///
+/// For Pedersen commitments \\(V\_i = v\_i \cdot B + r\_i \cdot \tilde{B}\\),
+/// the excess
+/// \\[
+/// E = \sum\_{\text{in}} V\_i - \sum\_{\text{out}} V\_j - f \cdot B
+/// \\]
+/// is a multiple of \\(\tilde{B}\\) alone exactly when the input values
+/// equal the output values plus the fee \\(f\\).  The proof is a Schnorr
+/// proof of knowledge of the blinding difference
+/// \\(\sum r\_i - \sum r\_j\\) with \\(E\\) as the public key.
+///
+/// The balance is computed modulo the group order, so the outputs must
+/// also be range-checked, for example with a
+/// [`RangeProof`](crate::RangeProof), to rule out values which wrap
+/// around.  To prove the balance and the range checks in a single
+/// constraint system proof, use the `r1cs::gadgets::balance` gadget.
+#[derive(Clone, Debug)]
+pub struct BalanceProof {
+    /// Commitment to the nonce
+    pub(crate) R: CompressedRistretto,
+    /// Response to the challenge
+    pub(crate) s: Scalar,
+}
+
+impl BalanceProof {
+    /// Create a balance proof for the `inputs` and `outputs`, committed
+    /// with the blindings `input_blindings` and `output_blindings`, and
+    /// the public `fee`.
+    ///
+    /// Returns [`ProofError::WrongNumBlindingFactors`] if the number of
+    /// blindings does not match the number of commitments.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create<T: TranscriptBackend, R: RngCore + CryptoRng>(
+        transcript: &mut T,
+        rng: &mut R,
+        pc_gens: &PedersenGens,
+        inputs: &[CompressedRistretto],
+        input_blindings: &[Scalar],
+        outputs: &[CompressedRistretto],
+        output_blindings: &[Scalar],
+        fee: u64,
+    ) -> Result<BalanceProof, ProofError> {
+        if inputs.len() != input_blindings.len() || outputs.len() != output_blindings.len() {
+            return Err(ProofError::WrongNumBlindingFactors);
+        }
+        Self::append_statement(transcript, inputs, outputs, fee);
+
+        let excess_blinding =
+            input_blindings.iter().sum::<Scalar>() - output_blindings.iter().sum::<Scalar>();
+
+        let k = Scalar::random(rng);
+        let R = (k * pc_gens.B_blinding).compress();
+        transcript.validate_and_append_point(b"R", &R)?;
+
+        let c = transcript.challenge_scalar(b"c");
+        let s = k + c * excess_blinding;
+
+        Ok(BalanceProof { R, s })
+    }
+
+    /// Verify the balance proof for the committed `inputs` and `outputs`
+    /// and the public `fee`.
+    pub fn verify<T: TranscriptBackend>(
+        &self,
+        transcript: &mut T,
+        pc_gens: &PedersenGens,
+        inputs: &[CompressedRistretto],
+        outputs: &[CompressedRistretto],
+        fee: u64,
+    ) -> Result<(), ProofError> {
+        Self::append_statement(transcript, inputs, outputs, fee);
+        transcript.validate_and_append_point(b"R", &self.R)?;
+        let c = transcript.challenge_scalar(b"c");
+
+        // Check that s * B_blinding = R + c * E, where
+        // E = sum(inputs) - sum(outputs) - fee * B
+        let check = RistrettoPoint::optional_multiscalar_mul(
+            iter::once(self.s)
+                .chain(iter::once(c * Scalar::from(fee)))
+                .chain(iter::once(-Scalar::ONE))
+                .chain(inputs.iter().map(|_| -c))
+                .chain(outputs.iter().map(|_| c)),
+            iter::once(Some(pc_gens.B_blinding))
+                .chain(iter::once(Some(pc_gens.B)))
+                .chain(iter::once(self.R.decompress()))
+                .chain(inputs.iter().map(|V| V.decompress()))
+                .chain(outputs.iter().map(|V| V.decompress())),
+        )
+        .ok_or(ProofError::FormatError)?;
+
+        if check.is_identity() {
+            Ok(())
+        } else {
+            Err(ProofError::VerificationError)
+        }
+    }
+
+    fn append_statement<T: TranscriptBackend>(
+        transcript: &mut T,
+        inputs: &[CompressedRistretto],
+        outputs: &[CompressedRistretto],
+        fee: u64,
+    ) {
+        transcript.balance_domain_sep(inputs.len() as u64, outputs.len() as u64);
+        for V in inputs {
+            transcript.append_point(b"V_in", V);
+        }
+        for V in outputs {
+            transcript.append_point(b"V_out", V);
+        }
+        transcript.append_u64(b"fee", fee);
+    }
+
+    /// Serializes the proof into a byte array of 64 bytes: the
+    /// compressed Ristretto point \\(R\\) followed by the scalar \\(s\\).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(64);
+        buf.extend_from_slice(self.R.as_bytes());
+        buf.extend_from_slice(self.s.as_bytes());
+        buf
+    }
+
+    /// Deserializes the proof from a byte slice.
+    ///
+    /// Returns an error if the slice is not 64 bytes long, or if the
+    /// scalar is not canonical.
+    pub fn from_bytes(slice: &[u8]) -> Result<BalanceProof, ProofError> {
+        if slice.len() != 64 {
+            return Err(ProofError::FormatError);
+        }
+
+        use crate::util::read32;
+
+        let R = CompressedRistretto(read32(&slice[..32]));
+        let s = Option::from(Scalar::from_canonical_bytes(read32(&slice[32..])))
+            .ok_or(ProofError::FormatError)?;
+
+        Ok(BalanceProof { R, s })
+    }
+}
+
+impl Serialize for BalanceProof {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.to_bytes()[..])
+    }
+}
+
+impl<'de> Deserialize<'de> for BalanceProof {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct BalanceProofVisitor;
+
+        impl<'de> Visitor<'de> for BalanceProofVisitor {
+            type Value = BalanceProof;
+
+            fn expecting(&self, formatter: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                formatter.write_str("a valid BalanceProof")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<BalanceProof, E>
+            where
+                E: serde::de::Error,
+            {
+                // Using Error::custom requires T: Display, which our error
+                // type only implements when it implements std::error::Error.
+                #[cfg(feature = "std")]
+                return BalanceProof::from_bytes(v).map_err(serde::de::Error::custom);
+                // In no-std contexts, drop the error message.
+                #[cfg(not(feature = "std"))]
+                return BalanceProof::from_bytes(v)
+                    .map_err(|_| serde::de::Error::custom("deserialization error"));
+            }
+        }
+
+        deserializer.deserialize_bytes(BalanceProofVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use merlin::Transcript;
+
+    /// Commits to the `values` with random blindings.
+    fn commit(pc_gens: &PedersenGens, values: &[u64]) -> (Vec<CompressedRistretto>, Vec<Scalar>) {
+        let mut rng = rand::thread_rng();
+        values
+            .iter()
+            .map(|v| {
+                let blinding = Scalar::random(&mut rng);
+                (
+                    pc_gens.commit(Scalar::from(*v), blinding).compress(),
+                    blinding,
+                )
+            })
+            .unzip()
+    }
+
+    fn balance_helper(inputs: &[u64], outputs: &[u64], proved_fee: u64, fee: u64) -> bool {
+        let mut rng = rand::thread_rng();
+        let pc_gens = PedersenGens::default();
+        let (input_commitments, input_blindings) = commit(&pc_gens, inputs);
+        let (output_commitments, output_blindings) = commit(&pc_gens, outputs);
+
+        let mut prover_transcript = Transcript::new(b"balanceprooftest");
+        let proof = BalanceProof::create(
+            &mut prover_transcript,
+            &mut rng,
+            &pc_gens,
+            &input_commitments,
+            &input_blindings,
+            &output_commitments,
+            &output_blindings,
+            proved_fee,
+        )
+        .unwrap();
+
+        // The proof survives serialization.
+        let proof = BalanceProof::from_bytes(&proof.to_bytes()).unwrap();
+
+        let mut verifier_transcript = Transcript::new(b"balanceprooftest");
+        proof
+            .verify(
+                &mut verifier_transcript,
+                &pc_gens,
+                &input_commitments,
+                &output_commitments,
+                fee,
+            )
+            .is_ok()
+    }
+
+    #[test]
+    fn balanced() {
+        assert!(balance_helper(&[100, 50], &[120, 29], 1, 1));
+        assert!(balance_helper(&[7], &[7], 0, 0));
+        assert!(balance_helper(&[], &[], 0, 0));
+    }
+
+    #[test]
+    fn unbalanced() {
+        assert!(!balance_helper(&[100, 50], &[120, 29], 0, 0));
+        assert!(!balance_helper(&[100, 50], &[120, 30], 1, 1));
+        // The proof is bound to the fee.
+        assert!(!balance_helper(&[100, 50], &[120, 29], 1, 2));
+    }
+
+    #[test]
+    fn wrong_number_of_blindings() {
+        let mut rng = rand::thread_rng();
+        let pc_gens = PedersenGens::default();
+        let (inputs, input_blindings) = commit(&pc_gens, &[1, 2]);
+        let mut transcript = Transcript::new(b"balanceprooftest");
+        assert_eq!(
+            BalanceProof::create(
+                &mut transcript,
+                &mut rng,
+                &pc_gens,
+                &inputs,
+                &input_blindings[..1],
+                &[],
+                &[],
+                3,
+            )
+            .unwrap_err(),
+            ProofError::WrongNumBlindingFactors
+        );
+    }
+
+    #[test]
+    fn malformed_bytes() {
+        assert!(BalanceProof::from_bytes(&[0u8; 63]).is_err());
+        assert!(BalanceProof::from_bytes(&[0xffu8; 64]).is_err());
+    }
+}
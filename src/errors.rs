@@ -1,6 +1,8 @@
 //! Errors related to proving and verifying proofs.
 
 extern crate alloc;
+#[cfg(feature = "r1cs")]
+use alloc::string::String;
 use alloc::vec::Vec;
 
 #[cfg(feature = "std")]
@@ -43,6 +45,16 @@ pub enum ProofError {
         error("Invalid input size, incorrect input length for proof")
     )]
     InvalidInputLength,
+    /// This error occurs when a batch of proofs failed to verify,
+    /// and identifies the first invalid proof in the batch.
+    #[cfg_attr(
+        feature = "std",
+        error("Batch verification failed, proof {index} is invalid.")
+    )]
+    BatchVerificationError {
+        /// The index of the first invalid proof in the batch.
+        index: usize,
+    },
     /// This error results from an internal error during proving.
     ///
     /// The single-party prover is implemented by performing
@@ -120,9 +132,7 @@ pub enum MPCError {
 }
 
 /// Represents an error during the proving or verifying of a constraint system.
-///
-/// XXX: should this be separate from a `ProofError`?
-#[cfg(feature = "yoloproofs")]
+#[cfg(feature = "r1cs")]
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "std", derive(Error))]
 pub enum R1CSError {
@@ -139,6 +149,29 @@ pub enum R1CSError {
     /// [`R1CSProof`](::r1cs::R1CSProof) fails.
     #[cfg_attr(feature = "std", error("R1CSProof did not verify correctly."))]
     VerificationError,
+    /// Occurs when batch verification of
+    /// [`R1CSProof`](crate::r1cs::R1CSProof)s fails.
+    #[cfg_attr(
+        feature = "std",
+        error("Batch verification failed, proof {index} is invalid.")
+    )]
+    BatchVerificationError {
+        /// The index of the first invalid proof in the batch.
+        index: usize,
+    },
+
+    /// Occurs when recording a
+    /// [`CircuitDescription`](crate::r1cs::CircuitDescription) of a
+    /// constraint system whose randomized constraints have no fixed
+    /// template: their multipliers, terms or transcript messages depend
+    /// on the challenges, or their coefficients are not polynomials of
+    /// bounded degree in the challenges.  Also occurs if a challenge is
+    /// drawn before the randomized constraints.
+    #[cfg_attr(
+        feature = "std",
+        error("The randomized constraints of the circuit cannot be recorded.")
+    )]
+    RandomizedCircuitNotRecordable,
 
     /// Occurs when trying to use a missing variable assignment.
     /// Used by gadgets that build the constraint system to signal that
@@ -152,16 +185,26 @@ pub enum R1CSError {
         /// The description of the reasons for the error.
         description: String,
     },
+
+    /// Occurs when an underlying proof fails with an error that has
+    /// no more specific constraint system counterpart.
+    #[cfg_attr(feature = "std", error("Proof error: {0}"))]
+    ProofError(ProofError),
 }
 
-#[cfg(feature = "yoloproofs")]
+#[cfg(feature = "r1cs")]
 impl From<ProofError> for R1CSError {
     fn from(e: ProofError) -> R1CSError {
         match e {
             ProofError::InvalidGeneratorsLength => R1CSError::InvalidGeneratorsLength,
             ProofError::FormatError => R1CSError::FormatError,
             ProofError::VerificationError => R1CSError::VerificationError,
-            _ => panic!("unexpected error type in conversion"),
+            ProofError::WrongNumBlindingFactors
+            | ProofError::InvalidBitsize
+            | ProofError::InvalidAggregation
+            | ProofError::InvalidInputLength
+            | ProofError::BatchVerificationError { .. }
+            | ProofError::ProvingError(_) => R1CSError::ProofError(e),
         }
     }
 }
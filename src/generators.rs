@@ -12,8 +12,8 @@ use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
 use curve25519_dalek::ristretto::RistrettoPoint;
 use curve25519_dalek::scalar::Scalar;
 use curve25519_dalek::traits::MultiscalarMul;
-use digest::{ExtendableOutput, Update, XofReader};
-use sha3::{Sha3_512, Shake256, Shake256Reader};
+use digest::{ExtendableOutput, FixedOutput, Update, XofReader};
+use sha3::{Sha3_256, Sha3_512, Shake256, Shake256Reader};
 
 /// Represents a pair of base points for Pedersen commitments.
 ///
@@ -39,6 +39,93 @@ impl PedersenGens {
     pub fn commit(&self, value: Scalar, blinding: Scalar) -> RistrettoPoint {
         RistrettoPoint::multiscalar_mul(&[value, blinding], &[self.B, self.B_blinding])
     }
+
+    /// Returns a digest of the bases `B` and `B_blinding`.
+    ///
+    /// The digest covers only two points, so it is cheap enough to
+    /// compute for every proof.
+    pub fn digest(&self) -> GeneratorsDigest {
+        GeneratorsDigest::from_points(&[self.B, self.B_blinding])
+    }
+}
+
+/// A 32-byte digest of an ordered sequence of generators.
+///
+/// The digest is computed as SHA3-256 over the domain separator
+/// `"bulletproofs-gens-digest-v1"`, the compressed encodings of the
+/// points in order, and finally the number of points as a 64-bit
+/// little-endian integer.
+///
+/// Digests can be bound into a proof transcript instead of the
+/// generators themselves, so that a proof only verifies against the
+/// generators it was created with.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct GeneratorsDigest(pub [u8; 32]);
+
+impl GeneratorsDigest {
+    /// Computes the digest of the given sequence of `points`.
+    pub fn from_points<'a, I>(points: I) -> Self
+    where
+        I: IntoIterator<Item = &'a RistrettoPoint>,
+    {
+        let mut hasher = Sha3_256::default();
+        hasher.update(b"bulletproofs-gens-digest-v1");
+        let mut count = 0u64;
+        for point in points {
+            hasher.update(point.compress().as_bytes());
+            count += 1;
+        }
+        hasher.update(&count.to_le_bytes());
+        GeneratorsDigest(hasher.finalize_fixed().into())
+    }
+
+    /// Computes the digests of the prefixes of `points` whose lengths
+    /// are \\(1, 2, 4, \ldots\\), up to the length of `points`.
+    ///
+    /// The `k`-th digest is the same as [`GeneratorsDigest::from_points`]
+    /// of the first \\(2^k\\) points, but every point is only
+    /// compressed and hashed once.
+    fn from_prefixes(points: &[RistrettoPoint]) -> Vec<Self> {
+        let mut hasher = Sha3_256::default();
+        hasher.update(b"bulletproofs-gens-digest-v1");
+        let mut digests = Vec::new();
+        for (i, point) in points.iter().enumerate() {
+            hasher.update(point.compress().as_bytes());
+            let count = (i + 1) as u64;
+            if count.is_power_of_two() {
+                let mut prefix = hasher.clone();
+                prefix.update(&count.to_le_bytes());
+                digests.push(GeneratorsDigest(prefix.finalize_fixed().into()));
+            }
+        }
+        digests
+    }
+
+    /// Computes a digest of the sequence of `digests`, under the
+    /// domain separator `label`.
+    ///
+    /// The digest is computed as SHA3-256 over the `label`, the
+    /// digests in order, and finally their number as a 64-bit
+    /// little-endian integer.
+    pub(crate) fn from_digests<I>(label: &'static [u8], digests: I) -> Self
+    where
+        I: IntoIterator<Item = GeneratorsDigest>,
+    {
+        let mut hasher = Sha3_256::default();
+        hasher.update(label);
+        let mut count = 0u64;
+        for digest in digests {
+            hasher.update(digest.as_bytes());
+            count += 1;
+        }
+        hasher.update(&count.to_le_bytes());
+        GeneratorsDigest(hasher.finalize_fixed().into())
+    }
+
+    /// Returns the digest bytes.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
 }
 
 impl Default for PedersenGens {
@@ -139,6 +226,16 @@ pub struct BulletproofGens {
     G_vec: Vec<Vec<RistrettoPoint>>,
     /// Precomputed \\(\mathbf H\\) generators for each party.
     H_vec: Vec<Vec<RistrettoPoint>>,
+    /// Whether proofs bind digests of the generators into the transcript.
+    bind_transcript: bool,
+    /// Digests of the power-of-two prefixes of each party's
+    /// \\(\mathbf G\\) generators, precomputed when transcript binding
+    /// is enabled.
+    G_digests: Vec<Vec<GeneratorsDigest>>,
+    /// Digests of the power-of-two prefixes of each party's
+    /// \\(\mathbf H\\) generators, precomputed when transcript binding
+    /// is enabled.
+    H_digests: Vec<Vec<GeneratorsDigest>>,
 }
 
 impl BulletproofGens {
@@ -160,11 +257,84 @@ impl BulletproofGens {
             party_capacity,
             G_vec: (0..party_capacity).map(|_| Vec::new()).collect(),
             H_vec: (0..party_capacity).map(|_| Vec::new()).collect(),
+            bind_transcript: false,
+            G_digests: Vec::new(),
+            H_digests: Vec::new(),
         };
         gens.increase_capacity(gens_capacity);
         gens
     }
 
+    /// Makes proofs created or verified with these generators bind
+    /// the generators into the proof transcript.
+    ///
+    /// When enabled, [`RangeProof`](crate::RangeProof) and
+    /// [`R1CSProof`](crate::r1cs::R1CSProof) append the
+    /// [`PedersenGens::digest`] and the [`BulletproofGens::digest`]
+    /// of the generators actually used by the proof to the transcript,
+    /// so that a proof only verifies against the generators it was
+    /// created with.  The prover and the verifier must agree on
+    /// this option.
+    ///
+    /// [`LinearProof::create_with_gens`](crate::LinearProof::create_with_gens)
+    /// binds a digest of the generators it uses instead of appending
+    /// each of them.
+    ///
+    /// The digests of the power-of-two prefixes of the generators,
+    /// which are the sizes used by proofs, are computed once here and
+    /// when the capacity is increased, so that binding them costs a
+    /// few hashes per proof.
+    pub fn with_transcript_binding(mut self) -> Self {
+        self.bind_transcript = true;
+        self.precompute_digests();
+        self
+    }
+
+    /// Precomputes the digests of the power-of-two prefixes of the
+    /// generators of each party.
+    fn precompute_digests(&mut self) {
+        self.G_digests = self
+            .G_vec
+            .iter()
+            .map(|G_j| GeneratorsDigest::from_prefixes(G_j))
+            .collect();
+        self.H_digests = self
+            .H_vec
+            .iter()
+            .map(|H_j| GeneratorsDigest::from_prefixes(H_j))
+            .collect();
+    }
+
+    /// Returns whether proofs bind digests of the generators into the
+    /// transcript, see [`BulletproofGens::with_transcript_binding`].
+    pub fn binds_transcript(&self) -> bool {
+        self.bind_transcript
+    }
+
+    /// Returns the digest of the generators used for aggregating `m`
+    /// proofs of size `n`.
+    ///
+    /// The digest combines, under the domain separator
+    /// `"bulletproofs-gens-aggregate-v1"`, the digests of the first `n`
+    /// \\(\mathbf G\\) generators of
+    /// parties \\(0, \ldots, m-1\\), followed by the digests of their
+    /// first `n` \\(\mathbf H\\) generators.  With transcript binding
+    /// enabled and `n` a power of two, the digests of the parties are
+    /// precomputed, and are otherwise computed from the generators.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` exceeds `gens_capacity` or `m` exceeds `party_capacity`.
+    pub fn digest(&self, n: usize, m: usize) -> GeneratorsDigest {
+        assert!(n <= self.gens_capacity && m <= self.party_capacity);
+        let G_digests = (0..m).map(|j| self.share(j).G_digest(n));
+        let H_digests = (0..m).map(|j| self.share(j).H_digest(n));
+        GeneratorsDigest::from_digests(
+            b"bulletproofs-gens-aggregate-v1",
+            G_digests.chain(H_digests),
+        )
+    }
+
     /// Returns j-th share of generators, with an appropriate
     /// slice of vectors G and H for the j-th range proof.
     pub fn share(&self, j: usize) -> BulletproofGensShare<'_> {
@@ -201,6 +371,9 @@ impl BulletproofGens {
             );
         }
         self.gens_capacity = new_capacity;
+        if self.bind_transcript {
+            self.precompute_digests();
+        }
     }
 
     /// Return an iterator over the aggregation of the parties' G generators with given size `n`.
@@ -284,6 +457,41 @@ impl<'a> BulletproofGensShare<'a> {
     pub(crate) fn H(&self, n: usize) -> impl Iterator<Item = &'a RistrettoPoint> {
         self.gens.H_vec[self.share].iter().take(n)
     }
+
+    /// Return the generators this is a share of.
+    pub(crate) fn gens(&self) -> &'a BulletproofGens {
+        self.gens
+    }
+
+    /// Returns the digest of this party's first `n` G generators,
+    /// precomputed if possible.
+    pub(crate) fn G_digest(&self, n: usize) -> GeneratorsDigest {
+        let digests = self.gens.G_digests.get(self.share);
+        Self::prefix_digest(&self.gens.G_vec[self.share], digests, n)
+    }
+
+    /// Returns the digest of this party's first `n` H generators,
+    /// precomputed if possible.
+    pub(crate) fn H_digest(&self, n: usize) -> GeneratorsDigest {
+        let digests = self.gens.H_digests.get(self.share);
+        Self::prefix_digest(&self.gens.H_vec[self.share], digests, n)
+    }
+
+    fn prefix_digest(
+        points: &[RistrettoPoint],
+        digests: Option<&Vec<GeneratorsDigest>>,
+        n: usize,
+    ) -> GeneratorsDigest {
+        let precomputed = if n.is_power_of_two() {
+            digests.and_then(|d| d.get(n.trailing_zeros() as usize))
+        } else {
+            None
+        };
+        match precomputed {
+            Some(digest) => *digest,
+            None => GeneratorsDigest::from_points(&points[..n]),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -353,4 +561,35 @@ mod tests {
         helper(32, 8);
         helper(16, 8);
     }
+
+    #[test]
+    fn digest_depends_only_on_used_generators() {
+        let small = BulletproofGens::new(32, 2);
+        let big = BulletproofGens::new(64, 4);
+
+        assert_eq!(small.digest(32, 2), big.digest(32, 2));
+        assert_ne!(big.digest(32, 2), big.digest(64, 2));
+        assert_ne!(big.digest(32, 2), big.digest(32, 4));
+        assert_ne!(big.digest(0, 0), big.digest(1, 1));
+
+        // The digests precomputed for transcript binding match the
+        // digests computed from the generators, also after resizing.
+        let mut bound = BulletproofGens::new(32, 4).with_transcript_binding();
+        bound.increase_capacity(64);
+        for (n, m) in [(64, 4), (32, 2), (1, 1), (48, 3), (0, 0)] {
+            assert_eq!(bound.digest(n, m), big.digest(n, m));
+        }
+        assert_eq!(
+            GeneratorsDigest::from_prefixes(&big.G_vec[1])[4],
+            GeneratorsDigest::from_points(big.share(1).G(16))
+        );
+
+        let pc_gens = PedersenGens::default();
+        let swapped = PedersenGens {
+            B: pc_gens.B_blinding,
+            B_blinding: pc_gens.B,
+        };
+        assert_eq!(pc_gens.digest(), PedersenGens::default().digest());
+        assert_ne!(pc_gens.digest(), swapped.digest());
+    }
 }
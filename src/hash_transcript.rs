@@ -0,0 +1,295 @@
+//! A [`TranscriptBackend`] built from a plain hash function.
+//!
+//! Merlin is based on STROBE, which is awkward to reimplement in
+//! constrained environments such as smart contracts.  The
+//! [`HashTranscript`] only needs a collision-resistant hash function
+//! \\(H\\) with a \\(d\\)-byte output, e.g. Keccak-256 (the default)
+//! or SHA3-256, so that verification can be mirrored wherever that
+//! hash function is available.  The exact byte-level derivation of the
+//! transcript state and challenges is specified on [`HashTranscript`].
+
+use core::marker::PhantomData;
+
+use digest::{Digest, Output};
+use rand_core::{impls, CryptoRng, RngCore};
+use sha3::Keccak256;
+
+use crate::transcript::{TranscriptBackend, WitnessRngBuilder};
+
+const INIT_LABEL: &[u8] = b"bulletproofs-hash-transcript-v1";
+const TAG_APPEND: u8 = 0x00;
+const TAG_CHALLENGE: u8 = 0x01;
+const TAG_OUTPUT: u8 = 0x02;
+const TAG_RNG: u8 = 0x03;
+const TAG_RNG_OUTPUT: u8 = 0x04;
+
+/// A transcript using Keccak-256 as the hash function.
+pub type KeccakTranscript = HashTranscript<Keccak256>;
+
+/// A Fiat–Shamir transcript built from the hash function `D`.
+///
+/// # Byte-level specification
+///
+/// The transcript state is a single \\(d\\)-byte digest \\(s\\).
+/// Below, \\(\mathrm{le32}(x)\\) and \\(\mathrm{le64}(x)\\) are the
+/// little-endian encodings of \\(x\\) as 4 and 8 bytes respectively,
+/// \\(|x|\\) is the length of \\(x\\) in bytes, and \\(\Vert\\) is
+/// concatenation.
+///
+/// * **Initialization** with a `label`:
+///   \\(s \gets H(\texttt{"bulletproofs-hash-transcript-v1"} \Vert \mathrm{le32}(|label|) \Vert label)\\).
+/// * **Appending** a `message` with a `label`:
+///   \\(s \gets H(s \Vert \mathtt{0x00} \Vert \mathrm{le32}(|label|) \Vert label \Vert \mathrm{le32}(|message|) \Vert message)\\).
+///   A `u64` is appended as its 8-byte little-endian encoding.
+/// * **Squeezing** \\(L\\) challenge bytes with a `label`: first
+///   \\(s \gets H(s \Vert \mathtt{0x01} \Vert \mathrm{le32}(|label|) \Vert label \Vert \mathrm{le32}(L))\\),
+///   then the output is the first \\(L\\) bytes of
+///   \\(H(s \Vert \mathtt{0x02} \Vert \mathrm{le32}(0)) \Vert H(s \Vert \mathtt{0x02} \Vert \mathrm{le32}(1)) \Vert \cdots\\).
+///
+/// Challenge scalars are derived by squeezing 64 bytes, interpreting
+/// them as a little-endian 512-bit integer and reducing it modulo the
+/// order \\(\ell\\) of the Ristretto group.  Points and scalars are
+/// appended in their canonical 32-byte encodings.
+///
+/// The witness-bound RNG used by the R1CS prover is only relevant to
+/// the prover and is not needed to verify proofs.  It forks the state
+/// as \\(H(s \Vert \mathtt{0x03} \Vert \ldots)\\), absorbing each
+/// labeled witness and then 32 bytes of external randomness in the
+/// same length-prefixed manner, and produces output blocks
+/// \\(H(seed \Vert \mathtt{0x04} \Vert \mathrm{le64}(i))\\).
+pub struct HashTranscript<D: Digest + Clone = Keccak256> {
+    state: Output<D>,
+}
+
+impl<D: Digest + Clone> Clone for HashTranscript<D> {
+    fn clone(&self) -> Self {
+        HashTranscript {
+            state: self.state.clone(),
+        }
+    }
+}
+
+/// Hashes `bytes` prefixed by its little-endian 32-bit length.
+fn update_prefixed<D: Digest>(hasher: &mut D, bytes: &[u8]) {
+    hasher.update((bytes.len() as u32).to_le_bytes());
+    hasher.update(bytes);
+}
+
+impl<D: Digest + Clone> HashTranscript<D> {
+    /// Initialize a new transcript with the supplied `label`, which
+    /// is used as a domain separator.
+    pub fn new(label: &'static [u8]) -> Self {
+        let mut hasher = D::new();
+        hasher.update(INIT_LABEL);
+        update_prefixed(&mut hasher, label);
+        HashTranscript {
+            state: hasher.finalize(),
+        }
+    }
+
+    /// Returns the current transcript state.
+    pub fn state(&self) -> &[u8] {
+        &self.state
+    }
+
+    fn hasher(&self, tag: u8) -> D {
+        let mut hasher = D::new();
+        hasher.update(&self.state);
+        hasher.update([tag]);
+        hasher
+    }
+}
+
+impl<D: Digest + Clone> TranscriptBackend for HashTranscript<D> {
+    type RngBuilder = HashTranscriptRngBuilder<D>;
+
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        let mut hasher = self.hasher(TAG_APPEND);
+        update_prefixed(&mut hasher, label);
+        update_prefixed(&mut hasher, message);
+        self.state = hasher.finalize();
+    }
+
+    fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+        let mut hasher = self.hasher(TAG_CHALLENGE);
+        update_prefixed(&mut hasher, label);
+        hasher.update((dest.len() as u32).to_le_bytes());
+        self.state = hasher.finalize();
+
+        let block_size = <D as Digest>::output_size();
+        for (i, chunk) in dest.chunks_mut(block_size).enumerate() {
+            let mut hasher = self.hasher(TAG_OUTPUT);
+            hasher.update((i as u32).to_le_bytes());
+            let block = hasher.finalize();
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+    }
+
+    fn build_rng(&self) -> Self::RngBuilder {
+        HashTranscriptRngBuilder {
+            hasher: self.hasher(TAG_RNG),
+        }
+    }
+}
+
+/// Constructs a [`HashTranscriptRng`] from a [`HashTranscript`].
+pub struct HashTranscriptRngBuilder<D: Digest + Clone> {
+    hasher: D,
+}
+
+impl<D: Digest + Clone> WitnessRngBuilder for HashTranscriptRngBuilder<D> {
+    type Rng = HashTranscriptRng<D>;
+
+    fn rekey_with_witness_bytes(mut self, label: &'static [u8], witness: &[u8]) -> Self {
+        update_prefixed(&mut self.hasher, label);
+        update_prefixed(&mut self.hasher, witness);
+        self
+    }
+
+    fn finalize<R: RngCore + CryptoRng>(mut self, rng: &mut R) -> Self::Rng {
+        let mut random_bytes = [0u8; 32];
+        rng.fill_bytes(&mut random_bytes);
+        update_prefixed(&mut self.hasher, &random_bytes);
+
+        HashTranscriptRng {
+            seed: self.hasher.finalize(),
+            counter: 0,
+            block: Output::<D>::default(),
+            position: <D as Digest>::output_size(),
+            _digest: PhantomData,
+        }
+    }
+}
+
+/// An RNG bound to a [`HashTranscript`] state and the prover's secrets.
+pub struct HashTranscriptRng<D: Digest + Clone> {
+    seed: Output<D>,
+    counter: u64,
+    block: Output<D>,
+    position: usize,
+    _digest: PhantomData<D>,
+}
+
+impl<D: Digest + Clone> RngCore for HashTranscriptRng<D> {
+    fn next_u32(&mut self) -> u32 {
+        impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for byte in dest.iter_mut() {
+            if self.position == self.block.len() {
+                let mut hasher = D::new();
+                hasher.update(&self.seed);
+                hasher.update([TAG_RNG_OUTPUT]);
+                hasher.update(self.counter.to_le_bytes());
+                self.block = hasher.finalize();
+                self.counter += 1;
+                self.position = 0;
+            }
+            *byte = self.block[self.position];
+            self.position += 1;
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl<D: Digest + Clone> CryptoRng for HashTranscriptRng<D> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::transcript::TranscriptProtocol;
+    use curve25519_dalek::scalar::Scalar;
+
+    #[test]
+    fn challenge_matches_specification() {
+        let mut transcript = KeccakTranscript::new(b"test");
+        transcript.append_message(b"msg", b"hello");
+        let challenge = transcript.challenge_scalar(b"c");
+
+        let mut h = Keccak256::new();
+        h.update(b"bulletproofs-hash-transcript-v1");
+        h.update(4u32.to_le_bytes());
+        h.update(b"test");
+        let s = h.finalize();
+
+        let mut h = Keccak256::new();
+        h.update(s);
+        h.update([0x00]);
+        h.update(3u32.to_le_bytes());
+        h.update(b"msg");
+        h.update(5u32.to_le_bytes());
+        h.update(b"hello");
+        let s = h.finalize();
+
+        let mut h = Keccak256::new();
+        h.update(s);
+        h.update([0x01]);
+        h.update(1u32.to_le_bytes());
+        h.update(b"c");
+        h.update(64u32.to_le_bytes());
+        let s = h.finalize();
+
+        let mut wide = [0u8; 64];
+        for i in 0..2u32 {
+            let mut h = Keccak256::new();
+            h.update(s);
+            h.update([0x02]);
+            h.update(i.to_le_bytes());
+            wide[32 * i as usize..32 * (i as usize + 1)].copy_from_slice(&h.finalize());
+        }
+
+        assert_eq!(transcript.state(), &s[..]);
+        assert_eq!(challenge, Scalar::from_bytes_mod_order_wide(&wide));
+    }
+
+    #[test]
+    fn challenges_depend_on_messages() {
+        let mut t1 = KeccakTranscript::new(b"test");
+        let mut t2 = KeccakTranscript::new(b"test");
+        t1.append_message(b"msg", b"hello");
+        t2.append_message(b"msg", b"world");
+        assert_ne!(t1.challenge_scalar(b"c"), t2.challenge_scalar(b"c"));
+    }
+
+    #[test]
+    fn rangeproof_with_hash_transcript() {
+        use crate::{BulletproofGens, PedersenGens, RangeProof};
+
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(64, 2);
+        let mut rng = rand::thread_rng();
+        let blindings = [Scalar::random(&mut rng), Scalar::random(&mut rng)];
+
+        let mut transcript = KeccakTranscript::new(b"HashTranscriptTest");
+        let (proof, commitments) = RangeProof::prove_multiple(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            &[7, 1037578891],
+            &blindings,
+            32,
+        )
+        .unwrap();
+
+        let mut transcript = KeccakTranscript::new(b"HashTranscriptTest");
+        assert!(proof
+            .verify_multiple(&bp_gens, &pc_gens, &mut transcript, &commitments, 32)
+            .is_ok());
+
+        // A merlin transcript does not accept a proof made with a hash transcript.
+        let mut transcript = merlin::Transcript::new(b"HashTranscriptTest");
+        assert!(proof
+            .verify_multiple(&bp_gens, &pc_gens, &mut transcript, &commitments, 32)
+            .is_err());
+    }
+}
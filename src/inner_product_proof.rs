@@ -10,10 +10,9 @@ use core::iter;
 use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
 use curve25519_dalek::scalar::Scalar;
 use curve25519_dalek::traits::VartimeMultiscalarMul;
-use merlin::Transcript;
 
 use crate::errors::ProofError;
-use crate::transcript::TranscriptProtocol;
+use crate::transcript::{TranscriptBackend, TranscriptProtocol};
 
 #[derive(Clone, Debug)]
 pub struct InnerProductProof {
@@ -35,8 +34,8 @@ impl InnerProductProof {
     ///
     /// The lengths of the vectors must all be the same, and must all be
     /// either 0 or a power of 2.
-    pub fn create(
-        transcript: &mut Transcript,
+    pub fn create<T: TranscriptBackend>(
+        transcript: &mut T,
         Q: &RistrettoPoint,
         G_factors: &[Scalar],
         H_factors: &[Scalar],
@@ -195,10 +194,10 @@ impl InnerProductProof {
     /// Computes three vectors of verification scalars \\([u\_{i}^{2}]\\), \\([u\_{i}^{-2}]\\) and \\([s\_{i}]\\) for combined multiscalar multiplication
     /// in a parent protocol. See [inner product protocol notes](index.html#verification-equation) for details.
     /// The verifier must provide the input length \\(n\\) explicitly to avoid unbounded allocation within the inner product proof.
-    pub(crate) fn verification_scalars(
+    pub(crate) fn verification_scalars<T: TranscriptBackend>(
         &self,
         n: usize,
-        transcript: &mut Transcript,
+        transcript: &mut T,
     ) -> Result<(Vec<Scalar>, Vec<Scalar>, Vec<Scalar>), ProofError> {
         let lg_n = self.L_vec.len();
         if lg_n >= 32 {
@@ -257,10 +256,10 @@ impl InnerProductProof {
     /// method to combine inner product verification with other checks
     /// in a single multiscalar multiplication.
     #[allow(dead_code)]
-    pub fn verify<IG, IH>(
+    pub fn verify<T, IG, IH>(
         &self,
         n: usize,
-        transcript: &mut Transcript,
+        transcript: &mut T,
         G_factors: IG,
         H_factors: IH,
         P: &RistrettoPoint,
@@ -269,6 +268,7 @@ impl InnerProductProof {
         H: &[RistrettoPoint],
     ) -> Result<(), ProofError>
     where
+        T: TranscriptBackend,
         IG: IntoIterator,
         IG::Item: Borrow<Scalar>,
         IH: IntoIterator,
@@ -428,6 +428,7 @@ mod tests {
     use super::*;
 
     use crate::util;
+    use merlin::Transcript;
     use sha3::Sha3_512;
 
     fn test_helper_create(n: usize) {
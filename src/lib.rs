@@ -24,17 +24,28 @@ mod notes {
     mod r1cs_proof {}
 }
 
+mod balance_proof;
 mod errors;
 mod generators;
+mod hash_transcript;
 mod inner_product_proof;
 mod linear_proof;
 mod range_proof;
+mod tracing_transcript;
 mod transcript;
 
+pub use crate::balance_proof::BalanceProof;
 pub use crate::errors::ProofError;
-pub use crate::generators::{BulletproofGens, BulletproofGensShare, PedersenGens};
-pub use crate::linear_proof::LinearProof;
+pub use crate::generators::{
+    BulletproofGens, BulletproofGensShare, GeneratorsDigest, PedersenGens,
+};
+pub use crate::hash_transcript::{
+    HashTranscript, HashTranscriptRng, HashTranscriptRngBuilder, KeccakTranscript,
+};
+pub use crate::linear_proof::{LinearGens, LinearProof, LinearVerificationScalars};
 pub use crate::range_proof::RangeProof;
+pub use crate::tracing_transcript::{first_mismatch, TraceEvent, TraceMismatch, TracingTranscript};
+pub use crate::transcript::{TranscriptBackend, WitnessRngBuilder};
 
 #[cfg_attr(feature = "docs", doc(include = "../docs/aggregation-api.md"))]
 pub mod range_proof_mpc {
@@ -44,6 +55,5 @@ pub mod range_proof_mpc {
     pub use crate::range_proof::party;
 }
 
-#[cfg(feature = "yoloproofs")]
-#[cfg(feature = "std")]
+#[cfg(feature = "r1cs")]
 pub mod r1cs;
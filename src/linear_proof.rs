@@ -7,13 +7,16 @@ use alloc::vec::Vec;
 use core::iter;
 use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
 use curve25519_dalek::scalar::Scalar;
-use curve25519_dalek::traits::VartimeMultiscalarMul;
-use merlin::Transcript;
+use curve25519_dalek::traits::{IsIdentity, VartimeMultiscalarMul};
 use rand_core::{CryptoRng, RngCore};
+use serde::de::Visitor;
+use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::errors::ProofError;
+use crate::generators::{BulletproofGensShare, GeneratorsDigest, PedersenGens};
 use crate::inner_product_proof::inner_product;
-use crate::transcript::TranscriptProtocol;
+use crate::transcript::{TranscriptBackend, TranscriptProtocol};
+use crate::util;
 
 /// A linear proof, which is an "lightweight" version of a Bulletproofs inner-product proof
 /// Protocol: Section E.3 of [GHL'21](https://eprint.iacr.org/2021/1397.pdf)
@@ -37,23 +40,298 @@ impl LinearProof {
     ///
     /// The lengths of the vectors must all be the same, and must all be either 0 or a power of 2.
     /// The proof is created with respect to the bases \\(G\\).
-    pub fn create<T: RngCore + CryptoRng>(
-        transcript: &mut Transcript,
-        rng: &mut T,
+    /// To prove statements about vectors of other lengths, use [`LinearProof::create_with_gens`].
+    pub fn create<T: TranscriptBackend, R: RngCore + CryptoRng>(
+        transcript: &mut T,
+        rng: &mut R,
         // Commitment to witness
         C: &CompressedRistretto,
         // Blinding factor for C
-        mut r: Scalar,
+        r: Scalar,
         // Secret scalar vector a
-        mut a_vec: Vec<Scalar>,
+        a_vec: Vec<Scalar>,
         // Public scalar vector b
-        mut b_vec: Vec<Scalar>,
+        b_vec: Vec<Scalar>,
         // Generator vector
-        mut G_vec: Vec<RistrettoPoint>,
+        G_vec: Vec<RistrettoPoint>,
         // Pedersen generator F, for committing to the secret value
         F: &RistrettoPoint,
         // Pedersen generator B, for committing to the blinding value
         B: &RistrettoPoint,
+    ) -> Result<LinearProof, ProofError> {
+        Self::create_with_binding(
+            transcript,
+            rng,
+            C,
+            r,
+            a_vec,
+            b_vec,
+            G_vec,
+            F,
+            B,
+            GensBinding::Points,
+        )
+    }
+
+    /// Create a linear proof like [`LinearProof::create`], over the
+    /// generators `gens`, but bind the generators into the transcript
+    /// through their digest instead of appending every generator.
+    ///
+    /// The digest precomputed in `gens` is bound, so the proof is always
+    /// bound to the generators it is created with.  Proofs created with
+    /// this function must be verified with [`LinearProof::verify_with_digest`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_with_digest<T: TranscriptBackend, R: RngCore + CryptoRng>(
+        transcript: &mut T,
+        rng: &mut R,
+        C: &CompressedRistretto,
+        r: Scalar,
+        a_vec: Vec<Scalar>,
+        b_vec: Vec<Scalar>,
+        gens: &LinearGens,
+    ) -> Result<LinearProof, ProofError> {
+        Self::create_with_binding(
+            transcript,
+            rng,
+            C,
+            r,
+            a_vec,
+            b_vec,
+            gens.G.clone(),
+            &gens.F,
+            &gens.B,
+            GensBinding::Digest(&gens.digest),
+        )
+    }
+
+    /// Returns the digest of the generators \\(G\\), \\(F\\) and \\(B\\),
+    /// as precomputed by [`LinearGens::new`].
+    pub fn gens_digest(
+        G: &[RistrettoPoint],
+        F: &RistrettoPoint,
+        B: &RistrettoPoint,
+    ) -> GeneratorsDigest {
+        GeneratorsDigest::from_points(G.iter().chain(iter::once(F)).chain(iter::once(B)))
+    }
+
+    /// Returns the digest bound by [`LinearProof::create_with_gens`]
+    /// for the first `n` generators of `bp_gens` and for `pc_gens`,
+    /// which combines their precomputed digests.
+    fn share_gens_digest(
+        bp_gens: &BulletproofGensShare,
+        pc_gens: &PedersenGens,
+        n: usize,
+    ) -> GeneratorsDigest {
+        GeneratorsDigest::from_digests(
+            b"bulletproofs-linear-gens-v1",
+            [bp_gens.G_digest(n), pc_gens.digest()],
+        )
+    }
+
+    /// Create a linear proof that \\(\langle a, b \rangle = c\\) for
+    /// vectors of any nonzero length \\(n\\), using the \\(G\\)
+    /// generators of a [`BulletproofGensShare`] and the Pedersen
+    /// generators `pc_gens` as \\(F = B\\) and \\(B = B\_{blinding}\\).
+    ///
+    /// The commitment must be
+    /// \\(C = \langle a, G \rangle + r \cdot B\_{blinding} + c \cdot B\\)
+    /// over the first \\(n\\) generators of the share.  The vectors are
+    /// padded with zeros to the next power of two \\(n'\\), and the proof
+    /// is created over the first \\(n'\\) generators, so the share must
+    /// have capacity for \\(n'\\) generators.  Since the padding is
+    /// zero, the proof is the same as a proof for the padded vectors.
+    ///
+    /// If the generators have
+    /// [transcript binding](crate::BulletproofGens::with_transcript_binding)
+    /// enabled, the generators are bound into the transcript by a
+    /// digest, combined from the precomputed digests of the share and of
+    /// `pc_gens`, rather than point by point.
+    ///
+    /// The proof is verified with [`LinearProof::verify_with_gens`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_with_gens<T: TranscriptBackend, R: RngCore + CryptoRng>(
+        transcript: &mut T,
+        rng: &mut R,
+        bp_gens: &BulletproofGensShare,
+        pc_gens: &PedersenGens,
+        C: &CompressedRistretto,
+        r: Scalar,
+        mut a_vec: Vec<Scalar>,
+        mut b_vec: Vec<Scalar>,
+    ) -> Result<LinearProof, ProofError> {
+        let n = b_vec.len();
+        if n == 0 || a_vec.len() != n {
+            return Err(ProofError::InvalidInputLength);
+        }
+        let padded_n = n.next_power_of_two();
+        if bp_gens.gens().gens_capacity < padded_n {
+            return Err(ProofError::InvalidGeneratorsLength);
+        }
+        a_vec.resize(padded_n, Scalar::ZERO);
+        b_vec.resize(padded_n, Scalar::ZERO);
+
+        let G_vec: Vec<RistrettoPoint> = bp_gens.G(padded_n).cloned().collect();
+        let (F, B) = (&pc_gens.B, &pc_gens.B_blinding);
+        let digest;
+        let binding = if bp_gens.gens().binds_transcript() {
+            digest = Self::share_gens_digest(bp_gens, pc_gens, padded_n);
+            GensBinding::Digest(&digest)
+        } else {
+            GensBinding::Points
+        };
+
+        Self::create_with_binding(transcript, rng, C, r, a_vec, b_vec, G_vec, F, B, binding)
+    }
+
+    /// Create a proof of several linear relations
+    /// \\(\langle a, b\_k \rangle = c\_k\\) about the same secret vector
+    /// \\(a\\), one for each public vector in `b_vecs`.
+    ///
+    /// The vector is committed as
+    /// \\(A = \langle a, G \rangle + r\_A \cdot B\_{blinding}\\) over the
+    /// first \\(n\\) generators of `bp_gens`, with blinding `a_blinding`,
+    /// and each value as a Pedersen commitment
+    /// \\(C\_k = c\_k \cdot B + r\_k \cdot B\_{blinding}\\), with blinding
+    /// `value_blindings[k]`.  Public values can be committed with a zero
+    /// blinding factor.
+    ///
+    /// After binding the public vectors and all commitments into the
+    /// transcript, the relations are combined with powers of a challenge
+    /// \\(\rho\\) into the single relation
+    /// \\(\langle a, \sum\_k \rho^{k+1} b\_k \rangle = \sum\_k \rho^{k+1} c\_k\\),
+    /// for \\(k\\) counting from zero, which is proven with
+    /// [`LinearProof::create_with_gens`].  The proof therefore has the
+    /// same logarithmic size as a proof of one relation, and is verified
+    /// with [`LinearProof::verify_multi`].
+    ///
+    /// The weights start at \\(\rho\\) rather than \\(1\\), which is the
+    /// weight of \\(A\\) in the combined commitment: a relation with the
+    /// same weight as \\(A\\) could be offset by a multiple of \\(B\\)
+    /// hidden in \\(A\\).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProofError::InvalidInputLength`] if `b_vecs` is empty,
+    /// if `value_commitments`, `value_blindings` and `b_vecs` have
+    /// different lengths, or if any vector in `b_vecs` does not have
+    /// the same length as `a_vec`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_multi<T: TranscriptBackend, R: RngCore + CryptoRng>(
+        transcript: &mut T,
+        rng: &mut R,
+        bp_gens: &BulletproofGensShare,
+        pc_gens: &PedersenGens,
+        A: &CompressedRistretto,
+        a_blinding: Scalar,
+        value_commitments: &[CompressedRistretto],
+        value_blindings: &[Scalar],
+        a_vec: Vec<Scalar>,
+        b_vecs: &[Vec<Scalar>],
+    ) -> Result<LinearProof, ProofError> {
+        if value_blindings.len() != value_commitments.len() {
+            return Err(ProofError::InvalidInputLength);
+        }
+        let (rho_powers, b_vec, C) =
+            Self::combine_relations(transcript, A, value_commitments, b_vecs, a_vec.len())?;
+
+        let r = a_blinding
+            + rho_powers
+                .iter()
+                .zip(value_blindings.iter())
+                .map(|(rho_k, r_k)| rho_k * r_k)
+                .sum::<Scalar>();
+
+        Self::create_with_gens(transcript, rng, bp_gens, pc_gens, &C, r, a_vec, b_vec)
+    }
+
+    /// Verify a proof created with [`LinearProof::create_multi`] that the
+    /// vector committed in `A` has inner product with each of the
+    /// public vectors in `b_vecs` equal to the value committed in the
+    /// corresponding entry of `value_commitments`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProofError::InvalidInputLength`] if `b_vecs` is empty,
+    /// if `value_commitments` and `b_vecs` have different lengths, or if
+    /// the vectors in `b_vecs` do not all have the same length.
+    pub fn verify_multi<T: TranscriptBackend>(
+        &self,
+        transcript: &mut T,
+        bp_gens: &BulletproofGensShare,
+        pc_gens: &PedersenGens,
+        A: &CompressedRistretto,
+        value_commitments: &[CompressedRistretto],
+        b_vecs: &[Vec<Scalar>],
+    ) -> Result<(), ProofError> {
+        let n = b_vecs.first().ok_or(ProofError::InvalidInputLength)?.len();
+        let (_, b_vec, C) = Self::combine_relations(transcript, A, value_commitments, b_vecs, n)?;
+
+        self.verify_with_gens(transcript, bp_gens, pc_gens, &C, b_vec)
+    }
+
+    /// Binds the public vectors and the commitments of a multi-relation
+    /// proof into the transcript, and combines the relations with the powers
+    /// \\(\rho, \rho^2, \ldots\\) of a challenge \\(\rho\\).  Returns the
+    /// powers of \\(\rho\\), the combined public vector and the combined
+    /// commitment.
+    fn combine_relations<T: TranscriptBackend>(
+        transcript: &mut T,
+        A: &CompressedRistretto,
+        value_commitments: &[CompressedRistretto],
+        b_vecs: &[Vec<Scalar>],
+        n: usize,
+    ) -> Result<(Vec<Scalar>, Vec<Scalar>, CompressedRistretto), ProofError> {
+        let k = b_vecs.len();
+        if k == 0 || value_commitments.len() != k {
+            return Err(ProofError::InvalidInputLength);
+        }
+        if b_vecs.iter().any(|b_k| b_k.len() != n) {
+            return Err(ProofError::InvalidInputLength);
+        }
+
+        transcript.linear_multi_domain_sep(k as u64, n as u64);
+        for b_k in b_vecs {
+            for b_ki in b_k {
+                transcript.append_scalar(b"b_ki", b_ki);
+            }
+        }
+        transcript.append_point(b"A", A);
+        for C_k in value_commitments {
+            transcript.append_point(b"C_k", C_k);
+        }
+        let rho = transcript.challenge_scalar(b"rho");
+        let rho_powers: Vec<Scalar> = util::exp_iter(rho).skip(1).take(k).collect();
+
+        let mut b_vec = alloc::vec![Scalar::ZERO; n];
+        for (rho_k, b_k) in rho_powers.iter().zip(b_vecs.iter()) {
+            for (b_i, b_ki) in b_vec.iter_mut().zip(b_k.iter()) {
+                *b_i += rho_k * b_ki;
+            }
+        }
+
+        let C = RistrettoPoint::optional_multiscalar_mul(
+            iter::once(&Scalar::ONE).chain(rho_powers.iter()),
+            iter::once(A)
+                .chain(value_commitments.iter())
+                .map(|p| p.decompress()),
+        )
+        .ok_or(ProofError::FormatError)?;
+
+        Ok((rho_powers, b_vec, C.compress()))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn create_with_binding<T: TranscriptBackend, R: RngCore + CryptoRng>(
+        transcript: &mut T,
+        rng: &mut R,
+        C: &CompressedRistretto,
+        mut r: Scalar,
+        mut a_vec: Vec<Scalar>,
+        mut b_vec: Vec<Scalar>,
+        mut G_vec: Vec<RistrettoPoint>,
+        F: &RistrettoPoint,
+        B: &RistrettoPoint,
+        binding: GensBinding,
     ) -> Result<LinearProof, ProofError> {
         let mut n = b_vec.len();
         // All of the input vectors must have the same length.
@@ -74,11 +352,7 @@ impl LinearProof {
         for b_i in &b_vec {
             transcript.append_scalar(b"b_i", b_i);
         }
-        for G_i in &G_vec {
-            transcript.append_point(b"G_i", &G_i.compress());
-        }
-        transcript.append_point(b"F", &F.compress());
-        transcript.append_point(b"B", &B.compress());
+        binding.append_gens(transcript, &G_vec, F, B);
 
         // Create slices G, H, a, b backed by their respective
         // vectors. This lets us reslice as we compress the lengths
@@ -159,9 +433,9 @@ impl LinearProof {
         })
     }
 
-    pub fn verify(
+    pub fn verify<T: TranscriptBackend>(
         &self,
-        transcript: &mut Transcript,
+        transcript: &mut T,
         // Commitment to witness
         C: &CompressedRistretto,
         // Generator vector
@@ -172,7 +446,214 @@ impl LinearProof {
         B: &RistrettoPoint,
         // Public scalar vector b
         b_vec: Vec<Scalar>,
+    ) -> Result<(), ProofError> {
+        self.verify_with_binding(transcript, C, G, F, B, b_vec, GensBinding::Points)
+    }
+
+    /// Verify a proof created with [`LinearProof::create_with_digest`]
+    /// over the generators `gens`, binding the generators into the
+    /// transcript through their precomputed digest.
+    pub fn verify_with_digest<T: TranscriptBackend>(
+        &self,
+        transcript: &mut T,
+        C: &CompressedRistretto,
+        gens: &LinearGens,
+        b_vec: Vec<Scalar>,
+    ) -> Result<(), ProofError> {
+        self.verify_with_binding(
+            transcript,
+            C,
+            &gens.G,
+            &gens.F,
+            &gens.B,
+            b_vec,
+            GensBinding::Digest(&gens.digest),
+        )
+    }
+
+    /// Verify a proof created with [`LinearProof::create_with_gens`]
+    /// for the public vector `b_vec` of any nonzero length.
+    ///
+    /// The padding of `b_vec` is derived from its length, as in
+    /// [`LinearProof::create_with_gens`].
+    pub fn verify_with_gens<T: TranscriptBackend>(
+        &self,
+        transcript: &mut T,
+        bp_gens: &BulletproofGensShare,
+        pc_gens: &PedersenGens,
+        C: &CompressedRistretto,
+        mut b_vec: Vec<Scalar>,
     ) -> Result<(), ProofError> {
+        let n = b_vec.len();
+        if n == 0 {
+            return Err(ProofError::InvalidInputLength);
+        }
+        let padded_n = n.next_power_of_two();
+        if bp_gens.gens().gens_capacity < padded_n {
+            return Err(ProofError::InvalidGeneratorsLength);
+        }
+        b_vec.resize(padded_n, Scalar::ZERO);
+
+        let G: Vec<RistrettoPoint> = bp_gens.G(padded_n).cloned().collect();
+        let (F, B) = (&pc_gens.B, &pc_gens.B_blinding);
+        let digest;
+        let binding = if bp_gens.gens().binds_transcript() {
+            digest = Self::share_gens_digest(bp_gens, pc_gens, padded_n);
+            GensBinding::Digest(&digest)
+        } else {
+            GensBinding::Points
+        };
+
+        self.verify_with_binding(transcript, C, &G, F, B, b_vec, binding)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn verify_with_binding<T: TranscriptBackend>(
+        &self,
+        transcript: &mut T,
+        C: &CompressedRistretto,
+        G: &[RistrettoPoint],
+        F: &RistrettoPoint,
+        B: &RistrettoPoint,
+        b_vec: Vec<Scalar>,
+        binding: GensBinding,
+    ) -> Result<(), ProofError> {
+        let scalars =
+            self.verification_scalars_with_binding(transcript, C, G, F, B, b_vec, binding)?;
+
+        if scalars.is_valid(G, F, B) {
+            Ok(())
+        } else {
+            Err(ProofError::VerificationError)
+        }
+    }
+
+    /// Verifies a batch of linear proofs with a single multiscalar
+    /// multiplication.
+    ///
+    /// The `i`-th proof is verified against `transcripts[i]`, the
+    /// commitment `commitments[i]` and the public vector `b_vecs[i]`,
+    /// using the first `b_vecs[i].len()` generators of `G`, so proofs
+    /// over vectors of different lengths can be batched together as
+    /// long as their generators are prefixes of the same `G`.  All
+    /// proofs share the Pedersen generators `F` and `B`.
+    ///
+    /// The verification equations of all proofs are combined with
+    /// random weights drawn from `rng`.  If the batch fails to verify,
+    /// the proofs are checked one by one (reusing the recomputed
+    /// challenges) and [`ProofError::BatchVerificationError`] reports
+    /// the index of the first invalid proof.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_batch<T: TranscriptBackend, R: RngCore + CryptoRng>(
+        proofs: &[LinearProof],
+        transcripts: &mut [T],
+        commitments: &[CompressedRistretto],
+        b_vecs: Vec<Vec<Scalar>>,
+        G: &[RistrettoPoint],
+        F: &RistrettoPoint,
+        B: &RistrettoPoint,
+        rng: &mut R,
+    ) -> Result<(), ProofError> {
+        let m = proofs.len();
+        if transcripts.len() != m || commitments.len() != m || b_vecs.len() != m {
+            return Err(ProofError::InvalidInputLength);
+        }
+        let max_n = b_vecs.iter().map(Vec::len).max().unwrap_or(0);
+        if G.len() < max_n {
+            return Err(ProofError::InvalidGeneratorsLength);
+        }
+
+        let all_scalars = proofs
+            .iter()
+            .zip(transcripts.iter_mut())
+            .zip(commitments.iter())
+            .zip(b_vecs)
+            .enumerate()
+            .map(|(index, (((proof, transcript), C), b_vec))| {
+                let G = &G[..b_vec.len()];
+                proof
+                    .verification_scalars(transcript, C, G, F, B, b_vec)
+                    .map_err(|_| ProofError::BatchVerificationError { index })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Sum the weighted coefficients of the shared generators, and
+        // collect the weighted per-proof points.
+        let mut G_scalars = alloc::vec![Scalar::ZERO; max_n];
+        let mut B_scalar = Scalar::ZERO;
+        let mut F_scalar = Scalar::ZERO;
+        let mut dynamic_scalars = Vec::new();
+        let mut dynamic_points = Vec::new();
+        for scalars in all_scalars.iter() {
+            let w = Scalar::random(rng);
+            B_scalar += w * scalars.B_scalar;
+            F_scalar += w * scalars.F_scalar;
+            for (G_scalar, s) in G_scalars.iter_mut().zip(scalars.G_scalars.iter()) {
+                *G_scalar += w * s;
+            }
+            dynamic_scalars.extend(scalars.dynamic_scalars.iter().map(|s| w * s));
+            dynamic_points.extend_from_slice(&scalars.dynamic_points);
+        }
+
+        let check = RistrettoPoint::vartime_multiscalar_mul(
+            iter::once(B_scalar)
+                .chain(iter::once(F_scalar))
+                .chain(G_scalars)
+                .chain(dynamic_scalars),
+            iter::once(B)
+                .chain(iter::once(F))
+                .chain(G.iter().take(max_n))
+                .chain(dynamic_points.iter()),
+        );
+
+        if check.is_identity() {
+            return Ok(());
+        }
+
+        // Find the first proof which fails on its own.
+        if let Some(index) = all_scalars
+            .iter()
+            .position(|scalars| !scalars.is_valid(G, F, B))
+        {
+            return Err(ProofError::BatchVerificationError { index });
+        }
+
+        // The individual proofs are valid, so the random combination
+        // can only have failed with negligible probability.
+        Err(ProofError::VerificationError)
+    }
+
+    /// Computes the verification equation of the proof, for combining
+    /// it with other checks in a single multiscalar multiplication.
+    ///
+    /// This appends the public data to the `transcript` and replays
+    /// the proof exactly as [`LinearProof::verify`] does, and takes the
+    /// same arguments apart from the proof itself.  The proof is valid
+    /// if and only if the returned [`LinearVerificationScalars`]
+    /// describe a multiscalar multiplication equal to the identity.
+    pub fn verification_scalars<T: TranscriptBackend>(
+        &self,
+        transcript: &mut T,
+        C: &CompressedRistretto,
+        G: &[RistrettoPoint],
+        F: &RistrettoPoint,
+        B: &RistrettoPoint,
+        b_vec: Vec<Scalar>,
+    ) -> Result<LinearVerificationScalars, ProofError> {
+        self.verification_scalars_with_binding(transcript, C, G, F, B, b_vec, GensBinding::Points)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn verification_scalars_with_binding<T: TranscriptBackend>(
+        &self,
+        transcript: &mut T,
+        C: &CompressedRistretto,
+        G: &[RistrettoPoint],
+        F: &RistrettoPoint,
+        B: &RistrettoPoint,
+        b_vec: Vec<Scalar>,
+        binding: GensBinding,
+    ) -> Result<LinearVerificationScalars, ProofError> {
         let n = b_vec.len();
         if G.len() != n {
             return Err(ProofError::InvalidGeneratorsLength);
@@ -184,61 +665,44 @@ impl LinearProof {
         for b_i in &b_vec {
             transcript.append_scalar(b"b_i", b_i);
         }
-        for G_i in G {
-            transcript.append_point(b"G_i", &G_i.compress());
-        }
-        transcript.append_point(b"F", &F.compress());
-        transcript.append_point(b"B", &B.compress());
+        binding.append_gens(transcript, G, F, B);
 
-        let (x_vec, x_inv_vec, b_0) = self.verification_scalars(n, transcript, b_vec)?;
+        let (x_vec, x_inv_vec, b_0) = self.challenges(n, transcript, b_vec)?;
         transcript.append_point(b"S", &self.S);
         let x_star = transcript.challenge_scalar(b"x_star");
 
-        // Decompress the compressed L values
-        let Ls = self
-            .L_vec
-            .iter()
-            .map(|p| p.decompress().ok_or(ProofError::VerificationError))
-            .collect::<Result<Vec<_>, _>>()?;
-
-        // Decompress the compressed R values
-        let Rs = self
-            .R_vec
-            .iter()
+        // Decompress the proof points, together with the commitment.
+        let points = iter::once(&self.S)
+            .chain(iter::once(C))
+            .chain(self.L_vec.iter())
+            .chain(self.R_vec.iter())
             .map(|p| p.decompress().ok_or(ProofError::VerificationError))
             .collect::<Result<Vec<_>, _>>()?;
 
-        // L_R_factors = sum_{j=0}^{l-1} (x_j * L_j + x_j^{-1} * R_j)
-        //
-        // Note: in GHL'21 the verification equation is incorrect (as of 05/03/22), with x_j and x_j^{-1} reversed.
-        // (Incorrect paper equation: sum_{j=0}^{l-1} (x_j^{-1} * L_j + x_j * R_j) )
-        let L_R_factors: RistrettoPoint = RistrettoPoint::vartime_multiscalar_mul(
-            x_vec.iter().chain(x_inv_vec.iter()),
-            Ls.iter().chain(Rs.iter()),
-        );
-
         // This is an optimized way to compute the base case G (G_0 in the paper):
         // G_0 = sum_{i=0}^{2^{l-1}} (x<i> * G_i)
-        let s = self.subset_product(n, x_vec);
-        let G_0: RistrettoPoint = RistrettoPoint::vartime_multiscalar_mul(s.iter(), G.iter());
-
-        let S = self.S.decompress().ok_or(ProofError::VerificationError)?;
-        let C = C.decompress().ok_or(ProofError::VerificationError)?;
+        let s = self.subset_product(n, x_vec.clone());
 
         // This matches the verification equation:
         // S == r_star * B + a_star * b_0 * F
         //      - x_star * (C + sum_{j=0}^{l-1} (x_j * L_j + x_j^{-1} * R_j))
         //      + a_star * sum_{i=0}^{2^{l-1}} (x<i> * G_i)
         //
-        // Where L_R_factors = sum_{j=0}^{l-1} (x_j * L_j + x_j^{-1} * R_j)
-        // and G_0 = sum_{i=0}^{2^{l-1}} (x<i> * G_i)
-        let expect_S = self.r * B + self.a * b_0 * F - x_star * (C + L_R_factors) + self.a * G_0;
+        // Note: in GHL'21 the verification equation is incorrect (as of 05/03/22), with x_j and x_j^{-1} reversed.
+        // (Incorrect paper equation: sum_{j=0}^{l-1} (x_j^{-1} * L_j + x_j * R_j) )
+        let dynamic_scalars = iter::once(-Scalar::ONE)
+            .chain(iter::once(-x_star))
+            .chain(x_vec.iter().map(|x| -x_star * x))
+            .chain(x_inv_vec.iter().map(|x_inv| -x_star * x_inv))
+            .collect();
 
-        if expect_S == S {
-            Ok(())
-        } else {
-            Err(ProofError::VerificationError)
-        }
+        Ok(LinearVerificationScalars {
+            B_scalar: self.r,
+            F_scalar: self.a * b_0,
+            G_scalars: s.iter().map(|s_i| self.a * s_i).collect(),
+            dynamic_scalars,
+            dynamic_points: points,
+        })
     }
 
     /// Computes the vector of challenge scalars \\([x\_{i}]\\), and its inverse \\([x\_{i}^{-1}]\\)
@@ -246,10 +710,10 @@ impl LinearProof {
     /// Also computes \\(b_0\\) which is the base case for public vector \\(b\\).
     ///
     /// The verifier must provide the input length \\(n\\) explicitly to avoid unbounded allocation.
-    pub(crate) fn verification_scalars(
+    fn challenges<T: TranscriptBackend>(
         &self,
         n: usize,
-        transcript: &mut Transcript,
+        transcript: &mut T,
         mut b_vec: Vec<Scalar>,
     ) -> Result<(Vec<Scalar>, Vec<Scalar>, Scalar), ProofError> {
         let lg_n = self.L_vec.len();
@@ -404,10 +868,180 @@ impl LinearProof {
     }
 }
 
+impl Serialize for LinearProof {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.to_bytes()[..])
+    }
+}
+
+impl<'de> Deserialize<'de> for LinearProof {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct LinearProofVisitor;
+
+        impl<'de> Visitor<'de> for LinearProofVisitor {
+            type Value = LinearProof;
+
+            fn expecting(&self, formatter: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                formatter.write_str("a valid LinearProof")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<LinearProof, E>
+            where
+                E: serde::de::Error,
+            {
+                // Using Error::custom requires T: Display, which our error
+                // type only implements when it implements std::error::Error.
+                #[cfg(feature = "std")]
+                return LinearProof::from_bytes(v).map_err(serde::de::Error::custom);
+                // In no-std contexts, drop the error message.
+                #[cfg(not(feature = "std"))]
+                return LinearProof::from_bytes(v)
+                    .map_err(|_| serde::de::Error::custom("deserialization error"));
+            }
+        }
+
+        deserializer.deserialize_bytes(LinearProofVisitor)
+    }
+}
+
+/// The generators \\(G\\), \\(F\\) and \\(B\\) of a [`LinearProof`],
+/// together with their digest.
+///
+/// The digest is computed once, when the generators are created, so
+/// that [`LinearProof::create_with_digest`] and
+/// [`LinearProof::verify_with_digest`] bind and check it without
+/// hashing every generator for each proof.
+#[derive(Clone, Debug)]
+pub struct LinearGens {
+    G: Vec<RistrettoPoint>,
+    F: RistrettoPoint,
+    B: RistrettoPoint,
+    digest: GeneratorsDigest,
+}
+
+impl LinearGens {
+    /// Creates the generators of a linear proof over vectors of length
+    /// `G.len()`, with \\(F\\) committing to the value and \\(B\\) to
+    /// the blinding factor, and computes their digest
+    /// [`LinearProof::gens_digest`].
+    pub fn new(G: Vec<RistrettoPoint>, F: RistrettoPoint, B: RistrettoPoint) -> Self {
+        let digest = LinearProof::gens_digest(&G, &F, &B);
+        LinearGens { G, F, B, digest }
+    }
+
+    /// Returns the generators \\(G\\).
+    pub fn G(&self) -> &[RistrettoPoint] {
+        &self.G
+    }
+
+    /// Returns the generator \\(F\\).
+    pub fn F(&self) -> &RistrettoPoint {
+        &self.F
+    }
+
+    /// Returns the generator \\(B\\).
+    pub fn B(&self) -> &RistrettoPoint {
+        &self.B
+    }
+
+    /// Returns the precomputed digest of the generators.
+    pub fn digest(&self) -> &GeneratorsDigest {
+        &self.digest
+    }
+}
+
+/// The verification equation of a [`LinearProof`], computed by
+/// [`LinearProof::verification_scalars`].
+///
+/// The proof is valid if and only if
+/// \\[
+/// b \cdot B + f \cdot F + \sum\_i g\_i \cdot G\_i + \sum\_j d\_j \cdot P\_j = 0,
+/// \\]
+/// where \\(b\\), \\(f\\), \\(g\_i\\) and \\(d\_j\\) are
+/// `B_scalar`, `F_scalar`, `G_scalars` and `dynamic_scalars`, and the
+/// points \\(P\_j\\) are `dynamic_points`.  To combine the proof with
+/// other checks, multiply all scalars by a random weight and add the
+/// terms to a larger multiscalar multiplication.
+#[derive(Clone, Debug)]
+pub struct LinearVerificationScalars {
+    /// The coefficient of the blinding generator \\(B\\).
+    pub B_scalar: Scalar,
+    /// The coefficient of the value generator \\(F\\).
+    pub F_scalar: Scalar,
+    /// The coefficients of the generators \\(G\\).
+    pub G_scalars: Vec<Scalar>,
+    /// The coefficients of `dynamic_points`.
+    pub dynamic_scalars: Vec<Scalar>,
+    /// The proof-specific points: \\(S\\), the commitment \\(C\\),
+    /// then the \\(L\_j\\) and the \\(R\_j\\) of the proof.
+    pub dynamic_points: Vec<RistrettoPoint>,
+}
+
+impl LinearVerificationScalars {
+    /// Checks the verification equation with a single multiscalar
+    /// multiplication.
+    pub fn is_valid(&self, G: &[RistrettoPoint], F: &RistrettoPoint, B: &RistrettoPoint) -> bool {
+        if G.len() < self.G_scalars.len() {
+            return false;
+        }
+        RistrettoPoint::vartime_multiscalar_mul(
+            iter::once(&self.B_scalar)
+                .chain(iter::once(&self.F_scalar))
+                .chain(self.G_scalars.iter())
+                .chain(self.dynamic_scalars.iter()),
+            iter::once(B)
+                .chain(iter::once(F))
+                .chain(G.iter().take(self.G_scalars.len()))
+                .chain(self.dynamic_points.iter()),
+        )
+        .is_identity()
+    }
+}
+
+/// How a [`LinearProof`] binds its generators into the transcript.
+#[derive(Copy, Clone)]
+enum GensBinding<'a> {
+    /// Append every generator.
+    Points,
+    /// Append a digest of all generators.
+    Digest(&'a GeneratorsDigest),
+}
+
+impl GensBinding<'_> {
+    fn append_gens<T: TranscriptBackend>(
+        self,
+        transcript: &mut T,
+        G: &[RistrettoPoint],
+        F: &RistrettoPoint,
+        B: &RistrettoPoint,
+    ) {
+        match self {
+            GensBinding::Points => {
+                for G_i in G {
+                    transcript.append_point(b"G_i", &G_i.compress());
+                }
+                transcript.append_point(b"F", &F.compress());
+                transcript.append_point(b"B", &B.compress());
+            }
+            GensBinding::Digest(digest) => {
+                transcript.append_message(b"gens_digest", digest.as_bytes());
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use merlin::Transcript;
+
     fn test_helper(n: usize) {
         let mut rng = rand::thread_rng();
 
@@ -460,8 +1094,451 @@ mod tests {
         let deserialized_proof = LinearProof::from_bytes(&serialized_proof).unwrap();
         let mut serde_verifier_transcript = Transcript::new(b"linearprooftest");
         assert!(deserialized_proof
-            .verify(&mut serde_verifier_transcript, &C, &G, &F, &B, b)
+            .verify(&mut serde_verifier_transcript, &C, &G, &F, &B, b.clone())
             .is_ok());
+
+        // Test serde serialization and deserialization
+        let serde_proof: LinearProof =
+            bincode::deserialize(&bincode::serialize(&proof).unwrap()).unwrap();
+        assert_eq!(serde_proof.to_bytes(), serialized_proof);
+
+        // Test folding the verification equation into a larger multiscalar
+        // multiplication, together with a check that B - B = 0.
+        let scalars = proof
+            .verification_scalars(&mut Transcript::new(b"linearprooftest"), &C, &G, &F, &B, b)
+            .unwrap();
+        let w = Scalar::random(&mut rng);
+        let check = RistrettoPoint::vartime_multiscalar_mul(
+            iter::once(w * scalars.B_scalar + Scalar::ONE)
+                .chain(iter::once(w * scalars.F_scalar))
+                .chain(scalars.G_scalars.iter().map(|s| w * s))
+                .chain(scalars.dynamic_scalars.iter().map(|s| w * s))
+                .chain(iter::once(-Scalar::ONE)),
+            iter::once(&B)
+                .chain(iter::once(&F))
+                .chain(G.iter())
+                .chain(scalars.dynamic_points.iter())
+                .chain(iter::once(&B)),
+        );
+        assert!(check.is_identity());
+    }
+
+    #[test]
+    fn test_linear_proof_with_digest() {
+        let mut rng = rand::thread_rng();
+        let n = 8;
+
+        use crate::generators::{BulletproofGens, PedersenGens};
+        let bp_gens = BulletproofGens::new(n, 1);
+        let G: Vec<RistrettoPoint> = bp_gens.share(0).G(n).cloned().collect();
+        let pedersen_gens = PedersenGens::default();
+        let F = pedersen_gens.B;
+        let B = pedersen_gens.B_blinding;
+        let gens = LinearGens::new(G.clone(), F, B);
+        let digest = LinearProof::gens_digest(&G, &F, &B);
+        assert_eq!(gens.digest(), &digest);
+
+        let a: Vec<_> = (0..n).map(|_| Scalar::random(&mut rng)).collect();
+        let b: Vec<_> = (0..n).map(|_| Scalar::random(&mut rng)).collect();
+        let r = Scalar::random(&mut rng);
+        let c = inner_product(&a, &b);
+        let C = RistrettoPoint::vartime_multiscalar_mul(
+            a.iter().chain(iter::once(&r)).chain(iter::once(&c)),
+            G.iter().chain(Some(&B)).chain(iter::once(&F)),
+        )
+        .compress();
+
+        let proof = LinearProof::create_with_digest(
+            &mut Transcript::new(b"linearprooftest"),
+            &mut rng,
+            &C,
+            r,
+            a.clone(),
+            b.clone(),
+            &gens,
+        )
+        .unwrap();
+
+        assert!(proof
+            .verify_with_digest(
+                &mut Transcript::new(b"linearprooftest"),
+                &C,
+                &gens,
+                b.clone()
+            )
+            .is_ok());
+
+        // A proof bound by digest does not verify with per-point binding.
+        assert!(proof
+            .verify(
+                &mut Transcript::new(b"linearprooftest"),
+                &C,
+                &G,
+                &F,
+                &B,
+                b.clone()
+            )
+            .is_err());
+
+        // A proof over other generators does not verify, as their digest
+        // is bound instead.
+        let other_gens = LinearGens::new(G, B, F);
+        assert!(proof
+            .verify_with_digest(&mut Transcript::new(b"linearprooftest"), &C, &other_gens, b)
+            .is_err());
+    }
+
+    fn batch_instance(
+        n: usize,
+        G: &[RistrettoPoint],
+        F: &RistrettoPoint,
+        B: &RistrettoPoint,
+    ) -> (LinearProof, CompressedRistretto, Vec<Scalar>) {
+        let mut rng = rand::thread_rng();
+        let G = &G[..n];
+        let a: Vec<_> = (0..n).map(|_| Scalar::random(&mut rng)).collect();
+        let b: Vec<_> = (0..n).map(|_| Scalar::random(&mut rng)).collect();
+        let r = Scalar::random(&mut rng);
+        let c = inner_product(&a, &b);
+        let C = RistrettoPoint::vartime_multiscalar_mul(
+            a.iter().chain(iter::once(&r)).chain(iter::once(&c)),
+            G.iter().chain(Some(B)).chain(iter::once(F)),
+        )
+        .compress();
+
+        let proof = LinearProof::create(
+            &mut Transcript::new(b"linearprooftest"),
+            &mut rng,
+            &C,
+            r,
+            a,
+            b.clone(),
+            G.to_vec(),
+            F,
+            B,
+        )
+        .unwrap();
+        (proof, C, b)
+    }
+
+    #[test]
+    fn test_linear_proof_batch() {
+        use crate::generators::{BulletproofGens, PedersenGens};
+        let bp_gens = BulletproofGens::new(32, 1);
+        let G: Vec<RistrettoPoint> = bp_gens.share(0).G(32).cloned().collect();
+        let pc_gens = PedersenGens::default();
+        let (F, B) = (pc_gens.B, pc_gens.B_blinding);
+
+        let instances: Vec<_> = [1, 8, 32, 16]
+            .iter()
+            .map(|&n| batch_instance(n, &G, &F, &B))
+            .collect();
+        let proofs: Vec<_> = instances.iter().map(|(p, _, _)| p.clone()).collect();
+        let commitments: Vec<_> = instances.iter().map(|(_, C, _)| *C).collect();
+        let b_vecs: Vec<_> = instances.iter().map(|(_, _, b)| b.clone()).collect();
+        let transcripts = || vec![Transcript::new(b"linearprooftest"); 4];
+
+        assert!(LinearProof::verify_batch(
+            &proofs,
+            &mut transcripts(),
+            &commitments,
+            b_vecs.clone(),
+            &G,
+            &F,
+            &B,
+            &mut rand::thread_rng(),
+        )
+        .is_ok());
+
+        // Use the wrong public vector for the third proof.
+        let mut bad_b_vecs = b_vecs.clone();
+        bad_b_vecs[2][0] += Scalar::ONE;
+        assert_eq!(
+            LinearProof::verify_batch(
+                &proofs,
+                &mut transcripts(),
+                &commitments,
+                bad_b_vecs,
+                &G,
+                &F,
+                &B,
+                &mut rand::thread_rng(),
+            ),
+            Err(ProofError::BatchVerificationError { index: 2 })
+        );
+
+        // Use another proof's commitment for the second proof.
+        let mut bad_commitments = commitments.clone();
+        bad_commitments[1] = bad_commitments[3];
+        assert_eq!(
+            LinearProof::verify_batch(
+                &proofs,
+                &mut transcripts(),
+                &bad_commitments,
+                b_vecs.clone(),
+                &G,
+                &F,
+                &B,
+                &mut rand::thread_rng(),
+            ),
+            Err(ProofError::BatchVerificationError { index: 1 })
+        );
+
+        assert_eq!(
+            LinearProof::verify_batch(
+                &proofs,
+                &mut transcripts(),
+                &commitments[..3],
+                b_vecs,
+                &G,
+                &F,
+                &B,
+                &mut rand::thread_rng(),
+            ),
+            Err(ProofError::InvalidInputLength)
+        );
+    }
+
+    #[test]
+    fn test_linear_proof_batch_with_extra_gens() {
+        use crate::generators::{BulletproofGens, PedersenGens};
+        let bp_gens = BulletproofGens::new(64, 1);
+        let G: Vec<RistrettoPoint> = bp_gens.share(0).G(64).cloned().collect();
+        let pc_gens = PedersenGens::default();
+        let (F, B) = (pc_gens.B, pc_gens.B_blinding);
+
+        // The longest public vector is shorter than G.
+        let instances: Vec<_> = [4, 16, 2]
+            .iter()
+            .map(|&n| batch_instance(n, &G, &F, &B))
+            .collect();
+        let proofs: Vec<_> = instances.iter().map(|(p, _, _)| p.clone()).collect();
+        let commitments: Vec<_> = instances.iter().map(|(_, C, _)| *C).collect();
+        let b_vecs: Vec<_> = instances.iter().map(|(_, _, b)| b.clone()).collect();
+
+        assert!(LinearProof::verify_batch(
+            &proofs,
+            &mut vec![Transcript::new(b"linearprooftest"); 3],
+            &commitments,
+            b_vecs,
+            &G,
+            &F,
+            &B,
+            &mut rand::thread_rng(),
+        )
+        .is_ok());
+    }
+
+    fn gens_helper(n: usize, bp_gens: &crate::BulletproofGens) -> Result<(), ProofError> {
+        let mut rng = rand::thread_rng();
+        let pc_gens = PedersenGens::default();
+        let share = bp_gens.share(0);
+
+        let a: Vec<_> = (0..n).map(|_| Scalar::random(&mut rng)).collect();
+        let b: Vec<_> = (0..n).map(|_| Scalar::random(&mut rng)).collect();
+        let r = Scalar::random(&mut rng);
+        let c = inner_product(&a, &b);
+        // C = <a, G> + r * B_blinding + <a, b> * B
+        let C = RistrettoPoint::vartime_multiscalar_mul(
+            a.iter().chain(iter::once(&r)).chain(iter::once(&c)),
+            share
+                .G(n)
+                .chain(iter::once(&pc_gens.B_blinding))
+                .chain(iter::once(&pc_gens.B)),
+        )
+        .compress();
+
+        let proof = LinearProof::create_with_gens(
+            &mut Transcript::new(b"linearprooftest"),
+            &mut rng,
+            &share,
+            &pc_gens,
+            &C,
+            r,
+            a,
+            b.clone(),
+        )?;
+        assert_eq!(
+            proof.L_vec.len(),
+            n.next_power_of_two().trailing_zeros() as usize
+        );
+
+        proof.verify_with_gens(
+            &mut Transcript::new(b"linearprooftest"),
+            &share,
+            &pc_gens,
+            &C,
+            b,
+        )
+    }
+
+    #[test]
+    fn test_linear_proof_with_gens() {
+        use crate::generators::BulletproofGens;
+        let bp_gens = BulletproofGens::new(512, 1);
+        for n in [1, 3, 64, 100, 300] {
+            assert_eq!(gens_helper(n, &bp_gens), Ok(()));
+        }
+        let bound_gens = bp_gens.with_transcript_binding();
+        assert_eq!(gens_helper(100, &bound_gens), Ok(()));
+
+        // 300 pads to 512 generators.
+        let small_gens = BulletproofGens::new(300, 1);
+        assert_eq!(
+            gens_helper(300, &small_gens),
+            Err(ProofError::InvalidGeneratorsLength)
+        );
+        assert_eq!(
+            gens_helper(0, &small_gens),
+            Err(ProofError::InvalidInputLength)
+        );
+    }
+
+    #[test]
+    fn test_linear_proof_multi() {
+        use crate::generators::BulletproofGens;
+        let mut rng = rand::thread_rng();
+        let bp_gens = BulletproofGens::new(128, 1);
+        let share = bp_gens.share(0);
+        let pc_gens = PedersenGens::default();
+        let (n, k) = (100, 3);
+
+        let a: Vec<_> = (0..n).map(|_| Scalar::random(&mut rng)).collect();
+        let a_blinding = Scalar::random(&mut rng);
+        let A = RistrettoPoint::vartime_multiscalar_mul(
+            a.iter().chain(iter::once(&a_blinding)),
+            share.G(n).chain(iter::once(&pc_gens.B_blinding)),
+        )
+        .compress();
+
+        let b_vecs: Vec<Vec<_>> = (0..k)
+            .map(|_| (0..n).map(|_| Scalar::random(&mut rng)).collect())
+            .collect();
+        // The last value is public, and committed with a zero blinding.
+        let value_blindings: Vec<_> = (0..k)
+            .map(|i| {
+                if i == k - 1 {
+                    Scalar::ZERO
+                } else {
+                    Scalar::random(&mut rng)
+                }
+            })
+            .collect();
+        let value_commitments: Vec<_> = b_vecs
+            .iter()
+            .zip(value_blindings.iter())
+            .map(|(b_k, r_k)| pc_gens.commit(inner_product(&a, b_k), *r_k).compress())
+            .collect();
+
+        let proof = LinearProof::create_multi(
+            &mut Transcript::new(b"linearprooftest"),
+            &mut rng,
+            &share,
+            &pc_gens,
+            &A,
+            a_blinding,
+            &value_commitments,
+            &value_blindings,
+            a.clone(),
+            &b_vecs,
+        )
+        .unwrap();
+        assert_eq!(proof.L_vec.len(), 7);
+
+        let verify = |value_commitments: &[CompressedRistretto], b_vecs: &[Vec<Scalar>]| {
+            proof.verify_multi(
+                &mut Transcript::new(b"linearprooftest"),
+                &share,
+                &pc_gens,
+                &A,
+                value_commitments,
+                b_vecs,
+            )
+        };
+        assert_eq!(verify(&value_commitments, &b_vecs), Ok(()));
+
+        // The public vectors are bound before drawing rho, so the
+        // verifier must use the same vectors as the prover.
+        let mut other_b_vecs = b_vecs.clone();
+        other_b_vecs[1][0] += Scalar::ONE;
+        assert_eq!(
+            verify(&value_commitments, &other_b_vecs),
+            Err(ProofError::VerificationError)
+        );
+
+        // A commitment to a different value must fail.
+        let mut bad_commitments = value_commitments.clone();
+        bad_commitments[1] = pc_gens
+            .commit(
+                inner_product(&a, &b_vecs[1]) + Scalar::ONE,
+                value_blindings[1],
+            )
+            .compress();
+        assert_eq!(
+            verify(&bad_commitments, &b_vecs),
+            Err(ProofError::VerificationError)
+        );
+
+        // A false first relation cannot be balanced by hiding the
+        // difference delta * B in the vector commitment.
+        let c_0 = inner_product(&a, &b_vecs[0]);
+        let delta = Scalar::ONE;
+        let forged_A = (A.decompress().unwrap() + delta * pc_gens.B).compress();
+        let mut forged_commitments = value_commitments.clone();
+        forged_commitments[0] = pc_gens.commit(c_0 - delta, value_blindings[0]).compress();
+        let forged_proof = LinearProof::create_multi(
+            &mut Transcript::new(b"linearprooftest"),
+            &mut rng,
+            &share,
+            &pc_gens,
+            &forged_A,
+            a_blinding,
+            &forged_commitments,
+            &value_blindings,
+            a.clone(),
+            &b_vecs,
+        )
+        .unwrap();
+        assert_eq!(
+            forged_proof.verify_multi(
+                &mut Transcript::new(b"linearprooftest"),
+                &share,
+                &pc_gens,
+                &forged_A,
+                &forged_commitments,
+                &b_vecs,
+            ),
+            Err(ProofError::VerificationError)
+        );
+
+        // Dimension mismatches are reported as such.
+        assert_eq!(
+            verify(&value_commitments[..2], &b_vecs),
+            Err(ProofError::InvalidInputLength)
+        );
+        let mut short_b_vecs = b_vecs.clone();
+        short_b_vecs[2].pop();
+        assert_eq!(
+            verify(&value_commitments, &short_b_vecs),
+            Err(ProofError::InvalidInputLength)
+        );
+        assert_eq!(verify(&[], &[]), Err(ProofError::InvalidInputLength));
+        assert_eq!(
+            LinearProof::create_multi(
+                &mut Transcript::new(b"linearprooftest"),
+                &mut rng,
+                &share,
+                &pc_gens,
+                &A,
+                a_blinding,
+                &value_commitments,
+                &value_blindings,
+                a[..n - 1].to_vec(),
+                &b_vecs,
+            )
+            .unwrap_err(),
+            ProofError::InvalidInputLength
+        );
     }
 
     #[test]
@@ -0,0 +1,1021 @@
+#![allow(non_snake_case)]
+
+//! Definition of the circuit description used as a verifier key.
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate rand;
+
+#[cfg(feature = "std")]
+use self::rand::thread_rng;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem;
+use curve25519_dalek::ristretto::CompressedRistretto;
+use curve25519_dalek::scalar::Scalar;
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{ConstraintSystem, LinearCombination, Metrics, R1CSProof, Variable, Verifier};
+use crate::errors::{ProofError, R1CSError};
+use crate::generators::{BulletproofGens, PedersenGens};
+use crate::transcript::{TranscriptBackend, TranscriptProtocol};
+use crate::util;
+
+/// A recorded constraint system, for use as a reusable verifier key.
+///
+/// Verifying an [`R1CSProof`] normally requires running the gadgets
+/// that build the constraint system through a [`Verifier`].  When the
+/// shape of the circuit is the same for every proof, the circuit can
+/// instead be recorded once with [`CircuitDescription::record`], stored
+/// or sent in serialized form, and every proof verified directly
+/// against it with [`CircuitDescription::verify`].
+///
+/// The description holds the number of commitments, the number of
+/// multipliers and the linear constraints of the circuit, together
+/// with the constraints flattened into sparse weight matrices, which
+/// are computed once when the description is recorded or deserialized
+/// and shared by every verification.  It also holds the messages, such
+/// as domain separators, which the gadgets append to the transcript,
+/// and which are appended again when verifying a proof.
+///
+/// # Randomized constraints
+///
+/// The randomized constraints of a circuit are recorded as a template
+/// of its deferred phase: the messages appended to the transcript and
+/// the challenges drawn from it, the number of multipliers, and the
+/// constraints, whose coefficients are kept as polynomials in the
+/// challenges.  Each proof instantiates the template with its own
+/// challenges.
+///
+/// The polynomials are interpolated from several runs of the gadget
+/// with chosen challenges.  A circuit can therefore only be recorded
+/// if its randomized constraints have the same multipliers, terms and
+/// transcript messages for all challenges, and if their coefficients
+/// are polynomials of degree at most
+/// [`CircuitDescription::MAX_CHALLENGE_DEGREE`] in each challenge, as
+/// they are in the shuffle gadgets.  Otherwise
+/// [`CircuitDescription::record`] fails with
+/// [`R1CSError::RandomizedCircuitNotRecordable`], and the circuit must
+/// be verified by running its gadgets through a [`Verifier`].
+///
+/// # Limitations
+///
+/// A description has at most [`CircuitDescription::MAX_MULTIPLIERS`]
+/// multipliers, and its flattened constraints take space proportional
+/// to the terms of its constraints, so that a malformed serialized
+/// description cannot claim an arbitrarily large circuit.
+///
+/// Transcript labels are `'static`, so deserializing a description
+/// leaks the labels of its transcript messages and challenges, except
+/// for the labels used by the gadgets of this crate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CircuitDescription {
+    pub(super) num_commitments: usize,
+    /// The number of multipliers of the first phase.
+    pub(super) num_multipliers: usize,
+    pub(super) constraints: Arc<RecordedConstraints>,
+    /// The messages appended by the gadgets in the first phase.
+    pub(super) operations: Vec<TranscriptOperation>,
+    pub(super) randomized: Option<Arc<RandomizedPhase>>,
+}
+
+impl CircuitDescription {
+    /// The largest number of multipliers of a circuit description.
+    ///
+    /// An [`R1CSProof`] covers fewer than \\(2^{32}\\) padded
+    /// multipliers, so no proof verifies against a larger circuit.
+    pub const MAX_MULTIPLIERS: usize = 1 << 31;
+
+    /// The largest degree in any one challenge of the coefficients of
+    /// the randomized constraints of a circuit description.
+    pub const MAX_CHALLENGE_DEGREE: usize = 64;
+
+    /// Records the circuit built by `gadget` for `num_commitments`
+    /// committed variables.
+    ///
+    /// The `gadget` is run against a [`Verifier`], receiving the
+    /// [`Variable`]s of the commitments, and must add the same
+    /// constraints and append the same transcript messages as it would
+    /// when verifying a proof.  It is run once for a circuit without
+    /// randomized constraints, and several times otherwise, to
+    /// interpolate the coefficients of the randomized constraints.
+    ///
+    /// Returns the error of the `gadget` if it fails, a
+    /// [`R1CSError::GadgetError`] if it commits variables itself, or
+    /// [`R1CSError::RandomizedCircuitNotRecordable`] if its randomized
+    /// constraints cannot be recorded, or if it draws challenges
+    /// outside of them.
+    pub fn record<F>(num_commitments: usize, gadget: F) -> Result<Self, R1CSError>
+    where
+        F: Fn(&mut Verifier<RecordingTranscript>, Vec<Variable>) -> Result<(), R1CSError>,
+    {
+        let run = |challenges| record_run(num_commitments, &gadget, challenges);
+        let reference = run(Vec::new())?;
+        let randomized = match &reference.phase_two {
+            Some(phase_two) => Some(RandomizedPhase::record(&reference, phase_two, run)?),
+            None => None,
+        };
+        let phase_one = reference.phase_one;
+        CircuitDescription::new(
+            num_commitments,
+            phase_one.multipliers,
+            phase_one.constraints,
+            phase_one.operations,
+            randomized,
+        )
+    }
+
+    /// Creates a description from its parts, flattening the
+    /// constraints.
+    ///
+    /// Returns a [`R1CSError::FormatError`] if the circuit has more
+    /// than [`CircuitDescription::MAX_MULTIPLIERS`] multipliers, if a
+    /// constraint refers to a variable the circuit does not have, or if
+    /// a challenge is drawn in the first phase.
+    fn new(
+        num_commitments: usize,
+        num_multipliers: usize,
+        constraints: Vec<LinearCombination>,
+        operations: Vec<TranscriptOperation>,
+        randomized: Option<RandomizedPhase>,
+    ) -> Result<Self, R1CSError> {
+        let all_multipliers = num_multipliers
+            .checked_add(randomized.as_ref().map_or(0, |phase| phase.num_multipliers))
+            .filter(|n| *n <= Self::MAX_MULTIPLIERS)
+            .ok_or(R1CSError::FormatError)?;
+        let early_challenge = operations
+            .iter()
+            .any(|op| matches!(op, TranscriptOperation::Challenge { .. }));
+        let randomized_variables = randomized.iter().all(|phase| {
+            phase
+                .variables()
+                .all(|var| in_circuit(var, num_commitments, all_multipliers))
+        });
+        if early_challenge || !randomized_variables {
+            return Err(R1CSError::FormatError);
+        }
+        let constraints = RecordedConstraints::new(num_commitments, num_multipliers, constraints)
+            .ok_or(R1CSError::FormatError)?;
+        Ok(CircuitDescription {
+            num_commitments,
+            num_multipliers,
+            constraints: Arc::new(constraints),
+            operations,
+            randomized: randomized.map(Arc::new),
+        })
+    }
+
+    /// Returns the number of committed variables of the circuit.
+    pub fn num_commitments(&self) -> usize {
+        self.num_commitments
+    }
+
+    /// Returns the number of multipliers of the circuit, including
+    /// those of its randomized constraints.
+    pub fn num_multipliers(&self) -> usize {
+        self.metrics().multipliers
+    }
+
+    /// Returns the metrics of the recorded constraint system.
+    pub fn metrics(&self) -> Metrics {
+        let (phase_two_multipliers, phase_two_constraints) =
+            self.randomized.as_ref().map_or((0, 0), |phase| {
+                (phase.num_multipliers, phase.constraints.len())
+            });
+        Metrics {
+            multipliers: self.num_multipliers + phase_two_multipliers,
+            constraints: self.constraints.len() + phase_two_constraints,
+            phase_one_constraints: self.constraints.len(),
+            phase_two_constraints,
+            namespaces: Vec::new(),
+        }
+    }
+
+    /// Attempts to verify the supplied `proof` of this circuit for the
+    /// given `commitments`, which take the place of the commitments
+    /// passed to [`Verifier::commit`].
+    ///
+    /// The `transcript` must be in the same state as the one passed to
+    /// [`Verifier::new`] when verifying the proof without the
+    /// description.
+    ///
+    /// This is a convenience wrapper around
+    /// [`CircuitDescription::verify_with_rng`], passing in a threadsafe
+    /// RNG.
+    #[cfg(feature = "std")]
+    pub fn verify<T: TranscriptBackend>(
+        &self,
+        transcript: T,
+        commitments: &[CompressedRistretto],
+        proof: &R1CSProof,
+        pc_gens: &PedersenGens,
+        bp_gens: &BulletproofGens,
+    ) -> Result<(), R1CSError> {
+        self.verify_with_rng(
+            transcript,
+            commitments,
+            proof,
+            pc_gens,
+            bp_gens,
+            &mut thread_rng(),
+        )
+    }
+
+    /// Same as [`CircuitDescription::verify`], but uses `rng` as the
+    /// external randomness for the random weight combining the
+    /// verification checks.
+    pub fn verify_with_rng<T: TranscriptBackend, R: RngCore + CryptoRng>(
+        &self,
+        transcript: T,
+        commitments: &[CompressedRistretto],
+        proof: &R1CSProof,
+        pc_gens: &PedersenGens,
+        bp_gens: &BulletproofGens,
+        rng: &mut R,
+    ) -> Result<(), R1CSError> {
+        self.verifier(transcript, commitments)?
+            .verify_with_rng(proof, pc_gens, bp_gens, rng)
+    }
+
+    /// Returns a [`Verifier`] containing this circuit, with the given
+    /// `commitments` committed to the `transcript`, followed by the
+    /// messages appended by the gadgets.
+    ///
+    /// This can be used to verify proofs of the circuit in a batch with
+    /// [`Verifier::verify_batch`].
+    pub fn verifier<T: TranscriptBackend>(
+        &self,
+        transcript: T,
+        commitments: &[CompressedRistretto],
+    ) -> Result<Verifier<T>, R1CSError> {
+        if commitments.len() != self.num_commitments {
+            return Err(ProofError::InvalidInputLength.into());
+        }
+        Ok(Verifier::from_circuit_description(
+            transcript,
+            commitments,
+            self,
+        ))
+    }
+}
+
+/// Returns whether `var` is a variable of a circuit with the given
+/// numbers of commitments and multipliers.
+fn in_circuit(var: &Variable, num_commitments: usize, num_multipliers: usize) -> bool {
+    match var {
+        Variable::Committed(i) => *i < num_commitments,
+        Variable::MultiplierLeft(i)
+        | Variable::MultiplierRight(i)
+        | Variable::MultiplierOutput(i) => *i < num_multipliers,
+        Variable::One() => true,
+    }
+}
+
+/// The transcript of a [`Verifier`] recording a [`CircuitDescription`].
+///
+/// It records the messages appended to it and the challenges drawn
+/// from it, and answers the challenges of the randomized constraints
+/// with the values chosen by [`CircuitDescription::record`].
+pub struct RecordingTranscript {
+    operations: Vec<TranscriptOperation>,
+    /// The challenges answered so far, followed by those chosen in
+    /// advance.
+    challenges: Vec<Scalar>,
+    num_challenges: usize,
+    /// Set if challenge bytes other than a challenge scalar are drawn.
+    unsupported_challenge: bool,
+    /// The source of the challenges which are not chosen in advance.
+    randomness: Transcript,
+}
+
+impl RecordingTranscript {
+    fn new(challenges: Vec<Scalar>) -> Self {
+        RecordingTranscript {
+            operations: Vec::new(),
+            challenges,
+            num_challenges: 0,
+            unsupported_challenge: false,
+            randomness: Transcript::new(b"RecordingTranscript"),
+        }
+    }
+
+    /// Returns the operations recorded since the last call.
+    pub(super) fn take_operations(&mut self) -> Vec<TranscriptOperation> {
+        mem::take(&mut self.operations)
+    }
+}
+
+impl TranscriptBackend for RecordingTranscript {
+    type RngBuilder = merlin::TranscriptRngBuilder;
+
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        self.operations.push(TranscriptOperation::Message {
+            label,
+            message: message.to_vec(),
+        });
+    }
+
+    fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+        self.operations
+            .push(TranscriptOperation::Challenge { label });
+        dest.iter_mut().for_each(|byte| *byte = 0);
+        // Challenge scalars are reduced from 64 bytes, so a canonical
+        // scalar padded with zeros is answered as itself.
+        if dest.len() != 64 {
+            self.unsupported_challenge = true;
+            return;
+        }
+        if self.num_challenges == self.challenges.len() {
+            let challenge = self.randomness.challenge_scalar(b"challenge");
+            self.challenges.push(challenge);
+        }
+        dest[..32].copy_from_slice(self.challenges[self.num_challenges].as_bytes());
+        self.num_challenges += 1;
+    }
+
+    fn build_rng(&self) -> Self::RngBuilder {
+        self.randomness.build_rng()
+    }
+}
+
+/// A transcript operation of a recorded gadget.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(super) enum TranscriptOperation {
+    /// A message appended to the transcript.
+    Message {
+        label: &'static [u8],
+        message: Vec<u8>,
+    },
+    /// A challenge scalar drawn from the transcript.
+    Challenge { label: &'static [u8] },
+}
+
+/// The multipliers, constraints and transcript operations of one phase
+/// of a recorded gadget.
+#[derive(PartialEq)]
+pub(super) struct RecordedPhase {
+    pub(super) multipliers: usize,
+    pub(super) constraints: Vec<LinearCombination>,
+    pub(super) operations: Vec<TranscriptOperation>,
+}
+
+/// A run of a gadget being recorded.
+pub(super) struct RecordedRun {
+    pub(super) num_commitments: usize,
+    pub(super) phase_one: RecordedPhase,
+    pub(super) phase_two: Option<RecordedPhase>,
+    pub(super) transcript: RecordingTranscript,
+}
+
+/// Runs the `gadget` of a circuit being recorded, answering the
+/// challenges of its randomized constraints with `challenges` as long
+/// as there are enough of them.
+fn record_run<F>(
+    num_commitments: usize,
+    gadget: &F,
+    challenges: Vec<Scalar>,
+) -> Result<RecordedRun, R1CSError>
+where
+    F: Fn(&mut Verifier<RecordingTranscript>, Vec<Variable>) -> Result<(), R1CSError>,
+{
+    // The commitments are placeholders, and only the transcript
+    // operations of the gadget are recorded.
+    let mut verifier = Verifier::new(RecordingTranscript::new(challenges));
+    let vars = (0..num_commitments)
+        .map(|_| verifier.commit(CompressedRistretto::default()))
+        .collect();
+    verifier.transcript().take_operations();
+    gadget(&mut verifier, vars)?;
+
+    let run = verifier.into_recorded_run()?;
+    if run.num_commitments != num_commitments {
+        return Err(R1CSError::GadgetError {
+            description: "a recorded gadget must not commit variables".into(),
+        });
+    }
+    let early_challenge = run
+        .phase_one
+        .operations
+        .iter()
+        .any(|op| matches!(op, TranscriptOperation::Challenge { .. }));
+    if early_challenge || run.transcript.unsupported_challenge {
+        return Err(R1CSError::RandomizedCircuitNotRecordable);
+    }
+    Ok(run)
+}
+
+impl RecordedRun {
+    /// Returns the challenges answered in the run.
+    fn challenges(&self) -> &[Scalar] {
+        &self.transcript.challenges[..self.transcript.num_challenges]
+    }
+
+    /// Returns the coefficients of the randomized constraints, in the
+    /// order of their terms, if the run has the same multipliers, terms
+    /// and transcript operations as the `reference` run.
+    fn coefficients(&self, reference: &RecordedRun) -> Option<Vec<Scalar>> {
+        let phase_two = self.phase_two.as_ref()?;
+        let reference_two = reference.phase_two.as_ref()?;
+        let same_terms = |lc: &LinearCombination, reference: &LinearCombination| {
+            lc.terms.len() == reference.terms.len()
+                && lc
+                    .terms
+                    .iter()
+                    .zip(&reference.terms)
+                    .all(|(t, r)| t.0 == r.0)
+        };
+        let same_shape = self.phase_one == reference.phase_one
+            && phase_two.multipliers == reference_two.multipliers
+            && phase_two.operations == reference_two.operations
+            && phase_two.constraints.len() == reference_two.constraints.len()
+            && phase_two
+                .constraints
+                .iter()
+                .zip(&reference_two.constraints)
+                .all(|(lc, reference)| same_terms(lc, reference));
+        if !same_shape {
+            return None;
+        }
+        Some(
+            phase_two
+                .constraints
+                .iter()
+                .flat_map(|lc| lc.terms.iter().map(|(_, coeff)| *coeff))
+                .collect(),
+        )
+    }
+}
+
+/// A polynomial in the challenges of a randomized phase, as a list of
+/// monomials, each of which lists the indices of its challenges with
+/// their exponents, and its coefficient.
+type Polynomial = Vec<(Vec<(usize, usize)>, Scalar)>;
+
+/// The template of the randomized constraints of a recorded circuit.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(super) struct RandomizedPhase {
+    /// The messages appended to the transcript and the challenges
+    /// drawn from it, in order.
+    operations: Vec<TranscriptOperation>,
+    num_multipliers: usize,
+    /// The terms of the constraints, with coefficients which are
+    /// polynomials in the challenges.
+    constraints: Vec<Vec<(Variable, Polynomial)>>,
+    /// The largest exponent of each challenge.
+    #[serde(skip)]
+    degrees: Vec<usize>,
+}
+
+impl RandomizedPhase {
+    /// Creates a template from its parts, or returns `None` if a
+    /// monomial refers to a missing challenge or has an exponent above
+    /// [`CircuitDescription::MAX_CHALLENGE_DEGREE`].
+    fn new(
+        operations: Vec<TranscriptOperation>,
+        num_multipliers: usize,
+        constraints: Vec<Vec<(Variable, Polynomial)>>,
+    ) -> Option<Self> {
+        let num_challenges = operations
+            .iter()
+            .filter(|op| matches!(op, TranscriptOperation::Challenge { .. }))
+            .count();
+        let mut degrees = vec![0; num_challenges];
+        let exponents = constraints
+            .iter()
+            .flatten()
+            .flat_map(|(_, polynomial)| polynomial.iter())
+            .flat_map(|(monomial, _)| monomial.iter());
+        for (j, e) in exponents {
+            if *e > CircuitDescription::MAX_CHALLENGE_DEGREE {
+                return None;
+            }
+            let degree = degrees.get_mut(*j)?;
+            *degree = (*degree).max(*e);
+        }
+        Some(RandomizedPhase {
+            operations,
+            num_multipliers,
+            constraints,
+            degrees,
+        })
+    }
+
+    /// Records the randomized phase `phase_two` of the `reference` run
+    /// of a gadget, interpolating the coefficients of its constraints
+    /// from further runs at chosen challenges.
+    ///
+    /// The coefficients are first interpolated along each challenge,
+    /// with the other challenges fixed, doubling the degree until the
+    /// interpolation matches a run at a random challenge.  Coefficients
+    /// depending on several challenges are then interpolated on a grid
+    /// of those challenges, and the whole template is checked against a
+    /// run at random challenges.
+    fn record<F>(
+        reference: &RecordedRun,
+        phase_two: &RecordedPhase,
+        run: F,
+    ) -> Result<Self, R1CSError>
+    where
+        F: Fn(Vec<Scalar>) -> Result<RecordedRun, R1CSError>,
+    {
+        let coefficients = |challenges| {
+            run(challenges)?
+                .coefficients(reference)
+                .ok_or(R1CSError::RandomizedCircuitNotRecordable)
+        };
+        let base = reference.challenges().to_vec();
+        let base_coefficients = reference
+            .coefficients(reference)
+            .ok_or(R1CSError::RandomizedCircuitNotRecordable)?;
+        let num_terms = base_coefficients.len();
+        let mut randomness = Transcript::new(b"CircuitDescription interpolation");
+
+        // Interpolate the coefficients along each challenge.
+        let mut univariate = Vec::with_capacity(base.len());
+        for j in 0..base.len() {
+            let at = |challenge| {
+                let mut challenges = base.clone();
+                challenges[j] = challenge;
+                challenges
+            };
+            let mut nodes: Vec<Vec<Scalar>> = Vec::new();
+            let mut degree = 0;
+            let interpolated = loop {
+                while nodes.len() <= degree {
+                    nodes.push(coefficients(at(Scalar::from(nodes.len() as u64)))?);
+                }
+                let interpolated: Vec<Vec<Scalar>> = (0..num_terms)
+                    .map(|t| {
+                        let mut values: Vec<Scalar> = nodes.iter().map(|node| node[t]).collect();
+                        interpolate_grid(&mut values, &[degree]);
+                        values
+                    })
+                    .collect();
+                let x = randomness.challenge_scalar(b"x");
+                let expected = coefficients(at(x))?;
+                let matches = interpolated
+                    .iter()
+                    .zip(&expected)
+                    .all(|(polynomial, value)| evaluate_univariate(polynomial, x) == *value);
+                if matches {
+                    break interpolated;
+                }
+                if degree == CircuitDescription::MAX_CHALLENGE_DEGREE {
+                    return Err(R1CSError::RandomizedCircuitNotRecordable);
+                }
+                degree = (2 * degree).clamp(1, CircuitDescription::MAX_CHALLENGE_DEGREE);
+            };
+            univariate.push(interpolated);
+        }
+
+        // The challenges on which each coefficient depends, with its
+        // degree in each of them.
+        let dependencies: Vec<Vec<(usize, usize)>> = (0..num_terms)
+            .map(|t| {
+                univariate
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(j, interpolated)| {
+                        let degree = interpolated[t]
+                            .iter()
+                            .rposition(|c| *c != Scalar::ZERO)
+                            .filter(|degree| *degree > 0)?;
+                        Some((j, degree))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut polynomials = vec![Polynomial::new(); num_terms];
+        let mut groups: Vec<(Vec<usize>, Vec<usize>)> = Vec::new();
+        for (t, dependency) in dependencies.iter().enumerate() {
+            match dependency.as_slice() {
+                [] => polynomials[t] = monomials(&base_coefficients[t..=t], &[], &[]),
+                [(j, degree)] => {
+                    polynomials[t] = monomials(&univariate[*j][t][..=*degree], &[*j], &[*degree])
+                }
+                _ => {
+                    let challenges: Vec<usize> = dependency.iter().map(|(j, _)| *j).collect();
+                    match groups.iter_mut().find(|(group, _)| *group == challenges) {
+                        Some((_, terms)) => terms.push(t),
+                        None => groups.push((challenges, vec![t])),
+                    }
+                }
+            }
+        }
+
+        // Interpolate the coefficients depending on several challenges
+        // on a grid of those challenges.
+        for (challenges, terms) in groups {
+            let degrees: Vec<usize> = challenges
+                .iter()
+                .map(|j| {
+                    terms
+                        .iter()
+                        .flat_map(|t| dependencies[*t].iter())
+                        .filter(|(k, _)| k == j)
+                        .map(|(_, degree)| *degree)
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect();
+            let size = degrees.iter().map(|degree| degree + 1).product();
+            let grid = (0..size)
+                .map(|index| {
+                    let mut point = base.clone();
+                    let mut rest = index;
+                    for (j, degree) in challenges.iter().zip(&degrees) {
+                        point[*j] = Scalar::from((rest % (degree + 1)) as u64);
+                        rest /= degree + 1;
+                    }
+                    coefficients(point)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            for t in terms {
+                let mut values: Vec<Scalar> = grid.iter().map(|node| node[t]).collect();
+                interpolate_grid(&mut values, &degrees);
+                polynomials[t] = monomials(&values, &challenges, &degrees);
+            }
+        }
+
+        let mut polynomials = polynomials.into_iter();
+        let constraints = phase_two
+            .constraints
+            .iter()
+            .map(|lc| {
+                lc.terms
+                    .iter()
+                    .zip(polynomials.by_ref())
+                    .map(|((var, _), polynomial)| (*var, polynomial))
+                    .collect()
+            })
+            .collect();
+        let phase = RandomizedPhase::new(
+            phase_two.operations.clone(),
+            phase_two.multipliers,
+            constraints,
+        )
+        .ok_or(R1CSError::RandomizedCircuitNotRecordable)?;
+
+        // Check the template against a run at random challenges.
+        let challenges: Vec<Scalar> = (0..base.len())
+            .map(|_| randomness.challenge_scalar(b"x"))
+            .collect();
+        let expected = coefficients(challenges.clone())?;
+        let instantiated = phase
+            .instantiate(&challenges)
+            .into_iter()
+            .flat_map(|lc| lc.terms.into_iter().map(|(_, coeff)| coeff));
+        if !instantiated.eq(expected) {
+            return Err(R1CSError::RandomizedCircuitNotRecordable);
+        }
+        Ok(phase)
+    }
+
+    /// Returns the transcript operations of the randomized phase.
+    pub(super) fn operations(&self) -> &[TranscriptOperation] {
+        &self.operations
+    }
+
+    /// Returns the number of multipliers of the randomized phase.
+    pub(super) fn num_multipliers(&self) -> usize {
+        self.num_multipliers
+    }
+
+    /// Returns the variables of the randomized constraints.
+    fn variables(&self) -> impl Iterator<Item = &Variable> {
+        self.constraints.iter().flatten().map(|(var, _)| var)
+    }
+
+    /// Returns the randomized constraints for the given values of the
+    /// challenges.
+    pub(super) fn instantiate(&self, challenges: &[Scalar]) -> Vec<LinearCombination> {
+        let powers: Vec<Vec<Scalar>> = challenges
+            .iter()
+            .zip(&self.degrees)
+            .map(|(challenge, degree)| util::exp_iter(*challenge).take(degree + 1).collect())
+            .collect();
+        let evaluate = |polynomial: &Polynomial| -> Scalar {
+            polynomial
+                .iter()
+                .map(|(monomial, coeff)| {
+                    monomial
+                        .iter()
+                        .fold(*coeff, |acc, (j, e)| acc * powers[*j][*e])
+                })
+                .sum()
+        };
+        self.constraints
+            .iter()
+            .map(|terms| {
+                terms
+                    .iter()
+                    .map(|(var, polynomial)| (*var, evaluate(polynomial)))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Returns the coefficients of the Lagrange basis polynomials of the
+/// nodes `0..=degree`: the entry `[i][e]` is the coefficient of
+/// \\(x^e\\) in the polynomial which is one at `i` and zero at the
+/// other nodes.
+fn lagrange_basis(degree: usize) -> Vec<Vec<Scalar>> {
+    let node = |i: usize| Scalar::from(i as u64);
+    // The product of (x - m) over all nodes m.
+    let mut product = vec![Scalar::ONE];
+    for m in 0..=degree {
+        let mut next = vec![Scalar::ZERO; product.len() + 1];
+        for (e, coeff) in product.iter().enumerate() {
+            next[e + 1] += coeff;
+            next[e] -= node(m) * coeff;
+        }
+        product = next;
+    }
+    (0..=degree)
+        .map(|i| {
+            // Divide the product by (x - i), and normalize the quotient
+            // to be one at i.
+            let mut quotient = vec![Scalar::ZERO; degree + 1];
+            quotient[degree] = product[degree + 1];
+            for e in (0..degree).rev() {
+                quotient[e] = product[e + 1] + node(i) * quotient[e + 1];
+            }
+            let denominator: Scalar = (0..=degree)
+                .filter(|m| *m != i)
+                .map(|m| node(i) - node(m))
+                .product();
+            let scale = denominator.invert();
+            quotient.iter().map(|coeff| coeff * scale).collect()
+        })
+        .collect()
+}
+
+/// Replaces the `values` of a polynomial on the grid of the nodes
+/// `0..=degrees[a]` along each axis `a`, indexed with the first axis
+/// varying fastest, by its coefficients, indexed in the same way by
+/// the exponents along each axis.
+fn interpolate_grid(values: &mut [Scalar], degrees: &[usize]) {
+    let mut stride = 1;
+    for degree in degrees {
+        let basis = lagrange_basis(*degree);
+        let len = degree + 1;
+        for start in (0..values.len()).filter(|index| (index / stride) % len == 0) {
+            let fiber: Vec<Scalar> = (0..len).map(|i| values[start + i * stride]).collect();
+            for e in 0..len {
+                values[start + e * stride] = fiber
+                    .iter()
+                    .zip(&basis)
+                    .map(|(value, polynomial)| value * polynomial[e])
+                    .sum();
+            }
+        }
+        stride *= len;
+    }
+}
+
+/// Evaluates the polynomial with the given `coefficients` at `x`.
+fn evaluate_univariate(coefficients: &[Scalar], x: Scalar) -> Scalar {
+    coefficients
+        .iter()
+        .rev()
+        .fold(Scalar::ZERO, |acc, coeff| acc * x + coeff)
+}
+
+/// Lists the nonzero `coefficients` of a polynomial in the
+/// `challenges`, indexed as by [`interpolate_grid`], as monomials.
+fn monomials(coefficients: &[Scalar], challenges: &[usize], degrees: &[usize]) -> Polynomial {
+    coefficients
+        .iter()
+        .enumerate()
+        .filter(|(_, coeff)| **coeff != Scalar::ZERO)
+        .map(|(index, coeff)| {
+            let mut rest = index;
+            let monomial = challenges
+                .iter()
+                .zip(degrees)
+                .filter_map(|(j, degree)| {
+                    let e = rest % (degree + 1);
+                    rest /= degree + 1;
+                    Some((*j, e)).filter(|_| e > 0)
+                })
+                .collect();
+            (monomial, *coeff)
+        })
+        .collect()
+}
+
+/// Returns a `'static` label equal to a deserialized transcript
+/// `label`.  The labels used by the gadgets of this crate are returned
+/// as they are, and other labels are leaked.
+fn static_label(label: Vec<u8>) -> &'static [u8] {
+    const GADGET_LABELS: &[&[u8]] = &[
+        b"dom-sep",
+        b"k",
+        b"width",
+        b"m",
+        b"n",
+        b"fee",
+        b"bitsize",
+        b"shuffle challenge",
+        b"shuffle tuple challenge",
+    ];
+    match GADGET_LABELS
+        .iter()
+        .find(|known| **known == label.as_slice())
+    {
+        Some(known) => known,
+        None => Box::leak(label.into_boxed_slice()),
+    }
+}
+
+/// The serialized form of a [`CircuitDescription`], without the
+/// flattened constraints, which are recomputed on deserialization.
+#[derive(Serialize)]
+struct SerializedCircuit<'a> {
+    num_commitments: usize,
+    num_multipliers: usize,
+    constraints: &'a [LinearCombination],
+    operations: &'a [TranscriptOperation],
+    randomized: Option<&'a RandomizedPhase>,
+}
+
+#[derive(Deserialize)]
+struct DeserializedCircuit {
+    num_commitments: usize,
+    num_multipliers: usize,
+    constraints: Vec<LinearCombination>,
+    operations: Vec<DeserializedOperation>,
+    randomized: Option<DeserializedRandomizedPhase>,
+}
+
+#[derive(Deserialize)]
+enum DeserializedOperation {
+    Message { label: Vec<u8>, message: Vec<u8> },
+    Challenge { label: Vec<u8> },
+}
+
+#[derive(Deserialize)]
+struct DeserializedRandomizedPhase {
+    operations: Vec<DeserializedOperation>,
+    num_multipliers: usize,
+    constraints: Vec<Vec<(Variable, Polynomial)>>,
+}
+
+impl From<DeserializedOperation> for TranscriptOperation {
+    fn from(op: DeserializedOperation) -> Self {
+        match op {
+            DeserializedOperation::Message { label, message } => TranscriptOperation::Message {
+                label: static_label(label),
+                message,
+            },
+            DeserializedOperation::Challenge { label } => TranscriptOperation::Challenge {
+                label: static_label(label),
+            },
+        }
+    }
+}
+
+impl Serialize for CircuitDescription {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        SerializedCircuit {
+            num_commitments: self.num_commitments,
+            num_multipliers: self.num_multipliers,
+            constraints: &self.constraints.constraints,
+            operations: &self.operations,
+            randomized: self.randomized.as_deref(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CircuitDescription {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let circuit = DeserializedCircuit::deserialize(deserializer)?;
+        // A deserialized description may claim too many multipliers, or
+        // refer to variables or challenges it does not have.
+        if circuit.num_multipliers > CircuitDescription::MAX_MULTIPLIERS {
+            return Err(serde::de::Error::custom("too many multipliers"));
+        }
+        let randomized = match circuit.randomized {
+            Some(phase) => Some(
+                RandomizedPhase::new(
+                    phase.operations.into_iter().map(Into::into).collect(),
+                    phase.num_multipliers,
+                    phase.constraints,
+                )
+                .ok_or_else(|| serde::de::Error::custom("invalid randomized constraints"))?,
+            ),
+            None => None,
+        };
+        CircuitDescription::new(
+            circuit.num_commitments,
+            circuit.num_multipliers,
+            circuit.constraints,
+            circuit.operations.into_iter().map(Into::into).collect(),
+            randomized,
+        )
+        .map_err(|_| serde::de::Error::custom("invalid circuit description"))
+    }
+}
+
+/// The constraints of a recorded circuit, together with their
+/// flattening into the sparse weight matrices \\(W\_L\\), \\(W\_R\\),
+/// \\(W\_O\\) and \\(W\_V\\) and the constant vector \\(c\\).
+///
+/// Each matrix lists its nonzero entries as the index of the variable
+/// and of the constraint using it, with the summed coefficients of the
+/// variable in that constraint, so that flattening the constraints for
+/// a proof only weights the precomputed entries by powers of the
+/// challenge \\(z\\).  The entries take space proportional to the
+/// terms of the constraints, and not to the number of variables.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(super) struct RecordedConstraints {
+    pub(super) constraints: Vec<LinearCombination>,
+    wL: Vec<(usize, usize, Scalar)>,
+    wR: Vec<(usize, usize, Scalar)>,
+    wO: Vec<(usize, usize, Scalar)>,
+    wV: Vec<(usize, usize, Scalar)>,
+    wc: Vec<(usize, usize, Scalar)>,
+}
+
+impl RecordedConstraints {
+    /// Flattens the `constraints`, or returns `None` if one of them
+    /// refers to a variable outside of the circuit.
+    fn new(
+        num_commitments: usize,
+        num_multipliers: usize,
+        constraints: Vec<LinearCombination>,
+    ) -> Option<Self> {
+        let mut weights = RecordedConstraints::default();
+        for (q, lc) in constraints.iter().enumerate() {
+            for (var, coeff) in &lc.terms {
+                let (entries, i, len) = match var {
+                    Variable::MultiplierLeft(i) => (&mut weights.wL, *i, num_multipliers),
+                    Variable::MultiplierRight(i) => (&mut weights.wR, *i, num_multipliers),
+                    Variable::MultiplierOutput(i) => (&mut weights.wO, *i, num_multipliers),
+                    Variable::Committed(i) => (&mut weights.wV, *i, num_commitments),
+                    Variable::One() => (&mut weights.wc, 0, 1),
+                };
+                if i >= len {
+                    return None;
+                }
+                // Merge repeated terms of the same constraint.
+                match entries.last_mut() {
+                    Some((last_i, last_q, sum)) if (*last_i, *last_q) == (i, q) => *sum += coeff,
+                    _ => entries.push((i, q, *coeff)),
+                }
+            }
+        }
+        weights.constraints = constraints;
+        Some(weights)
+    }
+
+    /// Returns the number of recorded constraints.
+    pub(super) fn len(&self) -> usize {
+        self.constraints.len()
+    }
+
+    /// Adds the weights of the constraints to the flattened vectors,
+    /// where `z_powers[q]` is the power of \\(z\\) weighting the
+    /// constraint `q`.
+    ///
+    /// As in the flattening of the verifier's own constraints, the
+    /// committed and constant terms are subtracted.
+    pub(super) fn add_to(
+        &self,
+        z_powers: &[Scalar],
+        wL: &mut [Scalar],
+        wR: &mut [Scalar],
+        wO: &mut [Scalar],
+        wV: &mut [Scalar],
+        wc: &mut Scalar,
+    ) {
+        for (i, q, coeff) in &self.wL {
+            wL[*i] += z_powers[*q] * coeff;
+        }
+        for (i, q, coeff) in &self.wR {
+            wR[*i] += z_powers[*q] * coeff;
+        }
+        for (i, q, coeff) in &self.wO {
+            wO[*i] += z_powers[*q] * coeff;
+        }
+        for (i, q, coeff) in &self.wV {
+            wV[*i] -= z_powers[*q] * coeff;
+        }
+        for (_, q, coeff) in &self.wc {
+            *wc -= z_powers[*q] * coeff;
+        }
+    }
+}
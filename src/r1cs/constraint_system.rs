@@ -1,8 +1,11 @@
 //! Definition of the constraint system trait.
 
+extern crate alloc;
+
 use super::{LinearCombination, R1CSError, Variable};
+use crate::transcript::TranscriptBackend;
+use alloc::string::String;
 use curve25519_dalek::scalar::Scalar;
-use merlin::Transcript;
 
 /// The interface for a constraint system, abstracting over the prover
 /// and verifier's roles.
@@ -17,10 +20,13 @@ use merlin::Transcript;
 /// using the `ConstraintSystem` trait, so that the prover and
 /// verifier share the logic for specifying constraints.
 pub trait ConstraintSystem {
+    /// The type of the proof transcript.
+    type Transcript: TranscriptBackend;
+
     /// Leases the proof transcript to the user, so they can
     /// add extra data to which the proof must be bound, but which
     /// is not available before creation of the constraint system.
-    fn transcript(&mut self) -> &mut Transcript;
+    fn transcript(&mut self) -> &mut Self::Transcript;
 
     /// Allocate and constrain multiplication variables.
     ///
@@ -66,7 +72,8 @@ pub trait ConstraintSystem {
         input_assignments: Option<(Scalar, Scalar)>,
     ) -> Result<(Variable, Variable, Variable), R1CSError>;
 
-    /// Counts the amount of constraints in the constraint system.
+    /// Counts the amount of constraints in the constraint system, in
+    /// total and per namespace.
     fn metrics(&self) -> crate::r1cs::Metrics;
 
     /// Enforce the explicit constraint that
@@ -74,6 +81,51 @@ pub trait ConstraintSystem {
     /// lc = 0
     /// ```
     fn constrain(&mut self, lc: LinearCombination);
+
+    /// Enter a namespace, which contains the multipliers and
+    /// constraints added until the matching call to
+    /// [`ConstraintSystem::pop_namespace`].
+    ///
+    /// Namespaces nest, and are reported by
+    /// [`ConstraintSystem::metrics`] and in diagnostics such as
+    /// [`Prover::check_satisfied`](crate::r1cs::Prover::check_satisfied).
+    /// The name is only computed by constraint systems which record
+    /// namespaces, so it costs nothing to name namespaces in gadgets.
+    ///
+    /// The default implementation ignores namespaces.
+    fn push_namespace<N, NR>(&mut self, name_fn: N)
+    where
+        N: FnOnce() -> NR,
+        NR: Into<String>,
+    {
+        let _ = name_fn;
+    }
+
+    /// Leave the namespace entered by the last call to
+    /// [`ConstraintSystem::push_namespace`].
+    fn pop_namespace(&mut self) {}
+
+    /// Call `f` inside a namespace named by `name_fn`, like
+    /// [`ConstraintSystem::push_namespace`] followed by `f` and
+    /// [`ConstraintSystem::pop_namespace`].
+    ///
+    /// ### Usage
+    ///
+    /// ```text
+    /// cs.namespace(|| "range check", |cs| range_proof(cs, v, value, 64))?;
+    /// ```
+    fn namespace<N, NR, F, R>(&mut self, name_fn: N, f: F) -> R
+    where
+        Self: Sized,
+        N: FnOnce() -> NR,
+        NR: Into<String>,
+        F: FnOnce(&mut Self) -> R,
+    {
+        self.push_namespace(name_fn);
+        let result = f(self);
+        self.pop_namespace();
+        result
+    }
 }
 
 /// An extension to the constraint system trait that permits randomized constraints.
@@ -81,6 +133,10 @@ pub trait ConstraintSystem {
 /// while gadgets that need randomization should use trait bound `CS: RandomizedConstraintSystem`.
 /// Gadgets generally _should not_ use this trait as a bound on the CS argument: it should be used
 /// by the higher-order protocol that composes gadgets together.
+///
+/// Randomized constraints are not part of the stable `r1cs` API, and
+/// this trait is only exported with the `yoloproofs` feature.
+#[cfg_attr(not(feature = "yoloproofs"), allow(dead_code))]
 pub trait RandomizableConstraintSystem: ConstraintSystem {
     /// Represents a concrete type for the CS in a randomization phase.
     type RandomizedCS: RandomizedConstraintSystem;
@@ -114,6 +170,9 @@ pub trait RandomizableConstraintSystem: ConstraintSystem {
 ///
 /// Note: this trait also includes `ConstraintSystem` trait
 /// in order to allow composition of gadgets: e.g. a shuffle gadget can be used in both phases.
+///
+/// This trait is only exported with the `yoloproofs` feature.
+#[cfg_attr(not(feature = "yoloproofs"), allow(dead_code))]
 pub trait RandomizedConstraintSystem: ConstraintSystem {
     /// Generates a challenge scalar.
     ///
@@ -0,0 +1,399 @@
+//! Export and import of constraint systems in a plain-text format.
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use curve25519_dalek::scalar::Scalar;
+
+use super::{ConstraintSystem, LinearCombination, Metrics, Variable};
+use crate::errors::R1CSError;
+
+/// A constraint system recorded from a [`Verifier`](crate::r1cs::Verifier)
+/// with [`Verifier::export`](crate::r1cs::Verifier::export), for
+/// inspecting, diffing and sharing circuits.
+///
+/// The circuit is written with [`ExportedCircuit::to_text`] and read
+/// back with [`ExportedCircuit::from_text`].  A circuit without
+/// randomized constraints can be replayed into a
+/// [`Prover`](crate::r1cs::Prover) or a [`Verifier`](crate::r1cs::Verifier)
+/// with [`ExportedCircuit::replay`].
+///
+/// # Format
+///
+/// The text format is line-based.  Blank lines and lines holding only
+/// a `#` comment are ignored, and everything after a `#` on any other
+/// line is its label.
+///
+/// ```text
+/// r1cs-circuit v1
+/// commitments 5
+/// phase 1
+/// multiplier 0 # outer/example
+/// constraint 1*V0 1*V1 -1*L0 # outer/example
+/// constraint 1*V2 1*V3 -1*R0 # outer/example
+/// constraint 1*V4 10*1 -1*O0 # outer/example
+/// ```
+///
+/// * The header `r1cs-circuit v1` is followed by the number of
+///   committed variables.
+/// * Each phase starts with `phase 1` or `phase 2`.  The second phase
+///   is present only if the circuit has randomized constraints, and
+///   starts with the challenges drawn while exporting it, as
+///   `challenge <scalar> # <label>` lines.
+/// * A `multiplier <index>` line allocates a multiplier, labelled with
+///   its namespace.  Multipliers are numbered consecutively across both
+///   phases.
+/// * A `constraint` line constrains the sum of its terms to zero,
+///   labelled with its namespace.  Each term is a coefficient and a
+///   variable, separated by `*`.  The variables are `V<i>` for the
+///   committed variables, `L<i>`, `R<i>` and `O<i>` for the left input,
+///   right input and output of multiplier `i`, and `1` for the constant
+///   one.  Coefficients below \\(2\^{64}\\) and their negations are
+///   written in decimal, and other scalars as `0x` followed by their
+///   32-byte little-endian encoding in hex.
+///
+/// The constraints of the second phase are instantiated with the
+/// recorded challenges, so they describe one run of the circuit, not
+/// the circuit itself.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportedCircuit {
+    pub(super) num_commitments: usize,
+    pub(super) phases: Vec<ExportedPhase>,
+}
+
+/// The multipliers and constraints added in one phase of a constraint
+/// system, with their labels.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(super) struct ExportedPhase {
+    pub(super) challenges: Vec<(String, Scalar)>,
+    pub(super) multipliers: Vec<Option<String>>,
+    pub(super) constraints: Vec<(LinearCombination, Option<String>)>,
+}
+
+const HEADER: &str = "r1cs-circuit v1";
+
+impl ExportedCircuit {
+    /// Returns the number of committed variables of the circuit.
+    pub fn num_commitments(&self) -> usize {
+        self.num_commitments
+    }
+
+    /// Returns whether the circuit has randomized constraints.
+    pub fn is_randomized(&self) -> bool {
+        self.phases.len() > 1
+    }
+
+    /// Returns the metrics of the exported constraint system, without
+    /// the namespaces.
+    pub fn metrics(&self) -> Metrics {
+        let phase_constraints = |i: usize| self.phases.get(i).map_or(0, |p| p.constraints.len());
+        Metrics {
+            multipliers: self.phases.iter().map(|p| p.multipliers.len()).sum(),
+            constraints: phase_constraints(0) + phase_constraints(1),
+            phase_one_constraints: phase_constraints(0),
+            phase_two_constraints: phase_constraints(1),
+            namespaces: Vec::new(),
+        }
+    }
+
+    /// Replays the circuit into the constraint system `cs`, which must
+    /// already hold the committed variables of the circuit.
+    ///
+    /// The multipliers of the circuit are allocated after any
+    /// multipliers `cs` already has, and the constraints are rewired to
+    /// the allocated multipliers, so a circuit can be replayed next to
+    /// other gadgets.
+    ///
+    /// The `assignments` give the left and right inputs of each
+    /// multiplier, and are required when replaying into a
+    /// [`Prover`](crate::r1cs::Prover).  The labels are replayed as
+    /// namespaces.
+    ///
+    /// Fails if the circuit has randomized constraints, because those
+    /// must depend on the challenges of each proof instead of the
+    /// recorded ones.
+    pub fn replay<CS: ConstraintSystem>(
+        &self,
+        cs: &mut CS,
+        assignments: Option<&[(Scalar, Scalar)]>,
+    ) -> Result<(), R1CSError> {
+        if self.is_randomized() {
+            return Err(R1CSError::GadgetError {
+                description: "randomized constraints cannot be replayed".into(),
+            });
+        }
+        let phase = &self.phases[0];
+        if let Some(assignments) = assignments {
+            if assignments.len() != phase.multipliers.len() {
+                return Err(R1CSError::GadgetError {
+                    description: "wrong number of multiplier assignments".into(),
+                });
+            }
+        }
+
+        let multipliers = phase
+            .multipliers
+            .iter()
+            .enumerate()
+            .map(|(i, label)| {
+                let assignment = assignments.map(|a| a[i]);
+                labelled(cs, label, |cs| cs.allocate_multiplier(assignment))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for (lc, label) in phase.constraints.iter() {
+            // The recorded multiplier indices are relative to the circuit.
+            let lc = lc
+                .terms
+                .iter()
+                .map(|(var, coeff)| {
+                    let var = match *var {
+                        Variable::MultiplierLeft(i) => multipliers[i].0,
+                        Variable::MultiplierRight(i) => multipliers[i].1,
+                        Variable::MultiplierOutput(i) => multipliers[i].2,
+                        var => var,
+                    };
+                    (var, *coeff)
+                })
+                .collect();
+            labelled(cs, label, |cs| cs.constrain(lc));
+        }
+        Ok(())
+    }
+
+    /// Writes the circuit in the text format described in the
+    /// [`ExportedCircuit`] documentation.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        // Writing to a `String` cannot fail.
+        let _ = self.write_text(&mut text);
+        text
+    }
+
+    fn write_text(&self, text: &mut String) -> core::fmt::Result {
+        writeln!(text, "{}", HEADER)?;
+        writeln!(text, "commitments {}", self.num_commitments)?;
+
+        let mut multiplier = 0;
+        for (i, phase) in self.phases.iter().enumerate() {
+            writeln!(text, "phase {}", i + 1)?;
+            for (label, challenge) in phase.challenges.iter() {
+                write!(text, "challenge {}", format_scalar(challenge))?;
+                write_label(text, &Some(label.clone()))?;
+            }
+            for label in phase.multipliers.iter() {
+                write!(text, "multiplier {}", multiplier)?;
+                write_label(text, label)?;
+                multiplier += 1;
+            }
+            for (lc, label) in phase.constraints.iter() {
+                write!(text, "constraint")?;
+                for (var, coeff) in lc.terms.iter() {
+                    write!(text, " {}*{}", format_scalar(coeff), format_variable(var))?;
+                }
+                write_label(text, label)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a circuit in the text format described in the
+    /// [`ExportedCircuit`] documentation.
+    ///
+    /// Returns [`R1CSError::FormatError`] if the text is malformed, or
+    /// refers to variables which the circuit does not have.
+    pub fn from_text(text: &str) -> Result<Self, R1CSError> {
+        let mut lines = text
+            .lines()
+            .map(|line| match line.find('#') {
+                Some(i) => (line[..i].trim(), Some(String::from(line[i + 1..].trim()))),
+                None => (line.trim(), None),
+            })
+            .filter(|(line, _)| !line.is_empty());
+
+        if lines.next() != Some((HEADER, None)) {
+            return Err(R1CSError::FormatError);
+        }
+        let num_commitments = match lines.next() {
+            Some((line, None)) => parse_keyword(line, "commitments")?,
+            _ => return Err(R1CSError::FormatError),
+        };
+
+        let mut circuit = ExportedCircuit {
+            num_commitments,
+            phases: Vec::new(),
+        };
+        let mut num_multipliers = 0;
+        for (line, label) in lines {
+            let mut words = line.split_whitespace();
+            let keyword = words.next().ok_or(R1CSError::FormatError)?;
+            if keyword == "phase" {
+                let phase: usize = parse_keyword(line, "phase")?;
+                if phase != circuit.phases.len() + 1 || phase > 2 || label.is_some() {
+                    return Err(R1CSError::FormatError);
+                }
+                circuit.phases.push(ExportedPhase::default());
+                continue;
+            }
+
+            let phase = circuit.phases.last_mut().ok_or(R1CSError::FormatError)?;
+            match keyword {
+                "challenge" => {
+                    let scalar = match (words.next(), words.next()) {
+                        (Some(scalar), None) => parse_scalar(scalar)?,
+                        _ => return Err(R1CSError::FormatError),
+                    };
+                    let label = label.ok_or(R1CSError::FormatError)?;
+                    if !phase.multipliers.is_empty() || !phase.constraints.is_empty() {
+                        return Err(R1CSError::FormatError);
+                    }
+                    phase.challenges.push((label, scalar));
+                }
+                "multiplier" => {
+                    if parse_keyword::<usize>(line, "multiplier")? != num_multipliers {
+                        return Err(R1CSError::FormatError);
+                    }
+                    phase.multipliers.push(label);
+                    num_multipliers += 1;
+                }
+                "constraint" => {
+                    let lc = words.map(parse_term).collect::<Result<_, _>>()?;
+                    phase.constraints.push((lc, label));
+                }
+                _ => return Err(R1CSError::FormatError),
+            }
+        }
+
+        if circuit.phases.is_empty() {
+            return Err(R1CSError::FormatError);
+        }
+        let well_formed = circuit
+            .phases
+            .iter()
+            .flat_map(|phase| phase.constraints.iter())
+            .flat_map(|(lc, _)| lc.terms.iter())
+            .all(|(var, _)| match var {
+                Variable::Committed(i) => *i < num_commitments,
+                Variable::MultiplierLeft(i)
+                | Variable::MultiplierRight(i)
+                | Variable::MultiplierOutput(i) => *i < num_multipliers,
+                Variable::One() => true,
+            });
+        if !well_formed {
+            return Err(R1CSError::FormatError);
+        }
+
+        Ok(circuit)
+    }
+}
+
+/// Calls `f` inside a namespace named `label`, if there is a label.
+fn labelled<CS: ConstraintSystem, R, F: FnOnce(&mut CS) -> R>(
+    cs: &mut CS,
+    label: &Option<String>,
+    f: F,
+) -> R {
+    match label {
+        Some(label) => cs.namespace(|| label.clone(), f),
+        None => f(cs),
+    }
+}
+
+fn write_label(text: &mut String, label: &Option<String>) -> core::fmt::Result {
+    match label {
+        // Labels run to the end of the line.
+        Some(label) => writeln!(text, " # {}", label.replace('\n', " ")),
+        None => writeln!(text),
+    }
+}
+
+/// Parses a line consisting of `keyword` and a single number.
+fn parse_keyword<N: core::str::FromStr>(line: &str, keyword: &str) -> Result<N, R1CSError> {
+    let mut words = line.split_whitespace();
+    match (words.next(), words.next(), words.next()) {
+        (Some(k), Some(n), None) if k == keyword => n.parse().map_err(|_| R1CSError::FormatError),
+        _ => Err(R1CSError::FormatError),
+    }
+}
+
+fn format_variable(var: &Variable) -> String {
+    match var {
+        Variable::Committed(i) => format!("V{}", i),
+        Variable::MultiplierLeft(i) => format!("L{}", i),
+        Variable::MultiplierRight(i) => format!("R{}", i),
+        Variable::MultiplierOutput(i) => format!("O{}", i),
+        Variable::One() => String::from("1"),
+    }
+}
+
+fn parse_variable(var: &str) -> Result<Variable, R1CSError> {
+    if var == "1" {
+        return Ok(Variable::One());
+    }
+    let mut chars = var.chars();
+    let kind = chars.next();
+    let index = chars.as_str().parse().map_err(|_| R1CSError::FormatError)?;
+    match kind {
+        Some('V') => Ok(Variable::Committed(index)),
+        Some('L') => Ok(Variable::MultiplierLeft(index)),
+        Some('R') => Ok(Variable::MultiplierRight(index)),
+        Some('O') => Ok(Variable::MultiplierOutput(index)),
+        _ => Err(R1CSError::FormatError),
+    }
+}
+
+fn parse_term(term: &str) -> Result<(Variable, Scalar), R1CSError> {
+    let mut parts = term.splitn(2, '*');
+    match (parts.next(), parts.next()) {
+        (Some(coeff), Some(var)) => Ok((parse_variable(var)?, parse_scalar(coeff)?)),
+        _ => Err(R1CSError::FormatError),
+    }
+}
+
+/// Returns the scalar as a `u64`, if it is less than \\(2\^{64}\\).
+fn small_scalar(s: &Scalar) -> Option<u64> {
+    let bytes = s.as_bytes();
+    if bytes[8..].iter().all(|b| *b == 0) {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(&bytes[..8]);
+        Some(u64::from_le_bytes(buf))
+    } else {
+        None
+    }
+}
+
+fn format_scalar(s: &Scalar) -> String {
+    if let Some(x) = small_scalar(s) {
+        format!("{}", x)
+    } else if let Some(x) = small_scalar(&-s) {
+        format!("-{}", x)
+    } else {
+        let mut hex = String::from("0x");
+        for b in s.as_bytes() {
+            let _ = write!(hex, "{:02x}", b);
+        }
+        hex
+    }
+}
+
+fn parse_scalar(s: &str) -> Result<Scalar, R1CSError> {
+    if let Some(hex) = s.strip_prefix("0x") {
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(R1CSError::FormatError);
+        }
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+                .map_err(|_| R1CSError::FormatError)?;
+        }
+        Option::from(Scalar::from_canonical_bytes(bytes)).ok_or(R1CSError::FormatError)
+    } else if let Some(x) = s.strip_prefix('-') {
+        let x: u64 = x.parse().map_err(|_| R1CSError::FormatError)?;
+        Ok(-Scalar::from(x))
+    } else {
+        let x: u64 = s.parse().map_err(|_| R1CSError::FormatError)?;
+        Ok(Scalar::from(x))
+    }
+}
@@ -0,0 +1,270 @@
+//! Confidential-transaction balance gadget.
+//!
+//! The gadget proves that committed inputs equal committed outputs plus
+//! a public fee, and that every output is in range, in a single
+//! [`R1CSProof`].  Without the range checks, a negative output, which
+//! wraps around the group order, could balance inputs of any value.
+//! For the same reason, the sum of the outputs and the fee must not
+//! wrap either, so the gadget bounds the number and the bitsize of the
+//! outputs.
+//!
+//! Proving the balance alone is cheaper with a
+//! [`BalanceProof`](crate::BalanceProof), outside of a constraint
+//! system.
+//!
+//! Balancing `m` outputs of `n` bits costs `mn` multipliers and
+//! `m(2n + 1) + 1` constraints.
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate rand;
+
+#[cfg(feature = "std")]
+use self::rand::thread_rng;
+use alloc::vec::Vec;
+use curve25519_dalek::ristretto::CompressedRistretto;
+use curve25519_dalek::scalar::Scalar;
+use rand_core::{CryptoRng, RngCore};
+
+use super::range::{range_check, MAX_BITSIZE};
+use crate::errors::R1CSError;
+use crate::generators::{BulletproofGens, PedersenGens};
+use crate::r1cs::{ConstraintSystem, LinearCombination, Prover, R1CSProof, Verifier};
+use crate::transcript::{TranscriptBackend, TranscriptProtocol};
+
+/// Enforces that the sum of the `inputs` equals the sum of the
+/// `outputs` plus the `fee`, and that each output is in the range
+/// `[0, 2^n)`.
+///
+/// The balance holds modulo the group order \\(\ell\\), so it only
+/// implies the balance of the integer values if neither side wraps
+/// around \\(\ell\\).  The inputs are not range-checked: they are
+/// assumed to be range-checked elsewhere, usually as outputs of
+/// earlier transactions, such that their sum is smaller than
+/// \\(\ell\\).  The outputs are bounded by requiring
+/// \\(m \cdot 2^n + \text{fee} < \ell\\) for \\(m\\) outputs.
+///
+/// The prover passes the values of the outputs as
+/// `output_assignments`, and the verifier passes `None`.
+///
+/// Returns a [`R1CSError::GadgetError`] if the number of output
+/// assignments does not match the number of outputs, if `n` is
+/// larger than [`MAX_BITSIZE`], or if the
+/// sum of the outputs and the fee could wrap around the group order.
+pub fn balance<CS: ConstraintSystem>(
+    cs: &mut CS,
+    inputs: Vec<LinearCombination>,
+    outputs: Vec<LinearCombination>,
+    output_assignments: Option<&[Scalar]>,
+    fee: u64,
+    n: usize,
+) -> Result<(), R1CSError> {
+    if n > MAX_BITSIZE {
+        return Err(R1CSError::GadgetError {
+            description: "balance bitsize exceeds MAX_BITSIZE".into(),
+        });
+    }
+    if let Some(assignments) = output_assignments {
+        if assignments.len() != outputs.len() {
+            return Err(R1CSError::GadgetError {
+                description: "wrong number of output assignments".into(),
+            });
+        }
+    }
+    if !outputs_below_group_order(outputs.len(), n, fee) {
+        return Err(R1CSError::GadgetError {
+            description: "the sum of the outputs could exceed the group order".into(),
+        });
+    }
+
+    let mut excess = LinearCombination::default() - fee;
+    for input in inputs {
+        excess = excess + input;
+    }
+    for (i, output) in outputs.into_iter().enumerate() {
+        range_check(cs, output.clone(), output_assignments.map(|a| a[i]), n)?;
+        excess = excess - output;
+    }
+    cs.constrain(excess);
+
+    Ok(())
+}
+
+/// The group order \\(\ell = 2^{252} +
+/// 27742317777372353535851937790883648493\\), as little-endian 64-bit
+/// limbs.
+const GROUP_ORDER_LIMBS: [u64; 4] = [
+    0x5812631a5cf5d3ed,
+    0x14def9dea2f79cd6,
+    0x0000000000000000,
+    0x1000000000000000,
+];
+
+/// Returns whether \\(m \cdot 2^n + \text{fee} < \ell\\), so that the
+/// sum of `m` outputs of `n` bits and the fee does not wrap around the
+/// group order.
+fn outputs_below_group_order(m: usize, n: usize, fee: u64) -> bool {
+    let m_bits = (usize::BITS - m.leading_zeros()) as usize;
+    if m_bits + n > 253 {
+        return false;
+    }
+
+    // Computes m * 2^n + fee as little-endian 64-bit limbs.  The bound
+    // above keeps m * 2^n below 2^253, so it fits in four limbs, and
+    // adding the fee cannot carry out of the last one.
+    let mut limbs = [0u64; 5];
+    let shifted = (m as u128) << (n % 64);
+    limbs[n / 64] = shifted as u64;
+    limbs[n / 64 + 1] = (shifted >> 64) as u64;
+    let mut carry = fee;
+    for limb in limbs.iter_mut() {
+        let (sum, overflow) = limb.overflowing_add(carry);
+        *limb = sum;
+        carry = overflow as u64;
+    }
+
+    limbs[..4].iter().rev().lt(GROUP_ORDER_LIMBS.iter().rev())
+}
+
+/// Creates a proof that the `inputs` balance the `outputs` and the
+/// `fee`, with `n`-bit outputs.
+///
+/// The inputs and outputs are given as the openings `(value, blinding)`
+/// of their commitments, which the caller has already published as
+/// [`PedersenGens::commit`]`(value, blinding)`.
+///
+/// The numbers of inputs and outputs, the fee and `n` are bound into
+/// the transcript before the commitments.
+///
+/// This is a convenience wrapper around [`prove_with_rng`], passing in
+/// a threadsafe RNG.
+#[cfg(feature = "std")]
+#[allow(clippy::too_many_arguments)]
+pub fn prove<T: TranscriptBackend>(
+    pc_gens: &PedersenGens,
+    bp_gens: &BulletproofGens,
+    transcript: T,
+    inputs: &[(Scalar, Scalar)],
+    outputs: &[(Scalar, Scalar)],
+    fee: u64,
+    n: usize,
+) -> Result<R1CSProof, R1CSError> {
+    prove_with_rng(
+        pc_gens,
+        bp_gens,
+        transcript,
+        inputs,
+        outputs,
+        fee,
+        n,
+        &mut thread_rng(),
+    )
+}
+
+/// Same as [`prove`], but uses `rng` as the external randomness of
+/// the proof.
+#[allow(clippy::too_many_arguments)]
+pub fn prove_with_rng<T: TranscriptBackend, R: RngCore + CryptoRng>(
+    pc_gens: &PedersenGens,
+    bp_gens: &BulletproofGens,
+    transcript: T,
+    inputs: &[(Scalar, Scalar)],
+    outputs: &[(Scalar, Scalar)],
+    fee: u64,
+    n: usize,
+    rng: &mut R,
+) -> Result<R1CSProof, R1CSError> {
+    let mut prover = Prover::new(pc_gens, transcript);
+    prover.transcript().r1cs_balance_domain_sep(
+        inputs.len() as u64,
+        outputs.len() as u64,
+        fee,
+        n as u64,
+    );
+    let mut commit = |openings: &[(Scalar, Scalar)]| -> Vec<LinearCombination> {
+        openings
+            .iter()
+            .map(|(v, v_blinding)| prover.commit(*v, *v_blinding).1.into())
+            .collect()
+    };
+    let (input_vars, output_vars) = (commit(inputs), commit(outputs));
+    let output_values: Vec<Scalar> = outputs.iter().map(|(v, _)| *v).collect();
+
+    balance(
+        &mut prover,
+        input_vars,
+        output_vars,
+        Some(&output_values),
+        fee,
+        n,
+    )?;
+
+    prover.prove_with_rng(bp_gens, rng)
+}
+
+/// Verifies a proof that the committed `inputs` balance the committed
+/// `outputs` and the `fee`, with `n`-bit outputs.
+///
+/// As in [`prove`], the numbers of inputs and outputs, the fee and `n`
+/// are bound into the transcript before the commitments.
+///
+/// This is a convenience wrapper around [`verify_with_rng`], passing
+/// in a threadsafe RNG.
+#[cfg(feature = "std")]
+#[allow(clippy::too_many_arguments)]
+pub fn verify<T: TranscriptBackend>(
+    proof: &R1CSProof,
+    pc_gens: &PedersenGens,
+    bp_gens: &BulletproofGens,
+    transcript: T,
+    inputs: &[CompressedRistretto],
+    outputs: &[CompressedRistretto],
+    fee: u64,
+    n: usize,
+) -> Result<(), R1CSError> {
+    verify_with_rng(
+        proof,
+        pc_gens,
+        bp_gens,
+        transcript,
+        inputs,
+        outputs,
+        fee,
+        n,
+        &mut thread_rng(),
+    )
+}
+
+/// Same as [`verify`], but uses `rng` as the external randomness for
+/// the random weight combining the verification checks.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_with_rng<T: TranscriptBackend, R: RngCore + CryptoRng>(
+    proof: &R1CSProof,
+    pc_gens: &PedersenGens,
+    bp_gens: &BulletproofGens,
+    transcript: T,
+    inputs: &[CompressedRistretto],
+    outputs: &[CompressedRistretto],
+    fee: u64,
+    n: usize,
+    rng: &mut R,
+) -> Result<(), R1CSError> {
+    let mut verifier = Verifier::new(transcript);
+    verifier.transcript().r1cs_balance_domain_sep(
+        inputs.len() as u64,
+        outputs.len() as u64,
+        fee,
+        n as u64,
+    );
+    let mut commit = |commitments: &[CompressedRistretto]| -> Vec<LinearCombination> {
+        commitments
+            .iter()
+            .map(|c| verifier.commit(*c).into())
+            .collect()
+    };
+    let (input_vars, output_vars) = (commit(inputs), commit(outputs));
+
+    balance(&mut verifier, input_vars, output_vars, None, fee, n)?;
+
+    verifier.verify_with_rng(proof, pc_gens, bp_gens, rng)
+}
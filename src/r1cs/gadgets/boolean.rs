@@ -0,0 +1,101 @@
+//! Boolean and logic gadgets.
+//!
+//! The logic gadgets take their inputs as linear combinations which
+//! are assumed to be boolean, that is `0` or `1`: for example the
+//! outputs of other gadgets of this module, variables allocated with
+//! [`allocate_boolean`], or values checked with [`constrain_boolean`].
+//! On other inputs, their results are meaningless.
+
+use curve25519_dalek::scalar::Scalar;
+
+use crate::errors::R1CSError;
+use crate::r1cs::{ConstraintSystem, LinearCombination, Variable};
+
+/// Allocates a variable constrained to be `0` or `1`, with the value
+/// of `assignment`.
+///
+/// Costs 1 multiplier and 2 constraints.
+pub fn allocate_boolean<CS: ConstraintSystem>(
+    cs: &mut CS,
+    assignment: Option<bool>,
+) -> Result<Variable, R1CSError> {
+    let (a, b, o) = cs.allocate_multiplier(assignment.map(|x| {
+        let bit = Scalar::from(x as u64);
+        (Scalar::ONE - bit, bit)
+    }))?;
+
+    // Enforce a * b = 0, so one of (a,b) is zero
+    cs.constrain(o.into());
+
+    // Enforce that a = 1 - b, so they both are 1 or 0.
+    cs.constrain(a + (b - 1u64));
+
+    Ok(b)
+}
+
+/// Enforces that `x` is `0` or `1`, that is `x * (1 - x) = 0`.
+///
+/// Costs 1 multiplier and 3 constraints.
+pub fn constrain_boolean<CS: ConstraintSystem>(cs: &mut CS, x: LinearCombination) {
+    let (_, _, o) = cs.multiply(x.clone(), -x + Scalar::ONE);
+    cs.constrain(o.into());
+}
+
+/// Returns `NOT x`, that is `1 - x`.
+///
+/// Costs nothing.
+pub fn not(x: LinearCombination) -> LinearCombination {
+    -x + Scalar::ONE
+}
+
+/// Returns `a AND b`, that is `a * b`.
+///
+/// Costs 1 multiplier and 2 constraints.
+pub fn and<CS: ConstraintSystem>(
+    cs: &mut CS,
+    a: LinearCombination,
+    b: LinearCombination,
+) -> Variable {
+    let (_, _, o) = cs.multiply(a, b);
+    o
+}
+
+/// Returns `a OR b`, that is `a + b - a * b`.
+///
+/// Costs 1 multiplier and 2 constraints.
+pub fn or<CS: ConstraintSystem>(
+    cs: &mut CS,
+    a: LinearCombination,
+    b: LinearCombination,
+) -> LinearCombination {
+    let (l, r, o) = cs.multiply(a, b);
+    l + r - o
+}
+
+/// Returns `a XOR b`, that is `a + b - 2 * a * b`.
+///
+/// Costs 1 multiplier and 2 constraints.
+pub fn xor<CS: ConstraintSystem>(
+    cs: &mut CS,
+    a: LinearCombination,
+    b: LinearCombination,
+) -> LinearCombination {
+    let (l, r, o) = cs.multiply(a, b);
+    l + r - o * Scalar::from(2u64)
+}
+
+/// Returns `a` if `cond` is `1`, and `b` if `cond` is `0`, that is
+/// `b + cond * (a - b)`.
+///
+/// Only `cond` needs to be boolean: `a` and `b` can have any values.
+///
+/// Costs 1 multiplier and 2 constraints.
+pub fn select<CS: ConstraintSystem>(
+    cs: &mut CS,
+    cond: LinearCombination,
+    a: LinearCombination,
+    b: LinearCombination,
+) -> LinearCombination {
+    let (_, _, o) = cs.multiply(cond, a - b.clone());
+    b + o
+}
@@ -0,0 +1,122 @@
+//! Comparison gadgets on bounded values.
+//!
+//! All gadgets in this module compare values `a` and `b` which are
+//! known to be in the range `[0, 2^k)`, for example because they were
+//! checked with [`range_check`].  The gadgets do not check this
+//! themselves: on unbounded inputs, their results are meaningless.
+//! The bitsize `k` can be at most
+//! [`MAX_BITSIZE`](super::range::MAX_BITSIZE)` - 1`.
+//!
+//! The gadgets returning a boolean [`Variable`] cost `k + 1`
+//! multipliers, and the enforce-only variants cost `k` multipliers.
+
+use curve25519_dalek::scalar::Scalar;
+
+use super::boolean::select;
+use super::range::{range_check, two_pow};
+use crate::errors::R1CSError;
+use crate::r1cs::{ConstraintSystem, LinearCombination, Variable};
+
+/// Returns a boolean variable which is `1` if `a < b` and `0`
+/// otherwise.
+///
+/// Costs `k + 1` multipliers and `2k + 3` constraints.
+pub fn less_than<CS: ConstraintSystem>(
+    cs: &mut CS,
+    a: LinearCombination,
+    a_assignment: Option<Scalar>,
+    b: LinearCombination,
+    b_assignment: Option<Scalar>,
+    k: usize,
+) -> Result<Variable, R1CSError> {
+    // d = b - a - 1 + 2^k is in [0, 2^(k+1) - 1), and its top bit is
+    // set exactly when b - a - 1 >= 0.
+    let offset = two_pow(k) - Scalar::ONE;
+    let d_assignment = a_assignment.and_then(|a| b_assignment.map(|b| b - a + offset));
+    let bits = range_check(cs, b - a + offset, d_assignment, k + 1)?;
+    Ok(bits[k])
+}
+
+/// Returns a boolean variable which is `1` if `a >= b` and `0`
+/// otherwise.
+///
+/// Costs `k + 1` multipliers and `2k + 3` constraints.
+pub fn greater_or_equal<CS: ConstraintSystem>(
+    cs: &mut CS,
+    a: LinearCombination,
+    a_assignment: Option<Scalar>,
+    b: LinearCombination,
+    b_assignment: Option<Scalar>,
+    k: usize,
+) -> Result<Variable, R1CSError> {
+    // d = a - b + 2^k is in [0, 2^(k+1)), and its top bit is set
+    // exactly when a - b >= 0.
+    let offset = two_pow(k);
+    let d_assignment = a_assignment.and_then(|a| b_assignment.map(|b| a - b + offset));
+    let bits = range_check(cs, a - b + offset, d_assignment, k + 1)?;
+    Ok(bits[k])
+}
+
+/// Enforces that `a < b`.
+///
+/// Costs `k` multipliers and `2k + 1` constraints.
+pub fn enforce_less_than<CS: ConstraintSystem>(
+    cs: &mut CS,
+    a: LinearCombination,
+    a_assignment: Option<Scalar>,
+    b: LinearCombination,
+    b_assignment: Option<Scalar>,
+    k: usize,
+) -> Result<(), R1CSError> {
+    // b - a - 1 is in [0, 2^k) exactly when a < b.
+    let d_assignment = a_assignment.and_then(|a| b_assignment.map(|b| b - a - Scalar::ONE));
+    range_check(cs, b - a - Scalar::ONE, d_assignment, k)?;
+    Ok(())
+}
+
+/// Enforces that `a >= b`.
+///
+/// Costs `k` multipliers and `2k + 1` constraints.
+pub fn enforce_greater_or_equal<CS: ConstraintSystem>(
+    cs: &mut CS,
+    a: LinearCombination,
+    a_assignment: Option<Scalar>,
+    b: LinearCombination,
+    b_assignment: Option<Scalar>,
+    k: usize,
+) -> Result<(), R1CSError> {
+    // a - b is in [0, 2^k) exactly when a >= b.
+    let d_assignment = a_assignment.and_then(|a| b_assignment.map(|b| a - b));
+    range_check(cs, a - b, d_assignment, k)?;
+    Ok(())
+}
+
+/// Returns the smaller of `a` and `b`.
+///
+/// Costs `k + 2` multipliers and `2k + 5` constraints.
+pub fn min<CS: ConstraintSystem>(
+    cs: &mut CS,
+    a: LinearCombination,
+    a_assignment: Option<Scalar>,
+    b: LinearCombination,
+    b_assignment: Option<Scalar>,
+    k: usize,
+) -> Result<LinearCombination, R1CSError> {
+    let lt = less_than(cs, a.clone(), a_assignment, b.clone(), b_assignment, k)?;
+    Ok(select(cs, lt.into(), a, b))
+}
+
+/// Returns the larger of `a` and `b`.
+///
+/// Costs `k + 2` multipliers and `2k + 5` constraints.
+pub fn max<CS: ConstraintSystem>(
+    cs: &mut CS,
+    a: LinearCombination,
+    a_assignment: Option<Scalar>,
+    b: LinearCombination,
+    b_assignment: Option<Scalar>,
+    k: usize,
+) -> Result<LinearCombination, R1CSError> {
+    let lt = less_than(cs, a.clone(), a_assignment, b.clone(), b_assignment, k)?;
+    Ok(select(cs, lt.into(), b, a))
+}
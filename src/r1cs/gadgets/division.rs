@@ -0,0 +1,187 @@
+//! Integer division and modulo gadgets.
+//!
+//! The gadgets divide a value `a` by a divisor `d`, allocating the
+//! quotient `q = floor(a / d)` and the remainder `r = a mod d` as
+//! witnesses, and enforcing that `a = q * d + r` with `0 <= r < d`.
+//!
+//! The quotient and the remainder are range-checked to `n` bits, so
+//! `q * d + r` is below `2^(2n)` and does not wrap around the order of
+//! the scalar field.  The bitsize `n` can be at most
+//! [`MAX_DIVISION_BITSIZE`].  A divisor of zero cannot satisfy the
+//! constraints.
+//!
+//! Since the quotient has `n` bits, the dividend `a` must be below
+//! `d * 2^n`.  The prover rejects a larger dividend with a
+//! [`R1CSError::GadgetError`], before allocating the quotient.
+
+use curve25519_dalek::scalar::Scalar;
+
+use super::comparison::enforce_less_than;
+use super::range::{allocate_bits, pack_bits, range_check, MAX_BITSIZE};
+use crate::errors::R1CSError;
+use crate::r1cs::{ConstraintSystem, LinearCombination};
+
+/// The largest bitsize supported by [`div_rem`] and [`div_rem_public`].
+pub const MAX_DIVISION_BITSIZE: usize = MAX_BITSIZE / 2;
+
+/// Divides `a` by the committed divisor `d`, and returns the quotient
+/// and the remainder.
+///
+/// Enforces that `a = q * d + r`, that `d`, `q` and `r` are in the
+/// range `[0, 2^n)`, and that `r < d`, so `a` must be below `d * 2^n`.
+/// The prover passes the values of `a` and `d` as `a_assignment` and
+/// `d_assignment`, and the verifier passes `None`.
+///
+/// Returns a [`R1CSError::GadgetError`] if `n` is larger than
+/// [`MAX_DIVISION_BITSIZE`], or if the prover's divisor is zero or its
+/// quotient does not fit in `n` bits.
+///
+/// Costs `4n + 1` multipliers and `8n + 5` constraints.
+pub fn div_rem<CS: ConstraintSystem>(
+    cs: &mut CS,
+    a: LinearCombination,
+    a_assignment: Option<Scalar>,
+    d: LinearCombination,
+    d_assignment: Option<Scalar>,
+    n: usize,
+) -> Result<(LinearCombination, LinearCombination), R1CSError> {
+    check_bitsize(n)?;
+    let (q_assignment, r_assignment) = match (a_assignment, d_assignment) {
+        (Some(a), Some(d)) => {
+            let (q, r) = div_rem_assignments(a, d, n)?;
+            (Some(q), Some(r))
+        }
+        _ => (None, None),
+    };
+
+    let q = pack_bits(&allocate_bits(cs, q_assignment, n)?);
+    range_check(cs, d.clone(), d_assignment, n)?;
+    let (_, _, qd) = cs.multiply(q.clone(), d.clone());
+    let r = a - qd;
+    range_check(cs, r.clone(), r_assignment, n)?;
+    enforce_less_than(cs, r.clone(), r_assignment, d, d_assignment, n)?;
+
+    Ok((q, r))
+}
+
+/// Divides `a` by the public divisor `d`, and returns the quotient and
+/// the remainder.
+///
+/// Enforces that `a = q * d + r`, that `q` and `r` are in the range
+/// `[0, 2^n)`, and that `r < d`, so `a` must be below `d * 2^n`.  The
+/// prover passes the value of `a` as `a_assignment`, and the verifier
+/// passes `None`.
+///
+/// Returns a [`R1CSError::GadgetError`] if `n` is larger than
+/// [`MAX_DIVISION_BITSIZE`], if `d` is zero or not below `2^n`, or if
+/// the prover's quotient does not fit in `n` bits.
+///
+/// Costs `3n` multipliers and `6n + 2` constraints.
+pub fn div_rem_public<CS: ConstraintSystem>(
+    cs: &mut CS,
+    a: LinearCombination,
+    a_assignment: Option<Scalar>,
+    d: u64,
+    n: usize,
+) -> Result<(LinearCombination, LinearCombination), R1CSError> {
+    check_bitsize(n)?;
+    if d == 0 || (n < 64 && d >> n != 0) {
+        return Err(R1CSError::GadgetError {
+            description: "divisor must be in the range [1, 2^n)".into(),
+        });
+    }
+    let (q_assignment, r_assignment) = match a_assignment {
+        Some(a) => {
+            let (q, r) = div_rem_assignments(a, d.into(), n)?;
+            (Some(q), Some(r))
+        }
+        None => (None, None),
+    };
+
+    let q = pack_bits(&allocate_bits(cs, q_assignment, n)?);
+    let r = a - q.clone() * Scalar::from(d);
+    range_check(cs, r.clone(), r_assignment, n)?;
+    enforce_less_than(
+        cs,
+        r.clone(),
+        r_assignment,
+        Scalar::from(d).into(),
+        Some(d.into()),
+        n,
+    )?;
+
+    Ok((q, r))
+}
+
+fn check_bitsize(n: usize) -> Result<(), R1CSError> {
+    if n > MAX_DIVISION_BITSIZE {
+        return Err(R1CSError::GadgetError {
+            description: "division bitsize exceeds MAX_DIVISION_BITSIZE".into(),
+        });
+    }
+    Ok(())
+}
+
+/// Computes the quotient and the remainder of the prover's values,
+/// as integers below the order of the scalar field.
+///
+/// Fails if the quotient does not fit in `n` bits, which happens when
+/// `a` is not below `d * 2^n`.
+fn div_rem_assignments(a: Scalar, d: Scalar, n: usize) -> Result<(Scalar, Scalar), R1CSError> {
+    if d == Scalar::ZERO {
+        return Err(R1CSError::GadgetError {
+            description: "division by zero".into(),
+        });
+    }
+    let (a, d) = (a.to_bytes(), d.to_bytes());
+    let bit = |bytes: &[u8; 32], i: usize| (bytes[i / 8] >> (i % 8)) & 1;
+
+    // Binary long division.  Canonical scalars are below 2^253, so
+    // the shifted remainder never overflows 256 bits.
+    let mut q = [0u8; 32];
+    let mut r = [0u8; 32];
+    for i in (0..256).rev() {
+        shift_left(&mut r, bit(&a, i));
+        if !less_than(&r, &d) {
+            subtract(&mut r, &d);
+            q[i / 8] |= 1 << (i % 8);
+        }
+    }
+    if (n..256).any(|i| bit(&q, i) == 1) {
+        return Err(R1CSError::GadgetError {
+            description: "quotient does not fit in n bits: the dividend must be below d * 2^n"
+                .into(),
+        });
+    }
+    Ok((
+        Scalar::from_bytes_mod_order(q),
+        Scalar::from_bytes_mod_order(r),
+    ))
+}
+
+/// Shifts the little-endian integer `x` left by one bit, shifting in
+/// `low_bit`.
+fn shift_left(x: &mut [u8; 32], low_bit: u8) {
+    let mut carry = low_bit;
+    for byte in x.iter_mut() {
+        let next = *byte >> 7;
+        *byte = (*byte << 1) | carry;
+        carry = next;
+    }
+}
+
+/// Returns whether the little-endian integer `x` is less than `y`.
+fn less_than(x: &[u8; 32], y: &[u8; 32]) -> bool {
+    x.iter().rev().lt(y.iter().rev())
+}
+
+/// Subtracts the little-endian integer `y` from `x`, which must not be
+/// smaller.
+fn subtract(x: &mut [u8; 32], y: &[u8; 32]) {
+    let mut borrow = 0i16;
+    for (x_i, y_i) in x.iter_mut().zip(y.iter()) {
+        let diff = *x_i as i16 - *y_i as i16 - borrow;
+        borrow = (diff < 0) as i16;
+        *x_i = diff.rem_euclid(256) as u8;
+    }
+}
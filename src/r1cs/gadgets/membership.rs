@@ -0,0 +1,138 @@
+//! Set membership and non-membership gadgets for public sets.
+//!
+//! Membership of a value `v` in a set `{s_0, ..., s_(m-1)}` can be
+//! proved in two ways:
+//!
+//! * [`set_membership`] enforces that `Prod(v - s_i) = 0`, costing
+//!   `m - 1` multipliers.  This is the cheapest way to prove plain
+//!   membership.
+//! * [`set_membership_one_hot`] allocates a selection vector of `m`
+//!   bits with exactly one bit set, costing `m` multipliers.  The bits
+//!   are returned, so that values associated with the selected element
+//!   in other public tables can be selected with one constraint each,
+//!   using [`select_one_hot`].
+//!
+//! Both costs are linear in the size of the set.  For sets of many
+//! thousands of elements, prefer proving membership in a Merkle tree
+//! of the set, whose cost is logarithmic in its size.
+//!
+//! [`set_non_membership`] proves that a value is not in the set, with
+//! `m` multipliers.
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use curve25519_dalek::scalar::Scalar;
+
+use super::boolean::allocate_boolean;
+use super::nonzero::enforce_nonzero;
+use crate::errors::R1CSError;
+use crate::r1cs::{ConstraintSystem, LinearCombination, Variable};
+
+fn empty_set_error() -> R1CSError {
+    R1CSError::GadgetError {
+        description: "set must not be empty".into(),
+    }
+}
+
+/// Returns `Prod(v - s_i)` over the elements of the nonempty `set`.
+///
+/// Costs `m - 1` multipliers and `2(m - 1)` constraints.
+fn product_of_differences<CS: ConstraintSystem>(
+    cs: &mut CS,
+    v: LinearCombination,
+    set: &[Scalar],
+) -> LinearCombination {
+    let first = v.clone() - set[0];
+    set[1..].iter().fold(first, |product, s| {
+        let (_, _, o) = cs.multiply(product, v.clone() - *s);
+        o.into()
+    })
+}
+
+/// Enforces that `v` is one of the elements of `set`.
+///
+/// Returns a [`R1CSError::GadgetError`] if the set is empty.
+///
+/// Costs `m - 1` multipliers and `2m - 1` constraints, for a set of
+/// `m` elements.
+pub fn set_membership<CS: ConstraintSystem>(
+    cs: &mut CS,
+    v: LinearCombination,
+    set: &[Scalar],
+) -> Result<(), R1CSError> {
+    if set.is_empty() {
+        return Err(empty_set_error());
+    }
+    let product = product_of_differences(cs, v, set);
+    cs.constrain(product);
+    Ok(())
+}
+
+/// Enforces that `v` is one of the elements of `set`, and returns a
+/// one-hot selection vector: boolean variables `b_i`, of which only the
+/// one at the index of `v` in the set is `1`.
+///
+/// The prover passes the index of `v` in the set as `index`, and the
+/// verifier passes `None`.
+///
+/// Returns a [`R1CSError::GadgetError`] if the set is empty.
+///
+/// Costs `m` multipliers and `2m + 2` constraints, for a set of `m`
+/// elements.
+pub fn set_membership_one_hot<CS: ConstraintSystem>(
+    cs: &mut CS,
+    v: LinearCombination,
+    set: &[Scalar],
+    index: Option<usize>,
+) -> Result<Vec<Variable>, R1CSError> {
+    if set.is_empty() {
+        return Err(empty_set_error());
+    }
+    let bits = (0..set.len())
+        .map(|i| allocate_boolean(cs, index.map(|index| index == i)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Enforce that exactly one bit is set
+    let sum: LinearCombination = bits.iter().map(|b| (*b, Scalar::ONE)).collect();
+    cs.constrain(sum - 1u64);
+
+    // Enforce that v is the selected element
+    cs.constrain(v - select_one_hot(&bits, set));
+
+    Ok(bits)
+}
+
+/// Returns the element of `table` selected by the one-hot selection
+/// vector `bits`, that is `Sum(b_i * t_i)`.
+///
+/// Costs nothing: the result is a linear combination of the bits.
+pub fn select_one_hot(bits: &[Variable], table: &[Scalar]) -> LinearCombination {
+    bits.iter()
+        .zip(table.iter())
+        .map(|(b, t)| (*b, *t))
+        .collect()
+}
+
+/// Enforces that `v` is not an element of `set`.
+///
+/// The prover passes the value of `v` as `v_assignment`, and the
+/// verifier passes `None`.  An empty set contains no elements, so
+/// every value is accepted.
+///
+/// Costs `m` multipliers and `2m` constraints, for a set of `m`
+/// elements.
+pub fn set_non_membership<CS: ConstraintSystem>(
+    cs: &mut CS,
+    v: LinearCombination,
+    v_assignment: Option<Scalar>,
+    set: &[Scalar],
+) -> Result<(), R1CSError> {
+    if set.is_empty() {
+        return Ok(());
+    }
+    let product = product_of_differences(cs, v, set);
+    let product_assignment =
+        v_assignment.map(|v| set.iter().fold(Scalar::ONE, |product, s| product * (v - s)));
+    enforce_nonzero(cs, product, product_assignment)
+}
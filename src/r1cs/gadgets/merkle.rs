@@ -0,0 +1,208 @@
+//! Merkle tree membership proofs, with trees hashed by [`MiMC`].
+//!
+//! The nodes of a tree are hashed with [`MiMC::compress`]`(left, right)`.
+//! A [`MerkleTree`] is built natively from its leaves, and provides the
+//! [`MerklePath`] of each leaf, which is the prover's witness for
+//! [`merkle_membership`].
+//!
+//! The gadgets hide both the leaf and its position in the tree.  Each
+//! level of the path costs [`LEVEL_MULTIPLIERS`] multipliers and
+//! [`LEVEL_CONSTRAINTS`] constraints: 330 and 660 for the hash, and the
+//! rest to swap the children according to the secret direction.  The
+//! gadgets enter a namespace per level, so that the cost of each level
+//! is reported in [`Metrics::namespaces`](crate::r1cs::Metrics::namespaces).
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
+use curve25519_dalek::scalar::Scalar;
+
+use super::boolean::allocate_boolean;
+use super::mimc::MiMC;
+use crate::errors::R1CSError;
+use crate::r1cs::{ConstraintSystem, LinearCombination};
+
+/// The number of multipliers of each level of [`merkle_root`].
+pub const LEVEL_MULTIPLIERS: usize = 332;
+
+/// The number of constraints of each level of [`merkle_root`].
+pub const LEVEL_CONSTRAINTS: usize = 663;
+
+/// A Merkle tree of scalars.
+#[derive(Clone, Debug)]
+pub struct MerkleTree {
+    /// The nodes of each level of the tree, from the leaves to the root.
+    levels: Vec<Vec<Scalar>>,
+}
+
+/// The path from a leaf to the root of a [`MerkleTree`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct MerklePath {
+    /// The sibling of the node at each level, from the leaf upwards.
+    pub siblings: Vec<Scalar>,
+    /// Whether the node at each level is the right child of its
+    /// parent, from the leaf upwards.
+    pub directions: Vec<bool>,
+}
+
+impl MerkleTree {
+    /// Builds the tree of the `leaves`.
+    ///
+    /// The leaves are padded with zeros to a power of two, so a tree of
+    /// `m` leaves has depth `ceil(log2(m))`.  The padding leaves are
+    /// members of the tree like any other: when `m` is not a power of
+    /// two, [`merkle_membership`] accepts a proof that `0` is in the
+    /// tree.  Applications where `0` may be a meaningful leaf should
+    /// pad the leaves themselves, with a value that cannot occur as a
+    /// leaf.
+    pub fn new(mimc: &MiMC, leaves: &[Scalar]) -> Self {
+        let mut level = leaves.to_vec();
+        level.resize(leaves.len().max(1).next_power_of_two(), Scalar::ZERO);
+
+        let mut levels = vec![level];
+        while levels[levels.len() - 1].len() > 1 {
+            let next = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| mimc.compress(pair[0], pair[1]))
+                .collect();
+            levels.push(next);
+        }
+        MerkleTree { levels }
+    }
+
+    /// Returns the root of the tree.
+    pub fn root(&self) -> Scalar {
+        self.levels[self.levels.len() - 1][0]
+    }
+
+    /// Returns the depth of the tree, which is the length of its paths.
+    pub fn depth(&self) -> usize {
+        self.levels.len() - 1
+    }
+
+    /// Returns the path of the leaf at `index`, or `None` if the index
+    /// is out of range.
+    pub fn path(&self, index: usize) -> Option<MerklePath> {
+        if index >= self.levels[0].len() {
+            return None;
+        }
+        let (siblings, directions) = self.levels[..self.depth()]
+            .iter()
+            .enumerate()
+            .map(|(i, level)| {
+                let node = index >> i;
+                (level[node ^ 1], node & 1 == 1)
+            })
+            .unzip();
+        Some(MerklePath {
+            siblings,
+            directions,
+        })
+    }
+}
+
+impl MerklePath {
+    /// Returns the root of the tree containing `leaf` at this path.
+    pub fn root(&self, mimc: &MiMC, leaf: Scalar) -> Scalar {
+        self.siblings
+            .iter()
+            .zip(self.directions.iter())
+            .fold(leaf, |node, (sibling, is_right)| {
+                if *is_right {
+                    mimc.compress(*sibling, node)
+                } else {
+                    mimc.compress(node, *sibling)
+                }
+            })
+    }
+}
+
+/// Returns the root of the tree of depth `depth` which contains `leaf`,
+/// computed inside the constraint system.
+///
+/// The prover passes the value of the leaf and its path as `witness`,
+/// and the verifier passes `None`.
+///
+/// Returns a [`R1CSError::GadgetError`] if the path does not have
+/// length `depth`.
+///
+/// Costs [`LEVEL_MULTIPLIERS`] multipliers and [`LEVEL_CONSTRAINTS`]
+/// constraints per level, in the namespace `merkle level {i}` for the
+/// level `i` counted from the leaf.
+pub fn merkle_root<CS: ConstraintSystem>(
+    cs: &mut CS,
+    mimc: &MiMC,
+    leaf: LinearCombination,
+    depth: usize,
+    witness: Option<(Scalar, &MerklePath)>,
+) -> Result<LinearCombination, R1CSError> {
+    if let Some((_, path)) = witness {
+        if path.siblings.len() != depth || path.directions.len() != depth {
+            return Err(R1CSError::GadgetError {
+                description: "Merkle path length does not match the depth".into(),
+            });
+        }
+    }
+
+    let mut node = leaf;
+    let mut node_assignment = witness.map(|(leaf, _)| leaf);
+    for i in 0..depth {
+        let level = witness.map(|(_, path)| (path.siblings[i], path.directions[i]));
+        node = cs.namespace(
+            || format!("merkle level {}", i),
+            |cs| -> Result<_, R1CSError> {
+                let is_right = allocate_boolean(cs, level.map(|(_, is_right)| is_right))?;
+
+                // Allocate the sibling as delta = sibling - node, and
+                // swap the children with o = is_right * delta.
+                let assignments = node_assignment
+                    .and_then(|node| level.map(|(sibling, is_right)| (is_right, sibling - node)));
+                let (d, delta, o) = cs.allocate_multiplier(
+                    assignments.map(|(is_right, delta)| (Scalar::from(is_right as u64), delta)),
+                )?;
+                cs.constrain(d - is_right);
+
+                let left = node.clone() + o;
+                let right = node + delta - o;
+                Ok(mimc.compress_gadget(cs, left, right))
+            },
+        )?;
+
+        node_assignment = node_assignment.and_then(|node| {
+            level.map(|(sibling, is_right)| {
+                if is_right {
+                    mimc.compress(sibling, node)
+                } else {
+                    mimc.compress(node, sibling)
+                }
+            })
+        });
+    }
+    Ok(node)
+}
+
+/// Enforces that `leaf` is in the tree of depth `depth` with the public
+/// `root`.
+///
+/// The prover passes the value of the leaf and its path as `witness`,
+/// and the verifier passes `None`.
+///
+/// Returns a [`R1CSError::GadgetError`] if the path does not have
+/// length `depth`.
+///
+/// Costs [`LEVEL_MULTIPLIERS`] multipliers and [`LEVEL_CONSTRAINTS`]
+/// constraints per level, and one more constraint.
+pub fn merkle_membership<CS: ConstraintSystem>(
+    cs: &mut CS,
+    mimc: &MiMC,
+    leaf: LinearCombination,
+    root: Scalar,
+    depth: usize,
+    witness: Option<(Scalar, &MerklePath)>,
+) -> Result<(), R1CSError> {
+    let computed_root = merkle_root(cs, mimc, leaf, depth, witness)?;
+    cs.constrain(computed_root - root);
+    Ok(())
+}
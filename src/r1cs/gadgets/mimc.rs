@@ -0,0 +1,139 @@
+//! The MiMC hash function over the Ristretto scalar field, natively and
+//! as a gadget.
+//!
+//! MiMC is a block cipher whose rounds are `x -> (x + k + c_i)^e`, with
+//! a key `k` and round constants `c_i`, and whose output is `x + k`
+//! after the last round.  Its rounds need only a few multiplications,
+//! so it is cheap to evaluate inside a constraint system.
+//!
+//! The parameters for the scalar field of order `l` are:
+//!
+//! * the exponent `e = 5`, the smallest for which `x -> x^e` is a
+//!   permutation of the field, since `gcd(5, l - 1) = 1`;
+//! * `110` rounds, one more than `ceil(log_5(l)) = 109`;
+//! * the round constants `c_0 = 0` and, for `i > 0`, the SHA3-512 hash
+//!   of `b"bulletproofs MiMC-5 round constant"` followed by `i` as a
+//!   little-endian `u64`, reduced modulo `l`.
+//!
+//! [`MiMC::compress`] is the two-to-one compression function
+//! `E_h(x) + h + x` of the Miyaguchi-Preneel construction, and
+//! [`MiMC::hash`] hashes a sequence of scalars by compressing them one
+//! at a time, `h_i = E_(h_(i-1))(x_i) + h_(i-1) + x_i` with `h_0 = 0`.
+//!
+//! Each round costs 3 multipliers, so the cipher costs `330` multipliers
+//! and `660` constraints, and hashing `n` scalars costs `330n`
+//! multipliers and `660n` constraints.
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use curve25519_dalek::scalar::Scalar;
+use sha3::Sha3_512;
+
+use crate::r1cs::{ConstraintSystem, LinearCombination};
+
+/// The exponent of the MiMC rounds.
+pub const MIMC_EXPONENT: u64 = 5;
+
+/// The number of MiMC rounds.
+pub const MIMC_ROUNDS: usize = 110;
+
+/// The MiMC cipher and hash function, with its round constants.
+#[derive(Clone, Debug)]
+pub struct MiMC {
+    round_constants: Vec<Scalar>,
+}
+
+impl Default for MiMC {
+    fn default() -> Self {
+        MiMC::new()
+    }
+}
+
+impl MiMC {
+    /// Generates the round constants.
+    pub fn new() -> Self {
+        let round_constants = (0..MIMC_ROUNDS)
+            .map(|i| {
+                if i == 0 {
+                    return Scalar::ZERO;
+                }
+                let mut input = b"bulletproofs MiMC-5 round constant".to_vec();
+                input.extend_from_slice(&(i as u64).to_le_bytes());
+                Scalar::hash_from_bytes::<Sha3_512>(&input)
+            })
+            .collect();
+        MiMC { round_constants }
+    }
+
+    /// Encrypts `x` with the key `k`.
+    pub fn encrypt(&self, x: Scalar, k: Scalar) -> Scalar {
+        let x = self.round_constants.iter().fold(x, |x, c| {
+            let t = x + k + c;
+            let t2 = t * t;
+            t2 * t2 * t
+        });
+        x + k
+    }
+
+    /// Compresses the chaining value `h` and the input `x` to a single
+    /// scalar.
+    pub fn compress(&self, h: Scalar, x: Scalar) -> Scalar {
+        self.encrypt(x, h) + h + x
+    }
+
+    /// Hashes the `inputs` to a single scalar.
+    pub fn hash(&self, inputs: &[Scalar]) -> Scalar {
+        inputs
+            .iter()
+            .fold(Scalar::ZERO, |h, x| self.compress(h, *x))
+    }
+
+    /// Returns `x` encrypted with the key `k` inside the constraint
+    /// system.
+    ///
+    /// Costs `330` multipliers and `660` constraints.
+    pub fn encrypt_gadget<CS: ConstraintSystem>(
+        &self,
+        cs: &mut CS,
+        x: LinearCombination,
+        k: LinearCombination,
+    ) -> LinearCombination {
+        let x = self.round_constants.iter().fold(x, |x, c| {
+            let t = x + k.clone() + *c;
+            let (_, _, t2) = cs.multiply(t.clone(), t.clone());
+            let (_, _, t4) = cs.multiply(t2.into(), t2.into());
+            let (_, _, t5) = cs.multiply(t4.into(), t);
+            t5.into()
+        });
+        x + k
+    }
+
+    /// Returns the compression of `h` and `x` inside the constraint
+    /// system.
+    ///
+    /// Costs `330` multipliers and `660` constraints.
+    pub fn compress_gadget<CS: ConstraintSystem>(
+        &self,
+        cs: &mut CS,
+        h: LinearCombination,
+        x: LinearCombination,
+    ) -> LinearCombination {
+        self.encrypt_gadget(cs, x.clone(), h.clone()) + h + x
+    }
+
+    /// Returns the hash of the `inputs` inside the constraint system.
+    ///
+    /// Costs `330n` multipliers and `660n` constraints, for `n` inputs.
+    pub fn hash_gadget<CS: ConstraintSystem>(
+        &self,
+        cs: &mut CS,
+        inputs: Vec<LinearCombination>,
+    ) -> LinearCombination {
+        inputs
+            .into_iter()
+            .fold(LinearCombination::default(), |h, x| {
+                self.compress_gadget(cs, h, x)
+            })
+    }
+}
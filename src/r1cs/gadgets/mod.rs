@@ -0,0 +1,27 @@
+//! Reusable gadgets for building constraint systems.
+//!
+//! Each gadget is a function generic over the
+//! [`ConstraintSystem`](crate::r1cs::ConstraintSystem) trait, so the
+//! same code specifies the constraints for both the
+//! [`Prover`](crate::r1cs::Prover) and the
+//! [`Verifier`](crate::r1cs::Verifier).  Gadgets take the assignments
+//! of their inputs as `Option`s, which the prover passes as `Some` and
+//! the verifier as `None`.
+//!
+//! The cost of each gadget is documented as the number of multipliers
+//! and linear constraints it adds, which are reported by
+//! [`ConstraintSystem::metrics`](crate::r1cs::ConstraintSystem::metrics).
+
+pub mod balance;
+pub mod boolean;
+pub mod comparison;
+pub mod division;
+pub mod membership;
+pub mod merkle;
+pub mod mimc;
+pub mod nonzero;
+pub mod range;
+// The shuffle gadgets need randomized constraints, which are still
+// experimental, so they are not available with `r1cs` alone.
+#[cfg(feature = "yoloproofs")]
+pub mod shuffle;
@@ -0,0 +1,78 @@
+//! Non-zero, inequality and zero-test gadgets.
+//!
+//! A value `v` is non-zero exactly when it has an inverse, so these
+//! gadgets allocate the inverse `v_inv` as a witness and enforce
+//! `v * v_inv = 1`.
+
+use curve25519_dalek::scalar::Scalar;
+
+use crate::errors::R1CSError;
+use crate::r1cs::{ConstraintSystem, LinearCombination, Variable};
+
+/// Enforces that `v` is not zero.
+///
+/// The prover passes the value of `v` as `v_assignment`, and the
+/// verifier passes `None`.
+///
+/// Costs 1 multiplier and 2 constraints.
+pub fn enforce_nonzero<CS: ConstraintSystem>(
+    cs: &mut CS,
+    v: LinearCombination,
+    v_assignment: Option<Scalar>,
+) -> Result<(), R1CSError> {
+    let (l, _, o) = cs.allocate_multiplier(v_assignment.map(|v| (v, v.invert())))?;
+    cs.constrain(l - v);
+    cs.constrain(o - 1u64);
+    Ok(())
+}
+
+/// Enforces that `a` and `b` are not equal.
+///
+/// Costs 1 multiplier and 2 constraints.
+pub fn enforce_not_equal<CS: ConstraintSystem>(
+    cs: &mut CS,
+    a: LinearCombination,
+    a_assignment: Option<Scalar>,
+    b: LinearCombination,
+    b_assignment: Option<Scalar>,
+) -> Result<(), R1CSError> {
+    let d_assignment = a_assignment.and_then(|a| b_assignment.map(|b| a - b));
+    enforce_nonzero(cs, a - b, d_assignment)
+}
+
+/// Returns a boolean variable which is `1` if `v` is zero and `0`
+/// otherwise.
+///
+/// Costs 2 multipliers and 4 constraints.
+pub fn is_zero<CS: ConstraintSystem>(
+    cs: &mut CS,
+    v: LinearCombination,
+    v_assignment: Option<Scalar>,
+) -> Result<Variable, R1CSError> {
+    // o = v * v_inv is 1 if v is non-zero, and 0 if v is zero, for
+    // which any v_inv gives the same o.
+    let (l, _, o) = cs.allocate_multiplier(v_assignment.map(|v| (v, v.invert())))?;
+    cs.constrain(l - v.clone());
+
+    // The result is z = 1 - o.  Enforcing v * z = 0 makes z zero when v
+    // is non-zero, which forces the prover to use the true inverse.
+    let (_, z, vz) = cs.multiply(v, -o + Scalar::ONE);
+    cs.constrain(vz.into());
+
+    Ok(z)
+}
+
+/// Returns a boolean variable which is `1` if `a` and `b` are equal and
+/// `0` otherwise.
+///
+/// Costs 2 multipliers and 4 constraints.
+pub fn is_equal<CS: ConstraintSystem>(
+    cs: &mut CS,
+    a: LinearCombination,
+    a_assignment: Option<Scalar>,
+    b: LinearCombination,
+    b_assignment: Option<Scalar>,
+) -> Result<Variable, R1CSError> {
+    let d_assignment = a_assignment.and_then(|a| b_assignment.map(|b| a - b));
+    is_zero(cs, a - b, d_assignment)
+}
@@ -0,0 +1,109 @@
+//! Bit decomposition and range check gadgets.
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use curve25519_dalek::scalar::Scalar;
+
+use super::boolean::allocate_boolean;
+use crate::errors::R1CSError;
+use crate::r1cs::{ConstraintSystem, LinearCombination, Variable};
+
+/// The largest bitsize supported by [`range_check`].
+///
+/// Every value below `2^252` is smaller than the order of the scalar
+/// field, so its bit decomposition is unique.  Wider decompositions
+/// can represent every scalar, and would not constrain the range.
+pub const MAX_BITSIZE: usize = 252;
+
+/// Allocates `n` boolean variables holding the bits of `assignment`,
+/// least significant bit first.
+///
+/// Each variable is constrained to be `0` or `1`, but not to any
+/// value: see [`bit_decomposition`] to constrain the bits to an
+/// existing value.  Bits of the assignment above `n` are ignored.
+///
+/// Costs `n` multipliers and `2n` constraints.
+pub fn allocate_bits<CS: ConstraintSystem>(
+    cs: &mut CS,
+    assignment: Option<Scalar>,
+    n: usize,
+) -> Result<Vec<Variable>, R1CSError> {
+    let bytes = assignment.map(|v| v.to_bytes());
+    (0..n)
+        .map(|i| {
+            let bit = bytes.map(|bytes| i < 256 && (bytes[i / 8] >> (i % 8)) & 1 == 1);
+            allocate_boolean(cs, bit)
+        })
+        .collect()
+}
+
+/// Returns `2^n` as a scalar.
+pub(super) fn two_pow(n: usize) -> Scalar {
+    (0..n).fold(Scalar::ONE, |acc, _| acc + acc)
+}
+
+/// Returns the linear combination `Sum(b_i * 2^i)` of the `bits`,
+/// least significant bit first.
+pub fn pack_bits(bits: &[Variable]) -> LinearCombination {
+    let mut exp_2 = Scalar::ONE;
+    bits.iter()
+        .map(|b| {
+            let term = (*b, exp_2);
+            exp_2 = exp_2 + exp_2;
+            term
+        })
+        .collect()
+}
+
+/// Decomposes `v` into `n` bits, least significant bit first, and
+/// returns the bit variables for use in further constraints.
+///
+/// Enforces that each bit is `0` or `1` and that
+/// `v = Sum(b_i * 2^i, i = 0..n-1)`.  The prover passes the value of
+/// `v` as `v_assignment`, and the verifier passes `None`.
+///
+/// For `n <= MAX_BITSIZE` this is the same statement as
+/// [`range_check`].  Larger `n` are allowed, but every scalar then has
+/// several decompositions, so the bits are not unique.
+///
+/// Costs `n` multipliers and `2n + 1` constraints.
+pub fn bit_decomposition<CS: ConstraintSystem>(
+    cs: &mut CS,
+    v: LinearCombination,
+    v_assignment: Option<Scalar>,
+    n: usize,
+) -> Result<Vec<Variable>, R1CSError> {
+    let bits = allocate_bits(cs, v_assignment, n)?;
+
+    // Enforce that v = Sum(b_i * 2^i, i = 0..n-1)
+    cs.constrain(v - pack_bits(&bits));
+
+    Ok(bits)
+}
+
+/// Enforces that `v` is in the range `[0, 2^n)`, and returns the bits
+/// of `v`, least significant bit first.
+///
+/// This is the statement proved by a
+/// [`RangeProof`](crate::RangeProof), but for any `n` up to
+/// [`MAX_BITSIZE`], and over any linear combination of variables
+/// rather than a single commitment.
+///
+/// Returns a [`R1CSError::GadgetError`] if `n` is larger than
+/// [`MAX_BITSIZE`].
+///
+/// Costs `n` multipliers and `2n + 1` constraints.
+pub fn range_check<CS: ConstraintSystem>(
+    cs: &mut CS,
+    v: LinearCombination,
+    v_assignment: Option<Scalar>,
+    n: usize,
+) -> Result<Vec<Variable>, R1CSError> {
+    if n > MAX_BITSIZE {
+        return Err(R1CSError::GadgetError {
+            description: "range_check bitsize exceeds MAX_BITSIZE".into(),
+        });
+    }
+    bit_decomposition(cs, v, v_assignment, n)
+}
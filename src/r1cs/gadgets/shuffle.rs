@@ -0,0 +1,255 @@
+//! Shuffle gadgets, proving that one list of committed values or
+//! records is a permutation of another.
+//!
+//! A list `y` is a permutation of `x` exactly when the polynomials
+//! `Prod(x_i - z)` and `Prod(y_i - z)` are equal, which is checked at a
+//! random challenge `z`.  The gadgets therefore use randomized
+//! constraints, and are only available with the `yoloproofs` feature.
+//!
+//! Randomized constraints are experimental: they are not covered by
+//! semver compatibility and are unsuitable for deployment.  Using this
+//! module in production therefore still requires enabling
+//! `yoloproofs`, until randomized constraints are stabilized under the
+//! `r1cs` feature.
+//!
+//! Records of several columns are first compressed to single values
+//! `Sum(w^j * x_ij)` with a second random challenge `w`.
+//!
+//! Shuffling `k >= 2` records costs `2(k - 1)` multipliers and `4k - 3`
+//! constraints, independently of the width of the records.
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate rand;
+
+#[cfg(feature = "std")]
+use self::rand::thread_rng;
+use alloc::vec;
+use alloc::vec::Vec;
+use curve25519_dalek::ristretto::CompressedRistretto;
+use curve25519_dalek::scalar::Scalar;
+use rand_core::{CryptoRng, RngCore};
+
+use crate::errors::R1CSError;
+use crate::generators::{BulletproofGens, PedersenGens};
+use crate::r1cs::{
+    ConstraintSystem, LinearCombination, Prover, R1CSProof, RandomizableConstraintSystem,
+    RandomizedConstraintSystem, Variable, Verifier,
+};
+use crate::transcript::{TranscriptBackend, TranscriptProtocol};
+
+/// Enforces that the values `y` are a permutation of the values `x`.
+///
+/// This is [`shuffle_tuples`] for records of a single column.
+pub fn shuffle<CS: RandomizableConstraintSystem>(
+    cs: &mut CS,
+    x: Vec<Variable>,
+    y: Vec<Variable>,
+) -> Result<(), R1CSError> {
+    let records = |vars: Vec<Variable>| vars.into_iter().map(|v| vec![v]).collect();
+    shuffle_tuples(cs, records(x), records(y))
+}
+
+/// Enforces that the records `y` are a permutation of the records `x`,
+/// where each record is a tuple of variables of the same width.
+///
+/// The gadget appends a domain separator with the number and width of
+/// the records to the transcript, so that the shuffle challenges are
+/// bound to its parameters.
+///
+/// Returns a [`R1CSError::GadgetError`] if `x` and `y` have different
+/// lengths, or if the records are empty or of different widths.
+pub fn shuffle_tuples<CS: RandomizableConstraintSystem>(
+    cs: &mut CS,
+    x: Vec<Vec<Variable>>,
+    y: Vec<Vec<Variable>>,
+) -> Result<(), R1CSError> {
+    if x.len() != y.len() {
+        return Err(R1CSError::GadgetError {
+            description: "shuffle inputs and outputs have different lengths".into(),
+        });
+    }
+    let k = x.len();
+    let width = x.first().map_or(1, |record| record.len());
+    if width == 0 || x.iter().chain(y.iter()).any(|record| record.len() != width) {
+        return Err(R1CSError::GadgetError {
+            description: "shuffle records must be nonempty and of the same width".into(),
+        });
+    }
+
+    cs.transcript().shuffle_domain_sep(k as u64, width as u64);
+
+    if k == 0 {
+        return Ok(());
+    }
+    if k == 1 {
+        for (x, y) in x[0].iter().zip(y[0].iter()) {
+            cs.constrain(*y - *x);
+        }
+        return Ok(());
+    }
+
+    cs.specify_randomized_constraints(move |cs| {
+        let (x, y) = if width == 1 {
+            let column =
+                |records: Vec<Vec<Variable>>| records.into_iter().map(|r| r[0].into()).collect();
+            (column(x), column(y))
+        } else {
+            let w = cs.challenge_scalar(b"shuffle tuple challenge");
+            (compress(x, w), compress(y, w))
+        };
+
+        let z = cs.challenge_scalar(b"shuffle challenge");
+        let x_product = product(cs, x, z);
+        let y_product = product(cs, y, z);
+
+        // Constrain the products of the two sides to be equal
+        cs.constrain(x_product - y_product);
+
+        Ok(())
+    })
+}
+
+/// Compresses each record to the linear combination `Sum(w^j * r_j)`.
+fn compress(records: Vec<Vec<Variable>>, w: Scalar) -> Vec<LinearCombination> {
+    records
+        .into_iter()
+        .map(|record| {
+            let mut w_j = Scalar::ONE;
+            record
+                .into_iter()
+                .map(|v| {
+                    let term = (v, w_j);
+                    w_j *= w;
+                    term
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns the output of the multipliers computing `Prod(v_i - z)`,
+/// for at least two values `v`.
+fn product<CS: RandomizedConstraintSystem>(
+    cs: &mut CS,
+    mut v: Vec<LinearCombination>,
+    z: Scalar,
+) -> Variable {
+    // Make last multiplier for i = k-1 and k-2
+    let last = v.pop().unwrap() - z;
+    let second_last = v.pop().unwrap() - z;
+    let (_, _, last_out) = cs.multiply(last, second_last);
+
+    // Make multipliers for i == [0, k-3]
+    v.into_iter().rev().fold(last_out, |prev_out, v_i| {
+        let (_, _, o) = cs.multiply(prev_out.into(), v_i - z);
+        o
+    })
+}
+
+/// Creates a proof that the `output` records are a permutation of the
+/// `input` records.
+///
+/// Each record is given as the openings `(value, blinding)` of the
+/// commitments to its columns, which the caller has already published
+/// as [`PedersenGens::commit`]`(value, blinding)`.
+///
+/// This is a convenience wrapper around [`prove_with_rng`], passing in
+/// a threadsafe RNG.
+#[cfg(feature = "std")]
+pub fn prove<T: TranscriptBackend>(
+    pc_gens: &PedersenGens,
+    bp_gens: &BulletproofGens,
+    transcript: T,
+    input: &[Vec<(Scalar, Scalar)>],
+    output: &[Vec<(Scalar, Scalar)>],
+) -> Result<R1CSProof, R1CSError> {
+    prove_with_rng(
+        pc_gens,
+        bp_gens,
+        transcript,
+        input,
+        output,
+        &mut thread_rng(),
+    )
+}
+
+/// Same as [`prove`], but uses `rng` as the external randomness of
+/// the proof.
+pub fn prove_with_rng<T: TranscriptBackend, R: RngCore + CryptoRng>(
+    pc_gens: &PedersenGens,
+    bp_gens: &BulletproofGens,
+    transcript: T,
+    input: &[Vec<(Scalar, Scalar)>],
+    output: &[Vec<(Scalar, Scalar)>],
+    rng: &mut R,
+) -> Result<R1CSProof, R1CSError> {
+    let mut prover = Prover::new(pc_gens, transcript);
+    let mut commit = |records: &[Vec<(Scalar, Scalar)>]| -> Vec<Vec<Variable>> {
+        records
+            .iter()
+            .map(|record| {
+                record
+                    .iter()
+                    .map(|(v, v_blinding)| prover.commit(*v, *v_blinding).1)
+                    .collect()
+            })
+            .collect()
+    };
+    let (x, y) = (commit(input), commit(output));
+
+    shuffle_tuples(&mut prover, x, y)?;
+
+    prover.prove_with_rng(bp_gens, rng)
+}
+
+/// Verifies a proof that the records committed to in
+/// `output_commitments` are a permutation of the records committed to
+/// in `input_commitments`.
+///
+/// This is a convenience wrapper around [`verify_with_rng`], passing
+/// in a threadsafe RNG.
+#[cfg(feature = "std")]
+pub fn verify<T: TranscriptBackend>(
+    proof: &R1CSProof,
+    pc_gens: &PedersenGens,
+    bp_gens: &BulletproofGens,
+    transcript: T,
+    input_commitments: &[Vec<CompressedRistretto>],
+    output_commitments: &[Vec<CompressedRistretto>],
+) -> Result<(), R1CSError> {
+    verify_with_rng(
+        proof,
+        pc_gens,
+        bp_gens,
+        transcript,
+        input_commitments,
+        output_commitments,
+        &mut thread_rng(),
+    )
+}
+
+/// Same as [`verify`], but uses `rng` as the external randomness for
+/// the random weight combining the verification checks.
+pub fn verify_with_rng<T: TranscriptBackend, R: RngCore + CryptoRng>(
+    proof: &R1CSProof,
+    pc_gens: &PedersenGens,
+    bp_gens: &BulletproofGens,
+    transcript: T,
+    input_commitments: &[Vec<CompressedRistretto>],
+    output_commitments: &[Vec<CompressedRistretto>],
+    rng: &mut R,
+) -> Result<(), R1CSError> {
+    let mut verifier = Verifier::new(transcript);
+    let mut commit = |records: &[Vec<CompressedRistretto>]| -> Vec<Vec<Variable>> {
+        records
+            .iter()
+            .map(|record| record.iter().map(|c| verifier.commit(*c)).collect())
+            .collect()
+    };
+    let (x, y) = (commit(input_commitments), commit(output_commitments));
+
+    shuffle_tuples(&mut verifier, x, y)?;
+
+    verifier.verify_with_rng(proof, pc_gens, bp_gens, rng)
+}
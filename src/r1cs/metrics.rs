@@ -1,3 +1,8 @@
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
 /// A struct that contains metrics about a constraint system.
 ///
 /// See [`ConstraintSystem::metrics`](::r1cs::ConstraintSystem::metrics).
@@ -11,4 +16,26 @@ pub struct Metrics {
     pub phase_one_constraints: usize,
     /// Number of linear constraints added in the randomization phase.
     pub phase_two_constraints: usize,
+    /// Metrics of each namespace entered with
+    /// [`ConstraintSystem::namespace`](crate::r1cs::ConstraintSystem::namespace),
+    /// in the order in which they were first entered.  Empty if the
+    /// constraint system doesn't use namespaces.
+    pub namespaces: Vec<NamespaceMetrics>,
+}
+
+/// Metrics about a single namespace of a constraint system.
+///
+/// See [`Metrics::namespaces`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamespaceMetrics {
+    /// The full path of the namespace, with the names of the nested
+    /// namespaces separated by `/`.  The `/` and `\` characters in the
+    /// names are escaped with a `\`.
+    pub path: String,
+    /// Number of multipliers allocated in the namespace, including its
+    /// nested namespaces.
+    pub multipliers: usize,
+    /// Number of linear constraints added in the namespace, including
+    /// its nested namespaces.
+    pub constraints: usize,
 }
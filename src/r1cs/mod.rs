@@ -3,20 +3,26 @@
 #[cfg_attr(feature = "docs", doc(include = "../../docs/cs-proof.md"))]
 mod notes {}
 
+mod circuit;
 mod constraint_system;
+mod export;
+pub mod gadgets;
 mod linear_combination;
 mod metrics;
+mod namespace;
 mod proof;
 mod prover;
 mod verifier;
 
-pub use self::constraint_system::{
-    ConstraintSystem, RandomizableConstraintSystem, RandomizedConstraintSystem,
-};
+pub use self::circuit::{CircuitDescription, RecordingTranscript};
+pub use self::constraint_system::ConstraintSystem;
+#[cfg(feature = "yoloproofs")]
+pub use self::constraint_system::{RandomizableConstraintSystem, RandomizedConstraintSystem};
+pub use self::export::ExportedCircuit;
 pub use self::linear_combination::{LinearCombination, Variable};
-pub use self::metrics::Metrics;
+pub use self::metrics::{Metrics, NamespaceMetrics};
 pub use self::proof::R1CSProof;
-pub use self::prover::Prover;
+pub use self::prover::{Prover, UnsatisfiedConstraint};
 pub use self::verifier::Verifier;
 
 pub use crate::errors::R1CSError;
@@ -0,0 +1,171 @@
+//! Bookkeeping of the namespaces entered while building a constraint system.
+
+extern crate alloc;
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem;
+
+use super::NamespaceMetrics;
+
+/// Records the namespace of every multiplier and constraint added to a
+/// constraint system.
+///
+/// Constraint systems only create this on the first call to
+/// [`ConstraintSystem::push_namespace`](super::ConstraintSystem::push_namespace),
+/// so that circuits which don't use namespaces don't pay for them.
+#[derive(Clone, Debug)]
+pub(super) struct Namespaces {
+    /// The full paths of the namespaces entered so far, indexed by id.
+    /// The root namespace has id 0 and an empty path.
+    paths: Vec<String>,
+    /// The id of each path in `paths`, for finding namespaces which
+    /// are entered again.
+    ids: BTreeMap<String, usize>,
+    /// The id of the parent of each namespace.  The root is its own parent.
+    parents: Vec<usize>,
+    /// The ids of the currently open namespaces, innermost last.
+    stack: Vec<usize>,
+    /// The namespace id of each multiplier.
+    multipliers: Vec<usize>,
+    /// The namespace id of each constraint.
+    constraints: Vec<usize>,
+}
+
+impl Default for Namespaces {
+    fn default() -> Self {
+        Namespaces {
+            paths: vec![String::new()],
+            ids: BTreeMap::new(),
+            parents: vec![0],
+            stack: Vec::new(),
+            multipliers: Vec::new(),
+            constraints: Vec::new(),
+        }
+    }
+}
+
+impl Namespaces {
+    fn current(&self) -> usize {
+        self.stack.last().cloned().unwrap_or(0)
+    }
+
+    /// Enters the namespace `name` inside the current namespace.
+    ///
+    /// The `/` and `\` characters of `name` are escaped with a `\`, so
+    /// that a name containing `/` is not confused with nested namespaces.
+    pub(super) fn push(&mut self, name: String) {
+        let parent = self.current();
+        let mut path = self.paths[parent].clone();
+        if parent != 0 {
+            path.push('/');
+        }
+        for c in name.chars() {
+            if c == '/' || c == '\\' {
+                path.push('\\');
+            }
+            path.push(c);
+        }
+        let id = match self.ids.get(&path) {
+            Some(&id) => id,
+            None => {
+                let id = self.paths.len();
+                self.ids.insert(path.clone(), id);
+                self.paths.push(path);
+                self.parents.push(parent);
+                id
+            }
+        };
+        self.stack.push(id);
+    }
+
+    /// Leaves the current namespace.
+    pub(super) fn pop(&mut self) {
+        self.stack.pop();
+    }
+
+    /// Returns the currently open namespaces, so that they can be
+    /// reopened with [`Namespaces::restore`].
+    pub(super) fn save(&self) -> Vec<usize> {
+        self.stack.clone()
+    }
+
+    /// Reopens the namespaces returned by [`Namespaces::save`], and
+    /// returns the namespaces which were open before.
+    pub(super) fn restore(&mut self, stack: Vec<usize>) -> Vec<usize> {
+        mem::replace(&mut self.stack, stack)
+    }
+
+    /// Records that the multiplier `index` was allocated in the
+    /// current namespace.  Multipliers allocated before namespaces were
+    /// in use belong to the root namespace.
+    pub(super) fn record_multiplier(&mut self, index: usize) {
+        let current = self.current();
+        Self::record(&mut self.multipliers, index, current);
+    }
+
+    /// Records that the constraint `index` was added in the current
+    /// namespace.
+    pub(super) fn record_constraint(&mut self, index: usize) {
+        let current = self.current();
+        Self::record(&mut self.constraints, index, current);
+    }
+
+    fn record(ids: &mut Vec<usize>, index: usize, id: usize) {
+        if ids.len() <= index {
+            ids.resize(index + 1, 0);
+        }
+        ids[index] = id;
+    }
+
+    /// Returns the namespace path of the multiplier `index`, or `None`
+    /// if it is in the root namespace.
+    pub(super) fn multiplier_label(&self, index: usize) -> Option<&str> {
+        self.label(self.multipliers.get(index))
+    }
+
+    /// Returns the namespace path of the constraint `index`, or `None`
+    /// if it is in the root namespace.
+    pub(super) fn constraint_label(&self, index: usize) -> Option<&str> {
+        self.label(self.constraints.get(index))
+    }
+
+    fn label(&self, id: Option<&usize>) -> Option<&str> {
+        match id {
+            Some(&id) if id != 0 => Some(&self.paths[id]),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of multipliers and constraints in each
+    /// namespace, including those of the namespaces nested in it.
+    pub(super) fn metrics(&self) -> Vec<NamespaceMetrics> {
+        let mut metrics: Vec<_> = self.paths[1..]
+            .iter()
+            .map(|path| NamespaceMetrics {
+                path: path.clone(),
+                multipliers: 0,
+                constraints: 0,
+            })
+            .collect();
+
+        for &id in self.multipliers.iter() {
+            self.for_each_ancestor(id, |ancestor| metrics[ancestor - 1].multipliers += 1);
+        }
+        for &id in self.constraints.iter() {
+            self.for_each_ancestor(id, |ancestor| metrics[ancestor - 1].constraints += 1);
+        }
+
+        metrics
+    }
+
+    /// Calls `f` on `id` and each of its ancestors, apart from the root.
+    fn for_each_ancestor<F: FnMut(usize)>(&self, mut id: usize, mut f: F) {
+        while id != 0 {
+            f(id);
+            id = self.parents[id];
+        }
+    }
+}
@@ -1,6 +1,9 @@
 #![allow(non_snake_case)]
 //! Definition of the proof struct.
 
+extern crate alloc;
+
+use alloc::vec::Vec;
 use curve25519_dalek::ristretto::CompressedRistretto;
 use curve25519_dalek::scalar::Scalar;
 use curve25519_dalek::traits::{Identity, IsIdentity};
@@ -16,21 +19,29 @@ const ONE_PHASE_COMMITMENTS: u8 = 0;
 const TWO_PHASE_COMMITMENTS: u8 = 1;
 
 /// A proof of some statement specified by a
-/// [`ConstraintSystem`](::r1cs::ConstraintSystem).
+/// [`ConstraintSystem`](crate::r1cs::ConstraintSystem).
 ///
 /// Statements are specified by writing gadget functions which add
-/// constraints to a [`ConstraintSystem`](::r1cs::ConstraintSystem)
+/// constraints to a [`ConstraintSystem`](crate::r1cs::ConstraintSystem)
 /// implementation.  To construct an [`R1CSProof`], a prover constructs
-/// a [`ProverCS`](::r1cs::ProverCS), then passes it to gadget
+/// a [`Prover`](crate::r1cs::Prover), then passes it to gadget
 /// functions to build the constraint system, then consumes the
 /// constraint system using
-/// [`ProverCS::prove`](::r1cs::ProverCS::prove) to produce an
+/// [`Prover::prove`](crate::r1cs::Prover::prove) to produce an
 /// [`R1CSProof`].  To verify an [`R1CSProof`], a verifier constructs a
-/// [`VerifierCS`](::r1cs::VerifierCS), then passes it to the same
+/// [`Verifier`](crate::r1cs::Verifier), then passes it to the same
 /// gadget functions to (re)build the constraint system, then consumes
 /// the constraint system using
-/// [`VerifierCS::verify`](::r1cs::VerifierCS::verify) to verify the
+/// [`Verifier::verify`](crate::r1cs::Verifier::verify) to verify the
 /// proof.
+///
+/// # Stability
+///
+/// With the `r1cs` feature, the encoding produced by
+/// [`R1CSProof::to_bytes`] and the transcript domain separators
+/// (`"r1cs v1"`, `"r1cs-1phase"` and `"r1cs-2phase"`) are frozen:
+/// proofs created by this version will verify with later versions
+/// of the crate.
 #[derive(Clone, Debug)]
 #[allow(non_snake_case)]
 pub struct R1CSProof {
@@ -1,23 +1,31 @@
 #![allow(non_snake_case)]
 
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate rand;
+
+#[cfg(feature = "std")]
+use self::rand::thread_rng;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
 use clear_on_drop::clear::Clear;
-use core::borrow::BorrowMut;
 use core::mem;
 use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
 use curve25519_dalek::scalar::Scalar;
 use curve25519_dalek::traits::{Identity, MultiscalarMul};
-use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
 
-use super::{
-    ConstraintSystem, LinearCombination, R1CSProof, RandomizableConstraintSystem,
-    RandomizedConstraintSystem, Variable,
-};
+use super::constraint_system::{RandomizableConstraintSystem, RandomizedConstraintSystem};
+use super::namespace::Namespaces;
+use super::{ConstraintSystem, LinearCombination, R1CSProof, Variable};
 
 use crate::errors::R1CSError;
 use crate::generators::{BulletproofGens, PedersenGens};
 use crate::inner_product_proof::InnerProductProof;
 use crate::r1cs::Metrics;
-use crate::transcript::TranscriptProtocol;
+use crate::transcript::{TranscriptBackend, TranscriptProtocol, WitnessRngBuilder};
 
 /// A [`ConstraintSystem`] implementation for use by the prover.
 ///
@@ -28,7 +36,7 @@ use crate::transcript::TranscriptProtocol;
 /// When all constraints are added, the proving code calls `prove`
 /// which consumes the `Prover` instance, samples random challenges
 /// that instantiate the randomized constraints, and creates a complete proof.
-pub struct Prover<'g, T: BorrowMut<Transcript>> {
+pub struct Prover<'g, T: TranscriptBackend> {
     transcript: T,
     pc_gens: &'g PedersenGens,
     /// The constraints accumulated so far.
@@ -43,6 +51,10 @@ pub struct Prover<'g, T: BorrowMut<Transcript>> {
 
     /// Index of a pending multiplier that's not fully assigned yet.
     pending_multiplier: Option<usize>,
+
+    /// The namespaces of the multipliers and constraints, recorded
+    /// once the first namespace is entered.
+    namespaces: Option<Namespaces>,
 }
 
 /// Separate struct to implement Drop trait for (for zeroing),
@@ -60,6 +72,27 @@ struct Secrets {
     v_blinding: Vec<Scalar>,
 }
 
+/// A constraint which is not satisfied by the prover's assignments, as
+/// reported by [`Prover::check_satisfied`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UnsatisfiedConstraint {
+    /// A multiplier whose output is not the product of its inputs.
+    Multiplier {
+        /// The index of the multiplier, as in [`Variable::MultiplierOutput`].
+        index: usize,
+        /// The path of the namespace of the multiplier, if any.
+        label: Option<String>,
+    },
+    /// A linear constraint which does not evaluate to zero.
+    Linear {
+        /// The index of the constraint, in the order in which the
+        /// constraints were added, counting the randomized ones last.
+        index: usize,
+        /// The path of the namespace of the constraint, if any.
+        label: Option<String>,
+    },
+}
+
 /// Prover in the randomizing phase.
 ///
 /// Note: this type is exported because it is used to specify the associated type
@@ -67,7 +100,7 @@ struct Secrets {
 /// monomorphize the closures for the proving and verifying code.
 /// However, this type cannot be instantiated by the user and therefore can only be used within
 /// the callback provided to `specify_randomized_constraints`.
-pub struct RandomizingProver<'g, T: BorrowMut<Transcript>> {
+pub struct RandomizingProver<'g, T: TranscriptBackend> {
     prover: Prover<'g, T>,
 }
 
@@ -95,9 +128,11 @@ impl Drop for Secrets {
     }
 }
 
-impl<'g, T: BorrowMut<Transcript>> ConstraintSystem for Prover<'g, T> {
-    fn transcript(&mut self) -> &mut Transcript {
-        self.transcript.borrow_mut()
+impl<'g, T: TranscriptBackend> ConstraintSystem for Prover<'g, T> {
+    type Transcript = T;
+
+    fn transcript(&mut self) -> &mut T {
+        &mut self.transcript
     }
 
     fn multiply(
@@ -110,6 +145,8 @@ impl<'g, T: BorrowMut<Transcript>> ConstraintSystem for Prover<'g, T> {
         let r = self.eval(&right);
         let o = l * r;
 
+        self.record_multiplier(self.secrets.a_L.len());
+
         // Create variables for l,r,o ...
         let l_var = Variable::MultiplierLeft(self.secrets.a_L.len());
         let r_var = Variable::MultiplierRight(self.secrets.a_R.len());
@@ -134,6 +171,7 @@ impl<'g, T: BorrowMut<Transcript>> ConstraintSystem for Prover<'g, T> {
         match self.pending_multiplier {
             None => {
                 let i = self.secrets.a_L.len();
+                self.record_multiplier(i);
                 self.pending_multiplier = Some(i);
                 self.secrets.a_L.push(scalar);
                 self.secrets.a_R.push(Scalar::ZERO);
@@ -156,6 +194,8 @@ impl<'g, T: BorrowMut<Transcript>> ConstraintSystem for Prover<'g, T> {
         let (l, r) = input_assignments.ok_or(R1CSError::MissingAssignment)?;
         let o = l * r;
 
+        self.record_multiplier(self.secrets.a_L.len());
+
         // Create variables for l,r,o ...
         let l_var = Variable::MultiplierLeft(self.secrets.a_L.len());
         let r_var = Variable::MultiplierRight(self.secrets.a_R.len());
@@ -174,31 +214,67 @@ impl<'g, T: BorrowMut<Transcript>> ConstraintSystem for Prover<'g, T> {
             constraints: self.constraints.len() + self.deferred_constraints.len(),
             phase_one_constraints: self.constraints.len(),
             phase_two_constraints: self.deferred_constraints.len(),
+            namespaces: self
+                .namespaces
+                .as_ref()
+                .map(Namespaces::metrics)
+                .unwrap_or_default(),
         }
     }
 
     fn constrain(&mut self, lc: LinearCombination) {
         // TODO: check that the linear combinations are valid
         // (e.g. that variables are valid, that the linear combination evals to 0 for prover, etc).
+        if let Some(namespaces) = &mut self.namespaces {
+            namespaces.record_constraint(self.constraints.len());
+        }
         self.constraints.push(lc);
     }
+
+    fn push_namespace<N, NR>(&mut self, name_fn: N)
+    where
+        N: FnOnce() -> NR,
+        NR: Into<String>,
+    {
+        self.namespaces
+            .get_or_insert_with(Namespaces::default)
+            .push(name_fn().into());
+    }
+
+    fn pop_namespace(&mut self) {
+        if let Some(namespaces) = &mut self.namespaces {
+            namespaces.pop();
+        }
+    }
 }
 
-impl<'g, T: BorrowMut<Transcript>> RandomizableConstraintSystem for Prover<'g, T> {
+impl<'g, T: TranscriptBackend> RandomizableConstraintSystem for Prover<'g, T> {
     type RandomizedCS = RandomizingProver<'g, T>;
 
     fn specify_randomized_constraints<F>(&mut self, callback: F) -> Result<(), R1CSError>
     where
         F: 'static + FnOnce(&mut Self::RandomizedCS) -> Result<(), R1CSError>,
     {
-        self.deferred_constraints.push(Box::new(callback));
+        // Add the randomized constraints in the namespaces in which
+        // they are specified, rather than those open at the end of the
+        // first phase.
+        let namespaces = self.namespaces.as_ref().map(Namespaces::save);
+        self.deferred_constraints
+            .push(Box::new(move |cs: &mut RandomizingProver<'g, T>| {
+                let outer = cs.prover.restore_namespaces(namespaces);
+                let result = callback(cs);
+                cs.prover.restore_namespaces(outer);
+                result
+            }));
         Ok(())
     }
 }
 
-impl<'g, T: BorrowMut<Transcript>> ConstraintSystem for RandomizingProver<'g, T> {
-    fn transcript(&mut self) -> &mut Transcript {
-        self.prover.transcript.borrow_mut()
+impl<'g, T: TranscriptBackend> ConstraintSystem for RandomizingProver<'g, T> {
+    type Transcript = T;
+
+    fn transcript(&mut self) -> &mut T {
+        &mut self.prover.transcript
     }
 
     fn multiply(
@@ -227,15 +303,27 @@ impl<'g, T: BorrowMut<Transcript>> ConstraintSystem for RandomizingProver<'g, T>
     fn constrain(&mut self, lc: LinearCombination) {
         self.prover.constrain(lc)
     }
+
+    fn push_namespace<N, NR>(&mut self, name_fn: N)
+    where
+        N: FnOnce() -> NR,
+        NR: Into<String>,
+    {
+        self.prover.push_namespace(name_fn)
+    }
+
+    fn pop_namespace(&mut self) {
+        self.prover.pop_namespace()
+    }
 }
 
-impl<'g, T: BorrowMut<Transcript>> RandomizedConstraintSystem for RandomizingProver<'g, T> {
+impl<'g, T: TranscriptBackend> RandomizedConstraintSystem for RandomizingProver<'g, T> {
     fn challenge_scalar(&mut self, label: &'static [u8]) -> Scalar {
-        self.prover.transcript.borrow_mut().challenge_scalar(label)
+        self.prover.transcript.challenge_scalar(label)
     }
 }
 
-impl<'g, T: BorrowMut<Transcript>> Prover<'g, T> {
+impl<'g, T: TranscriptBackend> Prover<'g, T> {
     /// Construct an empty constraint system with specified external
     /// input variables.
     ///
@@ -247,7 +335,8 @@ impl<'g, T: BorrowMut<Transcript>> Prover<'g, T> {
     /// the number of multiplication constraints that will eventually
     /// be added into the constraint system.
     ///
-    /// The `transcript` parameter is a Merlin proof transcript.  The
+    /// The `transcript` parameter is a proof transcript implementing
+    /// [`TranscriptBackend`], such as a Merlin transcript.  The
     /// `ProverCS` holds onto the `&mut Transcript` until it consumes
     /// itself during [`ProverCS::prove`], releasing its borrow of the
     /// transcript.  This ensures that the transcript cannot be
@@ -257,7 +346,7 @@ impl<'g, T: BorrowMut<Transcript>> Prover<'g, T> {
     ///
     /// Returns a new `Prover` instance.
     pub fn new(pc_gens: &'g PedersenGens, mut transcript: T) -> Self {
-        transcript.borrow_mut().r1cs_domain_sep();
+        transcript.r1cs_domain_sep();
 
         Prover {
             pc_gens,
@@ -272,6 +361,22 @@ impl<'g, T: BorrowMut<Transcript>> Prover<'g, T> {
             constraints: Vec::new(),
             deferred_constraints: Vec::new(),
             pending_multiplier: None,
+            namespaces: None,
+        }
+    }
+
+    /// Reopens the namespaces returned by [`Namespaces::save`], or the
+    /// root namespace for `None`, and returns the namespaces which were
+    /// open before.
+    fn restore_namespaces(&mut self, saved: Option<Vec<usize>>) -> Option<Vec<usize>> {
+        self.namespaces
+            .as_mut()
+            .map(|namespaces| namespaces.restore(saved.unwrap_or_default()))
+    }
+
+    fn record_multiplier(&mut self, index: usize) {
+        if let Some(namespaces) = &mut self.namespaces {
+            namespaces.record_multiplier(index);
         }
     }
 
@@ -299,7 +404,7 @@ impl<'g, T: BorrowMut<Transcript>> Prover<'g, T> {
 
         // Add the commitment to the transcript.
         let V = self.pc_gens.commit(v, v_blinding).compress();
-        self.transcript.borrow_mut().append_point(b"V", &V);
+        self.transcript.append_point(b"V", &V);
 
         (V, Variable::Committed(i))
     }
@@ -371,6 +476,46 @@ impl<'g, T: BorrowMut<Transcript>> Prover<'g, T> {
             .sum()
     }
 
+    /// Consume this `ConstraintSystem` to check that its assignments
+    /// satisfy every multiplier and every linear constraint, returning
+    /// the constraints which are not satisfied.
+    ///
+    /// This is a debugging aid for gadgets: a prover with unsatisfied
+    /// constraints still produces a proof, which then fails to verify
+    /// without saying why.
+    ///
+    /// The randomized constraints are instantiated by calling the
+    /// deferred callbacks, as during proving.  Their challenges are
+    /// drawn without committing the low-level variables first, so they
+    /// differ from the challenges of an actual proof.
+    pub fn check_satisfied(self) -> Result<Vec<UnsatisfiedConstraint>, R1CSError> {
+        let prover = self.create_randomized_constraints()?;
+        let secrets = &prover.secrets;
+        let namespaces = prover.namespaces.as_ref();
+
+        let multipliers = (0..secrets.a_O.len())
+            .filter(|&i| secrets.a_L[i] * secrets.a_R[i] != secrets.a_O[i])
+            .map(|index| UnsatisfiedConstraint::Multiplier {
+                index,
+                label: namespaces
+                    .and_then(|ns| ns.multiplier_label(index))
+                    .map(String::from),
+            });
+        let constraints = prover
+            .constraints
+            .iter()
+            .enumerate()
+            .filter(|(_, lc)| prover.eval(lc) != Scalar::ZERO)
+            .map(|(index, _)| UnsatisfiedConstraint::Linear {
+                index,
+                label: namespaces
+                    .and_then(|ns| ns.constraint_label(index))
+                    .map(String::from),
+            });
+
+        Ok(multipliers.chain(constraints).collect())
+    }
+
     /// Calls all remembered callbacks with an API that
     /// allows generating challenge scalars.
     fn create_randomized_constraints(mut self) -> Result<Self, R1CSError> {
@@ -378,10 +523,10 @@ impl<'g, T: BorrowMut<Transcript>> Prover<'g, T> {
         self.pending_multiplier = None;
 
         if self.deferred_constraints.len() == 0 {
-            self.transcript.borrow_mut().r1cs_1phase_domain_sep();
+            self.transcript.r1cs_1phase_domain_sep();
             Ok(self)
         } else {
-            self.transcript.borrow_mut().r1cs_2phase_domain_sep();
+            self.transcript.r1cs_2phase_domain_sep();
             // Note: the wrapper could've used &mut instead of ownership,
             // but specifying lifetimes for boxed closures is not going to be nice,
             // so we move the self into wrapper and then move it back out afterwards.
@@ -395,25 +540,53 @@ impl<'g, T: BorrowMut<Transcript>> Prover<'g, T> {
     }
 
     /// Consume this `ConstraintSystem` to produce a proof.
+    ///
+    /// This is a convenience wrapper around [`Prover::prove_with_rng`],
+    /// passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
     pub fn prove(self, bp_gens: &BulletproofGens) -> Result<R1CSProof, R1CSError> {
-        self.prove_and_return_transcript(bp_gens)
+        self.prove_with_rng(bp_gens, &mut thread_rng())
+    }
+
+    /// Consume this `ConstraintSystem` to produce a proof, using `rng`
+    /// as the external randomness for the proof's blinding factors.
+    pub fn prove_with_rng<R: RngCore + CryptoRng>(
+        self,
+        bp_gens: &BulletproofGens,
+        rng: &mut R,
+    ) -> Result<R1CSProof, R1CSError> {
+        self.prove_and_return_transcript_with_rng(bp_gens, rng)
             .map(|(proof, _transcript)| proof)
     }
 
     /// Consume this `ConstraintSystem` to produce a proof. Returns the proof and the transcript passed in `Prover::new`.
+    ///
+    /// This is a convenience wrapper around
+    /// [`Prover::prove_and_return_transcript_with_rng`], passing in a
+    /// threadsafe RNG.
+    #[cfg(feature = "std")]
     pub fn prove_and_return_transcript(
+        self,
+        bp_gens: &BulletproofGens,
+    ) -> Result<(R1CSProof, T), R1CSError> {
+        self.prove_and_return_transcript_with_rng(bp_gens, &mut thread_rng())
+    }
+
+    /// Same as [`Prover::prove_with_rng`], but also returns the
+    /// transcript passed in `Prover::new`.
+    pub fn prove_and_return_transcript_with_rng<R: RngCore + CryptoRng>(
         mut self,
         bp_gens: &BulletproofGens,
+        rng: &mut R,
     ) -> Result<(R1CSProof, T), R1CSError> {
         use crate::util;
-        use std::iter;
+        use core::iter;
 
         // Commit a length _suffix_ for the number of high-level variables.
         // We cannot do this in advance because user can commit variables one-by-one,
         // but this suffix provides safe disambiguation because each variable
         // is prefixed with a separate label.
         self.transcript
-            .borrow_mut()
             .append_u64(b"m", self.secrets.v.len() as u64);
 
         // Create a `TranscriptRng` from the high-level witness data
@@ -430,21 +603,21 @@ impl<'g, T: BorrowMut<Transcript>> Prover<'g, T> {
         // protect the v's in the commitments), we don't gain much by
         // committing the v's as well as the v_blinding's.
         let mut rng = {
-            let mut builder = self.transcript.borrow_mut().build_rng();
+            let mut builder = self.transcript.build_rng();
 
             // Commit the blinding factors for the input wires
             for v_b in &self.secrets.v_blinding {
                 builder = builder.rekey_with_witness_bytes(b"v_blinding", v_b.as_bytes());
             }
 
-            use rand::thread_rng;
-            builder.finalize(&mut thread_rng())
+            builder.finalize(rng)
         };
 
         // Commit to the first-phase low-level witness variables.
         let n1 = self.secrets.a_L.len();
+        let padded_n1 = n1.next_power_of_two();
 
-        if bp_gens.gens_capacity < n1 {
+        if bp_gens.gens_capacity < padded_n1 {
             return Err(R1CSError::InvalidGeneratorsLength);
         }
 
@@ -487,7 +660,10 @@ impl<'g, T: BorrowMut<Transcript>> Prover<'g, T> {
         )
         .compress();
 
-        let transcript = self.transcript.borrow_mut();
+        // Bind the generators before the first-phase challenges, and
+        // the generators of the padded circuit once its size is known.
+        let transcript = &mut self.transcript;
+        transcript.bind_generators(self.pc_gens, bp_gens, padded_n1, 1);
         transcript.append_point(b"A_I1", &A_I1);
         transcript.append_point(b"A_O1", &A_O1);
         transcript.append_point(b"S1", &S1);
@@ -565,7 +741,10 @@ impl<'g, T: BorrowMut<Transcript>> Prover<'g, T> {
             )
         };
 
-        let transcript = self.transcript.borrow_mut();
+        let transcript = &mut self.transcript;
+        if padded_n != padded_n1 {
+            transcript.bind_bulletproof_gens(bp_gens, padded_n, 1);
+        }
         transcript.append_point(b"A_I2", &A_I2);
         transcript.append_point(b"A_O2", &A_O2);
         transcript.append_point(b"S2", &S2);
@@ -621,7 +800,7 @@ impl<'g, T: BorrowMut<Transcript>> Prover<'g, T> {
         let T_5 = self.pc_gens.commit(t_poly.t5, t_5_blinding).compress();
         let T_6 = self.pc_gens.commit(t_poly.t6, t_6_blinding).compress();
 
-        let transcript = self.transcript.borrow_mut();
+        let transcript = &mut self.transcript;
         transcript.append_point(b"T_1", &T_1);
         transcript.append_point(b"T_3", &T_3);
         transcript.append_point(b"T_4", &T_4);
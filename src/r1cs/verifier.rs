@@ -1,21 +1,37 @@
 #![allow(non_snake_case)]
 
-use core::borrow::BorrowMut;
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate rand;
+
+#[cfg(feature = "std")]
+use self::rand::thread_rng;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter;
 use core::mem;
 use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
 use curve25519_dalek::scalar::Scalar;
-use curve25519_dalek::traits::VartimeMultiscalarMul;
-use merlin::Transcript;
+use curve25519_dalek::traits::{IsIdentity, VartimeMultiscalarMul};
+use rand_core::{CryptoRng, RngCore};
 
+use super::circuit::{
+    RecordedConstraints, RecordedPhase, RecordedRun, RecordingTranscript, TranscriptOperation,
+};
+use super::constraint_system::{RandomizableConstraintSystem, RandomizedConstraintSystem};
+use super::export::ExportedPhase;
+use super::namespace::Namespaces;
 use super::{
-    ConstraintSystem, LinearCombination, R1CSProof, RandomizableConstraintSystem,
-    RandomizedConstraintSystem, Variable,
+    CircuitDescription, ConstraintSystem, ExportedCircuit, LinearCombination, R1CSProof, Variable,
 };
 
 use crate::errors::R1CSError;
 use crate::generators::{BulletproofGens, PedersenGens};
 use crate::r1cs::Metrics;
-use crate::transcript::TranscriptProtocol;
+use crate::transcript::{TranscriptBackend, TranscriptProtocol, WitnessRngBuilder};
 
 /// A [`ConstraintSystem`] implementation for use by the verifier.
 ///
@@ -26,8 +42,12 @@ use crate::transcript::TranscriptProtocol;
 /// When all constraints are added, the verifying code calls `verify`
 /// which consumes the `Verifier` instance, samples random challenges
 /// that instantiate the randomized constraints, and verifies the proof.
-pub struct Verifier<T: BorrowMut<Transcript>> {
+pub struct Verifier<T: TranscriptBackend> {
     transcript: T,
+
+    /// The constraints of a recorded circuit, which precede the
+    /// `constraints` added to this verifier.
+    recorded_constraints: Option<Arc<RecordedConstraints>>,
     constraints: Vec<LinearCombination>,
 
     /// Records the number of low-level variables allocated in the
@@ -49,6 +69,14 @@ pub struct Verifier<T: BorrowMut<Transcript>> {
 
     /// Index of a pending multiplier that's not fully assigned yet.
     pending_multiplier: Option<usize>,
+
+    /// The namespaces of the multipliers and constraints, recorded
+    /// once the first namespace is entered.
+    namespaces: Option<Namespaces>,
+
+    /// The challenges drawn in the randomization phase, recorded only
+    /// while exporting the constraint system.
+    recorded_challenges: Option<Vec<(&'static [u8], Scalar)>>,
 }
 
 /// Verifier in the randomizing phase.
@@ -58,13 +86,15 @@ pub struct Verifier<T: BorrowMut<Transcript>> {
 /// monomorphize the closures for the proving and verifying code.
 /// However, this type cannot be instantiated by the user and therefore can only be used within
 /// the callback provided to `specify_randomized_constraints`.
-pub struct RandomizingVerifier<T: BorrowMut<Transcript>> {
+pub struct RandomizingVerifier<T: TranscriptBackend> {
     verifier: Verifier<T>,
 }
 
-impl<T: BorrowMut<Transcript>> ConstraintSystem for Verifier<T> {
-    fn transcript(&mut self) -> &mut Transcript {
-        self.transcript.borrow_mut()
+impl<T: TranscriptBackend> ConstraintSystem for Verifier<T> {
+    type Transcript = T;
+
+    fn transcript(&mut self) -> &mut T {
+        &mut self.transcript
     }
 
     fn multiply(
@@ -74,6 +104,7 @@ impl<T: BorrowMut<Transcript>> ConstraintSystem for Verifier<T> {
     ) -> (Variable, Variable, Variable) {
         let var = self.num_vars;
         self.num_vars += 1;
+        self.record_multiplier(var);
 
         // Create variables for l,r,o
         let l_var = Variable::MultiplierLeft(var);
@@ -94,6 +125,7 @@ impl<T: BorrowMut<Transcript>> ConstraintSystem for Verifier<T> {
             None => {
                 let i = self.num_vars;
                 self.num_vars += 1;
+                self.record_multiplier(i);
                 self.pending_multiplier = Some(i);
                 Ok(Variable::MultiplierLeft(i))
             }
@@ -110,6 +142,7 @@ impl<T: BorrowMut<Transcript>> ConstraintSystem for Verifier<T> {
     ) -> Result<(Variable, Variable, Variable), R1CSError> {
         let var = self.num_vars;
         self.num_vars += 1;
+        self.record_multiplier(var);
 
         // Create variables for l,r,o
         let l_var = Variable::MultiplierLeft(var);
@@ -122,9 +155,14 @@ impl<T: BorrowMut<Transcript>> ConstraintSystem for Verifier<T> {
     fn metrics(&self) -> Metrics {
         Metrics {
             multipliers: self.num_vars,
-            constraints: self.constraints.len() + self.deferred_constraints.len(),
-            phase_one_constraints: self.constraints.len(),
+            constraints: self.num_constraints() + self.deferred_constraints.len(),
+            phase_one_constraints: self.num_constraints(),
             phase_two_constraints: self.deferred_constraints.len(),
+            namespaces: self
+                .namespaces
+                .as_ref()
+                .map(Namespaces::metrics)
+                .unwrap_or_default(),
         }
     }
 
@@ -132,25 +170,57 @@ impl<T: BorrowMut<Transcript>> ConstraintSystem for Verifier<T> {
         // TODO: check that the linear combinations are valid
         // (e.g. that variables are valid, that the linear combination
         // evals to 0 for prover, etc).
+        let index = self.num_constraints();
+        if let Some(namespaces) = &mut self.namespaces {
+            namespaces.record_constraint(index);
+        }
         self.constraints.push(lc);
     }
+
+    fn push_namespace<N, NR>(&mut self, name_fn: N)
+    where
+        N: FnOnce() -> NR,
+        NR: Into<String>,
+    {
+        self.namespaces
+            .get_or_insert_with(Namespaces::default)
+            .push(name_fn().into());
+    }
+
+    fn pop_namespace(&mut self) {
+        if let Some(namespaces) = &mut self.namespaces {
+            namespaces.pop();
+        }
+    }
 }
 
-impl<T: BorrowMut<Transcript>> RandomizableConstraintSystem for Verifier<T> {
+impl<T: TranscriptBackend> RandomizableConstraintSystem for Verifier<T> {
     type RandomizedCS = RandomizingVerifier<T>;
 
     fn specify_randomized_constraints<F>(&mut self, callback: F) -> Result<(), R1CSError>
     where
         F: 'static + FnOnce(&mut Self::RandomizedCS) -> Result<(), R1CSError>,
     {
-        self.deferred_constraints.push(Box::new(callback));
+        // Add the randomized constraints in the namespaces in which
+        // they are specified, rather than those open at the end of the
+        // first phase.
+        let namespaces = self.namespaces.as_ref().map(Namespaces::save);
+        self.deferred_constraints
+            .push(Box::new(move |cs: &mut RandomizingVerifier<T>| {
+                let outer = cs.verifier.restore_namespaces(namespaces);
+                let result = callback(cs);
+                cs.verifier.restore_namespaces(outer);
+                result
+            }));
         Ok(())
     }
 }
 
-impl<T: BorrowMut<Transcript>> ConstraintSystem for RandomizingVerifier<T> {
-    fn transcript(&mut self) -> &mut Transcript {
-        self.verifier.transcript.borrow_mut()
+impl<T: TranscriptBackend> ConstraintSystem for RandomizingVerifier<T> {
+    type Transcript = T;
+
+    fn transcript(&mut self) -> &mut T {
+        &mut self.verifier.transcript
     }
 
     fn multiply(
@@ -179,24 +249,38 @@ impl<T: BorrowMut<Transcript>> ConstraintSystem for RandomizingVerifier<T> {
     fn constrain(&mut self, lc: LinearCombination) {
         self.verifier.constrain(lc)
     }
+
+    fn push_namespace<N, NR>(&mut self, name_fn: N)
+    where
+        N: FnOnce() -> NR,
+        NR: Into<String>,
+    {
+        self.verifier.push_namespace(name_fn)
+    }
+
+    fn pop_namespace(&mut self) {
+        self.verifier.pop_namespace()
+    }
 }
 
-impl<T: BorrowMut<Transcript>> RandomizedConstraintSystem for RandomizingVerifier<T> {
+impl<T: TranscriptBackend> RandomizedConstraintSystem for RandomizingVerifier<T> {
     fn challenge_scalar(&mut self, label: &'static [u8]) -> Scalar {
-        self.verifier
-            .transcript
-            .borrow_mut()
-            .challenge_scalar(label)
+        let challenge = self.verifier.transcript.challenge_scalar(label);
+        if let Some(challenges) = &mut self.verifier.recorded_challenges {
+            challenges.push((label, challenge));
+        }
+        challenge
     }
 }
 
-impl<T: BorrowMut<Transcript>> Verifier<T> {
+impl<T: TranscriptBackend> Verifier<T> {
     /// Construct an empty constraint system with specified external
     /// input variables.
     ///
     /// # Inputs
     ///
-    /// The `transcript` parameter is a Merlin proof transcript.  The
+    /// The `transcript` parameter is a proof transcript implementing
+    /// [`TranscriptBackend`], such as a Merlin transcript.  The
     /// `VerifierCS` holds onto the `&mut Transcript` until it consumes
     /// itself during [`VerifierCS::verify`], releasing its borrow of the
     /// transcript.  This ensures that the transcript cannot be
@@ -217,15 +301,39 @@ impl<T: BorrowMut<Transcript>> Verifier<T> {
     /// The second element is a list of [`Variable`]s corresponding to
     /// the external inputs, which can be used to form constraints.
     pub fn new(mut transcript: T) -> Self {
-        transcript.borrow_mut().r1cs_domain_sep();
+        transcript.r1cs_domain_sep();
 
         Verifier {
             transcript,
             num_vars: 0,
             V: Vec::new(),
+            recorded_constraints: None,
             constraints: Vec::new(),
             deferred_constraints: Vec::new(),
             pending_multiplier: None,
+            namespaces: None,
+            recorded_challenges: None,
+        }
+    }
+
+    /// Returns the number of constraints, including those of a
+    /// recorded circuit.
+    fn num_constraints(&self) -> usize {
+        self.recorded_constraints.as_ref().map_or(0, |rc| rc.len()) + self.constraints.len()
+    }
+
+    /// Reopens the namespaces returned by [`Namespaces::save`], or the
+    /// root namespace for `None`, and returns the namespaces which were
+    /// open before.
+    fn restore_namespaces(&mut self, saved: Option<Vec<usize>>) -> Option<Vec<usize>> {
+        self.namespaces
+            .as_mut()
+            .map(|namespaces| namespaces.restore(saved.unwrap_or_default()))
+    }
+
+    fn record_multiplier(&mut self, index: usize) {
+        if let Some(namespaces) = &mut self.namespaces {
+            namespaces.record_multiplier(index);
         }
     }
 
@@ -248,7 +356,7 @@ impl<T: BorrowMut<Transcript>> Verifier<T> {
         self.V.push(commitment);
 
         // Add the commitment to the transcript.
-        self.transcript.borrow_mut().append_point(b"V", &commitment);
+        self.transcript.append_point(b"V", &commitment);
 
         Variable::Committed(i)
     }
@@ -282,6 +390,18 @@ impl<T: BorrowMut<Transcript>> Verifier<T> {
         let mut wc = Scalar::ZERO;
 
         let mut exp_z = *z;
+        if let Some(recorded) = &self.recorded_constraints {
+            // The flattened columns of a recorded circuit only need
+            // to be weighted by the powers of z.
+            let z_powers: Vec<Scalar> = (0..recorded.len())
+                .map(|_| {
+                    let z_q = exp_z;
+                    exp_z *= z;
+                    z_q
+                })
+                .collect();
+            recorded.add_to(&z_powers, &mut wL, &mut wR, &mut wO, &mut wV, &mut wc);
+        }
         for lc in self.constraints.iter() {
             for (var, coeff) in &lc.terms {
                 match var {
@@ -308,6 +428,115 @@ impl<T: BorrowMut<Transcript>> Verifier<T> {
         (wL, wR, wO, wV, wc)
     }
 
+    /// Consume this `Verifier` to export its constraint system,
+    /// including the randomized constraints, for inspection.
+    ///
+    /// The randomized constraints are instantiated by calling the
+    /// deferred callbacks, as during verification.  Their challenges
+    /// are drawn from the transcript before any proof is added to it,
+    /// and are recorded in the exported circuit.
+    pub fn export(mut self) -> Result<ExportedCircuit, R1CSError> {
+        let num_commitments = self.V.len();
+        let phase_one_multipliers = self.num_vars;
+        let phase_one_constraints = self.num_constraints();
+        let randomized = !self.deferred_constraints.is_empty();
+
+        self.recorded_challenges = Some(Vec::new());
+        let verifier = self.create_randomized_constraints()?;
+        let namespaces = verifier.namespaces.as_ref();
+        let multiplier_label = |i: usize| {
+            namespaces
+                .and_then(|ns| ns.multiplier_label(i))
+                .map(String::from)
+        };
+        let constraint_label = |i: usize| {
+            namespaces
+                .and_then(|ns| ns.constraint_label(i))
+                .map(String::from)
+        };
+
+        let mut phases = vec![ExportedPhase::default()];
+        if randomized {
+            phases.push(ExportedPhase {
+                challenges: verifier
+                    .recorded_challenges
+                    .iter()
+                    .flatten()
+                    .map(|(label, challenge)| {
+                        (String::from_utf8_lossy(label).into_owned(), *challenge)
+                    })
+                    .collect(),
+                ..Default::default()
+            });
+        }
+        for i in 0..verifier.num_vars {
+            let phase = if i < phase_one_multipliers { 0 } else { 1 };
+            phases[phase].multipliers.push(multiplier_label(i));
+        }
+        let recorded = verifier
+            .recorded_constraints
+            .iter()
+            .flat_map(|rc| rc.constraints.iter());
+        for (i, lc) in recorded.chain(verifier.constraints.iter()).enumerate() {
+            let phase = if i < phase_one_constraints { 0 } else { 1 };
+            phases[phase]
+                .constraints
+                .push((lc.clone(), constraint_label(i)));
+        }
+
+        Ok(ExportedCircuit {
+            num_commitments,
+            phases,
+        })
+    }
+
+    /// Constructs a verifier for the recorded `circuit`, committing
+    /// the `commitments` to its variables and appending the messages of
+    /// its gadgets.
+    pub(super) fn from_circuit_description(
+        transcript: T,
+        commitments: &[CompressedRistretto],
+        circuit: &CircuitDescription,
+    ) -> Self {
+        let mut verifier = Verifier::new(transcript);
+        for V in commitments {
+            verifier.commit(*V);
+        }
+        for op in circuit.operations.iter() {
+            if let TranscriptOperation::Message { label, message } = op {
+                verifier.transcript.append_message(label, message);
+            }
+        }
+        verifier.num_vars = circuit.num_multipliers;
+        verifier.recorded_constraints = Some(circuit.constraints.clone());
+
+        if let Some(phase) = circuit.randomized.clone() {
+            // Replay the transcript operations of the randomized phase,
+            // and instantiate its constraints with the challenges.
+            verifier
+                .deferred_constraints
+                .push(Box::new(move |cs: &mut RandomizingVerifier<T>| {
+                    let mut challenges = Vec::new();
+                    for op in phase.operations() {
+                        match op {
+                            TranscriptOperation::Message { label, message } => {
+                                cs.transcript().append_message(label, message)
+                            }
+                            TranscriptOperation::Challenge { label } => {
+                                challenges.push(cs.challenge_scalar(label))
+                            }
+                        }
+                    }
+                    cs.verifier.num_vars += phase.num_multipliers();
+                    for lc in phase.instantiate(&challenges) {
+                        cs.constrain(lc);
+                    }
+                    Ok(())
+                }));
+        }
+        verifier
+    }
+
     /// Calls all remembered callbacks with an API that
     /// allows generating challenge scalars.
     fn create_randomized_constraints(mut self) -> Result<Self, R1CSError> {
@@ -315,52 +544,227 @@ impl<T: BorrowMut<Transcript>> Verifier<T> {
         self.pending_multiplier = None;
 
         if self.deferred_constraints.len() == 0 {
-            self.transcript.borrow_mut().r1cs_1phase_domain_sep();
+            self.transcript.r1cs_1phase_domain_sep();
             Ok(self)
         } else {
-            self.transcript.borrow_mut().r1cs_2phase_domain_sep();
-            // Note: the wrapper could've used &mut instead of ownership,
-            // but specifying lifetimes for boxed closures is not going to be nice,
-            // so we move the self into wrapper and then move it back out afterwards.
-            let mut callbacks = mem::replace(&mut self.deferred_constraints, Vec::new());
-            let mut wrapped_self = RandomizingVerifier { verifier: self };
-            for callback in callbacks.drain(..) {
-                callback(&mut wrapped_self)?;
-            }
-            Ok(wrapped_self.verifier)
+            self.transcript.r1cs_2phase_domain_sep();
+            self.call_deferred_constraints()
         }
     }
 
+    /// Calls all remembered callbacks, without appending a domain
+    /// separator for the randomized phase.
+    fn call_deferred_constraints(mut self) -> Result<Self, R1CSError> {
+        // Note: the wrapper could've used &mut instead of ownership,
+        // but specifying lifetimes for boxed closures is not going to be nice,
+        // so we move the self into wrapper and then move it back out afterwards.
+        let mut callbacks = mem::replace(&mut self.deferred_constraints, Vec::new());
+        let mut wrapped_self = RandomizingVerifier { verifier: self };
+        for callback in callbacks.drain(..) {
+            callback(&mut wrapped_self)?;
+        }
+        Ok(wrapped_self.verifier)
+    }
+
     /// Consume this `VerifierCS` and attempt to verify the supplied `proof`.
     /// The `pc_gens` and `bp_gens` are generators for Pedersen commitments and
     /// Bulletproofs vector commitments, respectively.  The
     /// [`BulletproofGens`] should have `gens_capacity` greater than
     /// the number of multiplication constraints that will eventually
     /// be added into the constraint system.
+    ///
+    /// This is a convenience wrapper around [`Verifier::verify_with_rng`],
+    /// passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
     pub fn verify(
         self,
         proof: &R1CSProof,
         pc_gens: &PedersenGens,
         bp_gens: &BulletproofGens,
     ) -> Result<(), R1CSError> {
-        self.verify_and_return_transcript(proof, pc_gens, bp_gens)
+        self.verify_with_rng(proof, pc_gens, bp_gens, &mut thread_rng())
+    }
+
+    /// Same as `verify`, but uses `rng` as the external randomness
+    /// for the random weight combining the verification checks.
+    pub fn verify_with_rng<R: RngCore + CryptoRng>(
+        self,
+        proof: &R1CSProof,
+        pc_gens: &PedersenGens,
+        bp_gens: &BulletproofGens,
+        rng: &mut R,
+    ) -> Result<(), R1CSError> {
+        self.verify_and_return_transcript_with_rng(proof, pc_gens, bp_gens, rng)
             .map(|_| ())
     }
+
     /// Same as `verify`, but also returns the transcript back to the user.
+    #[cfg(feature = "std")]
     pub fn verify_and_return_transcript(
-        mut self,
+        self,
         proof: &R1CSProof,
         pc_gens: &PedersenGens,
         bp_gens: &BulletproofGens,
     ) -> Result<T, R1CSError> {
+        self.verify_and_return_transcript_with_rng(proof, pc_gens, bp_gens, &mut thread_rng())
+    }
+
+    /// Same as [`Verifier::verify_with_rng`], but also returns the
+    /// transcript back to the user.
+    pub fn verify_and_return_transcript_with_rng<R: RngCore + CryptoRng>(
+        self,
+        proof: &R1CSProof,
+        pc_gens: &PedersenGens,
+        bp_gens: &BulletproofGens,
+        rng: &mut R,
+    ) -> Result<T, R1CSError> {
+        let (terms, transcript) = self.verification_terms(proof, pc_gens, bp_gens, rng)?;
+
+        if terms.is_valid(pc_gens, bp_gens) {
+            Ok(transcript)
+        } else {
+            Err(R1CSError::VerificationError)
+        }
+    }
+
+    /// Verifies a batch of proofs with a single multiscalar
+    /// multiplication.
+    ///
+    /// Each `Verifier` in the `batch` must already contain the
+    /// commitments and constraints of the statement proved by its
+    /// paired proof, exactly as it would before calling
+    /// [`Verifier::verify`].  All proofs must use the same `pc_gens`
+    /// and `bp_gens`.
+    ///
+    /// This is a convenience wrapper around
+    /// [`Verifier::verify_batch_with_rng`], passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn verify_batch<'p, I>(
+        batch: I,
+        pc_gens: &PedersenGens,
+        bp_gens: &BulletproofGens,
+    ) -> Result<(), R1CSError>
+    where
+        I: IntoIterator<Item = (Self, &'p R1CSProof)>,
+    {
+        Self::verify_batch_with_rng(batch, pc_gens, bp_gens, &mut thread_rng())
+    }
+
+    /// Same as [`Verifier::verify_batch`], but uses `rng` as the
+    /// external randomness.
+    ///
+    /// The verification checks of all proofs are combined with random
+    /// weights drawn from `rng`.  If the batch fails to verify, the
+    /// proofs are checked one by one (reusing the recomputed
+    /// challenges) and [`R1CSError::BatchVerificationError`] reports
+    /// the index of the first invalid proof.
+    pub fn verify_batch_with_rng<'p, I, R>(
+        batch: I,
+        pc_gens: &PedersenGens,
+        bp_gens: &BulletproofGens,
+        rng: &mut R,
+    ) -> Result<(), R1CSError>
+    where
+        I: IntoIterator<Item = (Self, &'p R1CSProof)>,
+        R: RngCore + CryptoRng,
+    {
+        let all_terms = batch
+            .into_iter()
+            .enumerate()
+            .map(|(index, (verifier, proof))| {
+                verifier
+                    .verification_terms(proof, pc_gens, bp_gens, rng)
+                    .map(|(terms, _transcript)| terms)
+                    .map_err(|e| match e {
+                        R1CSError::InvalidGeneratorsLength => e,
+                        _ => R1CSError::BatchVerificationError { index },
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Sum the weighted coefficients of the shared generators, and
+        // collect the weighted per-proof points.
+        let max_n = all_terms
+            .iter()
+            .map(|t| t.G_scalars.len())
+            .max()
+            .unwrap_or(0);
+        let mut G_scalars = vec![Scalar::ZERO; max_n];
+        let mut H_scalars = vec![Scalar::ZERO; max_n];
+        let mut B_scalar = Scalar::ZERO;
+        let mut B_blinding_scalar = Scalar::ZERO;
+        let mut dynamic_scalars = Vec::new();
+        let mut dynamic_points = Vec::new();
+        for terms in all_terms.iter() {
+            let c = Scalar::random(rng);
+            B_scalar += c * terms.B_scalar;
+            B_blinding_scalar += c * terms.B_blinding_scalar;
+            for (G_scalar, s) in G_scalars.iter_mut().zip(terms.G_scalars.iter()) {
+                *G_scalar += c * s;
+            }
+            for (H_scalar, s) in H_scalars.iter_mut().zip(terms.H_scalars.iter()) {
+                *H_scalar += c * s;
+            }
+            dynamic_scalars.extend(terms.dynamic_scalars.iter().map(|s| c * s));
+            dynamic_points.extend_from_slice(&terms.dynamic_points);
+        }
+
+        let gens = bp_gens.share(0);
+        let check = RistrettoPoint::vartime_multiscalar_mul(
+            iter::once(B_scalar)
+                .chain(iter::once(B_blinding_scalar))
+                .chain(G_scalars)
+                .chain(H_scalars)
+                .chain(dynamic_scalars),
+            iter::once(&pc_gens.B)
+                .chain(iter::once(&pc_gens.B_blinding))
+                .chain(gens.G(max_n))
+                .chain(gens.H(max_n))
+                .chain(dynamic_points.iter()),
+        );
+
+        if check.is_identity() {
+            return Ok(());
+        }
+
+        // Find the first proof which fails on its own.
+        if let Some(index) = all_terms
+            .iter()
+            .position(|terms| !terms.is_valid(pc_gens, bp_gens))
+        {
+            return Err(R1CSError::BatchVerificationError { index });
+        }
+
+        // The individual proofs are valid, so the random combination
+        // can only have failed with negligible probability.
+        Err(R1CSError::VerificationError)
+    }
+
+    /// Replays the proof against the transcript and computes the
+    /// scalars and points of its verification check, consuming the
+    /// verifier.  Returns the check together with the transcript.
+    fn verification_terms<R: RngCore + CryptoRng>(
+        mut self,
+        proof: &R1CSProof,
+        pc_gens: &PedersenGens,
+        bp_gens: &BulletproofGens,
+        rng: &mut R,
+    ) -> Result<(VerificationTerms, T), R1CSError> {
         // Commit a length _suffix_ for the number of high-level variables.
         // We cannot do this in advance because user can commit variables one-by-one,
         // but this suffix provides safe disambiguation because each variable
         // is prefixed with a separate label.
-        let transcript = self.transcript.borrow_mut();
+        let transcript = &mut self.transcript;
         transcript.append_u64(b"m", self.V.len() as u64);
 
         let n1 = self.num_vars;
+        let padded_n1 = n1.next_power_of_two();
+        if bp_gens.gens_capacity < padded_n1 {
+            return Err(R1CSError::InvalidGeneratorsLength);
+        }
+        // Bind the generators before the first-phase challenges, and
+        // the generators of the padded circuit once its size is known.
+        transcript.bind_generators(pc_gens, bp_gens, padded_n1, 1);
         transcript.validate_and_append_point(b"A_I1", &proof.A_I1)?;
         transcript.validate_and_append_point(b"A_O1", &proof.A_O1)?;
         transcript.validate_and_append_point(b"S1", &proof.S1)?;
@@ -368,7 +772,7 @@ impl<T: BorrowMut<Transcript>> Verifier<T> {
         // Process the remaining constraints.
         self = self.create_randomized_constraints()?;
 
-        let transcript = self.transcript.borrow_mut();
+        let transcript = &mut self.transcript;
 
         // If the number of multiplications is not 0 or a power of 2, then pad the circuit.
         let n = self.num_vars;
@@ -378,13 +782,13 @@ impl<T: BorrowMut<Transcript>> Verifier<T> {
 
         use crate::inner_product_proof::inner_product;
         use crate::util;
-        use std::iter;
 
         if bp_gens.gens_capacity < padded_n {
             return Err(R1CSError::InvalidGeneratorsLength);
         }
-        // We are performing a single-party circuit proof, so party index is 0.
-        let gens = bp_gens.share(0);
+        if padded_n != padded_n1 {
+            transcript.bind_bulletproof_gens(bp_gens, padded_n, 1);
+        }
 
         // These points are the identity in the 1-phase unrandomized case.
         transcript.append_point(b"A_I2", &proof.A_I2);
@@ -414,7 +818,7 @@ impl<T: BorrowMut<Transcript>> Verifier<T> {
         // Get IPP variables
         let (u_sq, u_inv_sq, s) = proof
             .ipp_proof
-            .verification_scalars(padded_n, self.transcript.borrow_mut())
+            .verification_scalars(padded_n, &mut self.transcript)
             .map_err(|_| R1CSError::VerificationError)?;
 
         let a = proof.ipp_proof.a;
@@ -458,12 +862,7 @@ impl<T: BorrowMut<Transcript>> Verifier<T> {
         // Create a `TranscriptRng` from the transcript. The verifier
         // has no witness data to commit, so this just mixes external
         // randomness into the existing transcript.
-        use rand::thread_rng;
-        let mut rng = self
-            .transcript
-            .borrow_mut()
-            .build_rng()
-            .finalize(&mut thread_rng());
+        let mut rng = self.transcript.build_rng().finalize(rng);
         let r = Scalar::random(&mut rng);
 
         let xx = x * x;
@@ -474,46 +873,112 @@ impl<T: BorrowMut<Transcript>> Verifier<T> {
         let T_scalars = [r * x, rxx * x, rxx * xx, rxx * xxx, rxx * xx * xx];
         let T_points = [proof.T_1, proof.T_3, proof.T_4, proof.T_5, proof.T_6];
 
-        let mega_check = RistrettoPoint::optional_multiscalar_mul(
-            iter::once(x) // A_I1
-                .chain(iter::once(xx)) // A_O1
-                .chain(iter::once(xxx)) // S1
-                .chain(iter::once(u * x)) // A_I2
-                .chain(iter::once(u * xx)) // A_O2
-                .chain(iter::once(u * xxx)) // S2
-                .chain(wV.iter().map(|wVi| wVi * rxx)) // V
-                .chain(T_scalars.iter().cloned()) // T_points
-                .chain(iter::once(
-                    w * (proof.t_x - a * b) + r * (xx * (wc + delta) - proof.t_x),
-                )) // B
-                .chain(iter::once(-proof.e_blinding - r * proof.t_x_blinding)) // B_blinding
-                .chain(g_scalars) // G
-                .chain(h_scalars) // H
-                .chain(u_sq.iter().cloned()) // ipp_proof.L_vec
-                .chain(u_inv_sq.iter().cloned()), // ipp_proof.R_vec
-            iter::once(proof.A_I1.decompress())
-                .chain(iter::once(proof.A_O1.decompress()))
-                .chain(iter::once(proof.S1.decompress()))
-                .chain(iter::once(proof.A_I2.decompress()))
-                .chain(iter::once(proof.A_O2.decompress()))
-                .chain(iter::once(proof.S2.decompress()))
-                .chain(self.V.iter().map(|V_i| V_i.decompress()))
-                .chain(T_points.iter().map(|T_i| T_i.decompress()))
-                .chain(iter::once(Some(pc_gens.B)))
-                .chain(iter::once(Some(pc_gens.B_blinding)))
-                .chain(gens.G(padded_n).map(|&G_i| Some(G_i)))
-                .chain(gens.H(padded_n).map(|&H_i| Some(H_i)))
-                .chain(proof.ipp_proof.L_vec.iter().map(|L_i| L_i.decompress()))
-                .chain(proof.ipp_proof.R_vec.iter().map(|R_i| R_i.decompress())),
-        )
-        .ok_or_else(|| R1CSError::VerificationError)?;
-
-        use curve25519_dalek::traits::IsIdentity;
+        let dynamic_scalars = iter::once(x) // A_I1
+            .chain(iter::once(xx)) // A_O1
+            .chain(iter::once(xxx)) // S1
+            .chain(iter::once(u * x)) // A_I2
+            .chain(iter::once(u * xx)) // A_O2
+            .chain(iter::once(u * xxx)) // S2
+            .chain(wV.iter().map(|wVi| wVi * rxx)) // V
+            .chain(T_scalars.iter().cloned()) // T_points
+            .chain(u_sq.iter().cloned()) // ipp_proof.L_vec
+            .chain(u_inv_sq.iter().cloned()) // ipp_proof.R_vec
+            .collect();
+        let dynamic_points = iter::once(proof.A_I1.decompress())
+            .chain(iter::once(proof.A_O1.decompress()))
+            .chain(iter::once(proof.S1.decompress()))
+            .chain(iter::once(proof.A_I2.decompress()))
+            .chain(iter::once(proof.A_O2.decompress()))
+            .chain(iter::once(proof.S2.decompress()))
+            .chain(self.V.iter().map(|V_i| V_i.decompress()))
+            .chain(T_points.iter().map(|T_i| T_i.decompress()))
+            .chain(proof.ipp_proof.L_vec.iter().map(|L_i| L_i.decompress()))
+            .chain(proof.ipp_proof.R_vec.iter().map(|R_i| R_i.decompress()))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(R1CSError::VerificationError)?;
+
+        let terms = VerificationTerms {
+            B_scalar: w * (proof.t_x - a * b) + r * (xx * (wc + delta) - proof.t_x),
+            B_blinding_scalar: -proof.e_blinding - r * proof.t_x_blinding,
+            G_scalars: g_scalars.collect(),
+            H_scalars: h_scalars.collect(),
+            dynamic_scalars,
+            dynamic_points,
+        };
+
+        Ok((terms, self.transcript))
+    }
+}
 
-        if !mega_check.is_identity() {
-            return Err(R1CSError::VerificationError);
+impl Verifier<RecordingTranscript> {
+    /// Consumes the verifier of a circuit being recorded, calling the
+    /// callbacks of its randomized constraints, and returns the
+    /// recorded phases of the circuit.
+    pub(super) fn into_recorded_run(mut self) -> Result<RecordedRun, R1CSError> {
+        let phase_one = RecordedPhase {
+            multipliers: self.num_vars,
+            constraints: mem::take(&mut self.constraints),
+            operations: self.transcript.take_operations(),
+        };
+        let num_commitments = self.V.len();
+        if self.deferred_constraints.is_empty() {
+            return Ok(RecordedRun {
+                num_commitments,
+                phase_one,
+                phase_two: None,
+                transcript: self.transcript,
+            });
         }
 
-        Ok(self.transcript)
+        self.pending_multiplier = None;
+        let mut verifier = self.call_deferred_constraints()?;
+        let phase_two = RecordedPhase {
+            multipliers: verifier.num_vars - phase_one.multipliers,
+            constraints: verifier.constraints,
+            operations: verifier.transcript.take_operations(),
+        };
+        Ok(RecordedRun {
+            num_commitments,
+            phase_one,
+            phase_two: Some(phase_two),
+            transcript: verifier.transcript,
+        })
+    }
+}
+
+/// The verification check of a single R1CS proof, as the scalars and
+/// points of a multiscalar multiplication which is the identity if and
+/// only if the proof is valid.
+///
+/// The `G` and `H` scalars apply to prefixes of the first share of the
+/// `BulletproofGens`, so the checks of proofs of different sizes can be
+/// summed coefficient-wise.
+struct VerificationTerms {
+    B_scalar: Scalar,
+    B_blinding_scalar: Scalar,
+    G_scalars: Vec<Scalar>,
+    H_scalars: Vec<Scalar>,
+    dynamic_scalars: Vec<Scalar>,
+    dynamic_points: Vec<RistrettoPoint>,
+}
+
+impl VerificationTerms {
+    fn is_valid(&self, pc_gens: &PedersenGens, bp_gens: &BulletproofGens) -> bool {
+        // We are performing single-party circuit proofs, so party index is 0.
+        let gens = bp_gens.share(0);
+        let n = self.G_scalars.len();
+        RistrettoPoint::vartime_multiscalar_mul(
+            iter::once(&self.B_scalar)
+                .chain(iter::once(&self.B_blinding_scalar))
+                .chain(self.G_scalars.iter())
+                .chain(self.H_scalars.iter())
+                .chain(self.dynamic_scalars.iter()),
+            iter::once(&pc_gens.B)
+                .chain(iter::once(&pc_gens.B_blinding))
+                .chain(gens.G(n))
+                .chain(gens.H(n))
+                .chain(self.dynamic_points.iter()),
+        )
+        .is_identity()
     }
 }
@@ -18,7 +18,7 @@ use crate::errors::MPCError;
 use crate::generators::{BulletproofGens, PedersenGens};
 use crate::inner_product_proof;
 use crate::range_proof::RangeProof;
-use crate::transcript::TranscriptProtocol;
+use crate::transcript::{TranscriptBackend, TranscriptProtocol};
 
 use rand_core::{CryptoRng, RngCore};
 
@@ -34,13 +34,13 @@ pub struct Dealer {}
 
 impl Dealer {
     /// Creates a new dealer coordinating `m` parties proving `n`-bit ranges.
-    pub fn new<'a, 'b>(
+    pub fn new<'a, 'b, T: TranscriptBackend + Clone>(
         bp_gens: &'b BulletproofGens,
         pc_gens: &'b PedersenGens,
-        transcript: &'a mut Transcript,
+        transcript: &'a mut T,
         n: usize,
         m: usize,
-    ) -> Result<DealerAwaitingBitCommitments<'a, 'b>, MPCError> {
+    ) -> Result<DealerAwaitingBitCommitments<'a, 'b, T>, MPCError> {
         if !(n == 8 || n == 16 || n == 32 || n == 64) {
             return Err(MPCError::InvalidBitsize);
         }
@@ -69,6 +69,7 @@ impl Dealer {
         let initial_transcript = transcript.clone();
 
         transcript.rangeproof_domain_sep(n as u64, m as u64);
+        transcript.bind_generators(pc_gens, bp_gens, n, m);
 
         Ok(DealerAwaitingBitCommitments {
             bp_gens,
@@ -82,23 +83,23 @@ impl Dealer {
 }
 
 /// A dealer waiting for the parties to send their [`BitCommitment`]s.
-pub struct DealerAwaitingBitCommitments<'a, 'b> {
+pub struct DealerAwaitingBitCommitments<'a, 'b, T: TranscriptBackend + Clone = Transcript> {
     bp_gens: &'b BulletproofGens,
     pc_gens: &'b PedersenGens,
-    transcript: &'a mut Transcript,
+    transcript: &'a mut T,
     /// The dealer keeps a copy of the initial transcript state, so
     /// that it can attempt to verify the aggregated proof at the end.
-    initial_transcript: Transcript,
+    initial_transcript: T,
     n: usize,
     m: usize,
 }
 
-impl<'a, 'b> DealerAwaitingBitCommitments<'a, 'b> {
+impl<'a, 'b, T: TranscriptBackend + Clone> DealerAwaitingBitCommitments<'a, 'b, T> {
     /// Receive each party's [`BitCommitment`]s and compute the [`BitChallenge`].
     pub fn receive_bit_commitments(
         self,
         bit_commitments: Vec<BitCommitment>,
-    ) -> Result<(DealerAwaitingPolyCommitments<'a, 'b>, BitChallenge), MPCError> {
+    ) -> Result<(DealerAwaitingPolyCommitments<'a, 'b, T>, BitChallenge), MPCError> {
         if self.m != bit_commitments.len() {
             return Err(MPCError::WrongNumBitCommitments);
         }
@@ -139,11 +140,11 @@ impl<'a, 'b> DealerAwaitingBitCommitments<'a, 'b> {
 
 /// A dealer which has sent the [`BitChallenge`] to the parties and
 /// is waiting for their [`PolyCommitment`]s.
-pub struct DealerAwaitingPolyCommitments<'a, 'b> {
+pub struct DealerAwaitingPolyCommitments<'a, 'b, T: TranscriptBackend + Clone = Transcript> {
     n: usize,
     m: usize,
-    transcript: &'a mut Transcript,
-    initial_transcript: Transcript,
+    transcript: &'a mut T,
+    initial_transcript: T,
     bp_gens: &'b BulletproofGens,
     pc_gens: &'b PedersenGens,
     bit_challenge: BitChallenge,
@@ -154,13 +155,13 @@ pub struct DealerAwaitingPolyCommitments<'a, 'b> {
     S: RistrettoPoint,
 }
 
-impl<'a, 'b> DealerAwaitingPolyCommitments<'a, 'b> {
+impl<'a, 'b, T: TranscriptBackend + Clone> DealerAwaitingPolyCommitments<'a, 'b, T> {
     /// Receive [`PolyCommitment`]s from the parties and compute the
     /// [`PolyChallenge`].
     pub fn receive_poly_commitments(
         self,
         poly_commitments: Vec<PolyCommitment>,
-    ) -> Result<(DealerAwaitingProofShares<'a, 'b>, PolyChallenge), MPCError> {
+    ) -> Result<(DealerAwaitingProofShares<'a, 'b, T>, PolyChallenge), MPCError> {
         if self.m != poly_commitments.len() {
             return Err(MPCError::WrongNumPolyCommitments);
         }
@@ -200,11 +201,11 @@ impl<'a, 'b> DealerAwaitingPolyCommitments<'a, 'b> {
 /// A dealer which has sent the [`PolyChallenge`] to the parties and
 /// is waiting to aggregate their [`ProofShare`]s into a
 /// [`RangeProof`].
-pub struct DealerAwaitingProofShares<'a, 'b> {
+pub struct DealerAwaitingProofShares<'a, 'b, T: TranscriptBackend + Clone = Transcript> {
     n: usize,
     m: usize,
-    transcript: &'a mut Transcript,
-    initial_transcript: Transcript,
+    transcript: &'a mut T,
+    initial_transcript: T,
     bp_gens: &'b BulletproofGens,
     pc_gens: &'b PedersenGens,
     bit_challenge: BitChallenge,
@@ -217,7 +218,7 @@ pub struct DealerAwaitingProofShares<'a, 'b> {
     T_2: RistrettoPoint,
 }
 
-impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
+impl<'a, 'b, T: TranscriptBackend + Clone> DealerAwaitingProofShares<'a, 'b, T> {
     /// Assembles proof shares into an `RangeProof`.
     ///
     /// Used as a helper function by `receive_trusted_shares` (which
@@ -316,10 +317,10 @@ impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
     /// performing local aggregation,
     /// [`receive_trusted_shares`](DealerAwaitingProofShares::receive_trusted_shares)
     /// saves time by skipping verification of the aggregated proof.
-    pub fn receive_shares_with_rng<T: RngCore + CryptoRng>(
+    pub fn receive_shares_with_rng<R: RngCore + CryptoRng>(
         mut self,
         proof_shares: &[ProofShare],
-        rng: &mut T,
+        rng: &mut R,
     ) -> Result<RangeProof, MPCError> {
         let proof = self.assemble_shares(proof_shares)?;
 
@@ -11,15 +11,13 @@ use alloc::vec::Vec;
 
 use core::iter;
 
-use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
-use curve25519_dalek::scalar::Scalar;
-use merlin::Transcript;
-
 use crate::errors::ProofError;
 use crate::generators::{BulletproofGens, PedersenGens};
 use crate::inner_product_proof::InnerProductProof;
-use crate::transcript::TranscriptProtocol;
+use crate::transcript::{TranscriptBackend, TranscriptProtocol};
 use crate::util;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
 
 use rand_core::{CryptoRng, RngCore};
 use serde::de::Visitor;
@@ -131,14 +129,14 @@ impl RangeProof {
     /// );
     /// # }
     /// ```
-    pub fn prove_single_with_rng<T: RngCore + CryptoRng>(
+    pub fn prove_single_with_rng<T: TranscriptBackend + Clone, R: RngCore + CryptoRng>(
         bp_gens: &BulletproofGens,
         pc_gens: &PedersenGens,
-        transcript: &mut Transcript,
+        transcript: &mut T,
         v: u64,
         v_blinding: &Scalar,
         n: usize,
-        rng: &mut T,
+        rng: &mut R,
     ) -> Result<(RangeProof, CompressedRistretto), ProofError> {
         let (p, Vs) = RangeProof::prove_multiple_with_rng(
             bp_gens,
@@ -157,10 +155,10 @@ impl RangeProof {
     /// This is a convenience wrapper around [`RangeProof::prove_single_with_rng`],
     /// passing in a threadsafe RNG.
     #[cfg(feature = "std")]
-    pub fn prove_single(
+    pub fn prove_single<T: TranscriptBackend + Clone>(
         bp_gens: &BulletproofGens,
         pc_gens: &PedersenGens,
-        transcript: &mut Transcript,
+        transcript: &mut T,
         v: u64,
         v_blinding: &Scalar,
         n: usize,
@@ -230,14 +228,14 @@ impl RangeProof {
     /// );
     /// # }
     /// ```
-    pub fn prove_multiple_with_rng<T: RngCore + CryptoRng>(
+    pub fn prove_multiple_with_rng<T: TranscriptBackend + Clone, R: RngCore + CryptoRng>(
         bp_gens: &BulletproofGens,
         pc_gens: &PedersenGens,
-        transcript: &mut Transcript,
+        transcript: &mut T,
         values: &[u64],
         blindings: &[Scalar],
         n: usize,
-        rng: &mut T,
+        rng: &mut R,
     ) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
         use self::dealer::*;
         use self::party::*;
@@ -290,10 +288,10 @@ impl RangeProof {
     /// This is a convenience wrapper around [`RangeProof::prove_multiple_with_rng`],
     /// passing in a threadsafe RNG.
     #[cfg(feature = "std")]
-    pub fn prove_multiple(
+    pub fn prove_multiple<T: TranscriptBackend + Clone>(
         bp_gens: &BulletproofGens,
         pc_gens: &PedersenGens,
-        transcript: &mut Transcript,
+        transcript: &mut T,
         values: &[u64],
         blindings: &[Scalar],
         n: usize,
@@ -312,14 +310,14 @@ impl RangeProof {
     /// Verifies a rangeproof for a given value commitment \\(V\\).
     ///
     /// This is a convenience wrapper around `verify_multiple` for the `m=1` case.
-    pub fn verify_single_with_rng<T: RngCore + CryptoRng>(
+    pub fn verify_single_with_rng<T: TranscriptBackend, R: RngCore + CryptoRng>(
         &self,
         bp_gens: &BulletproofGens,
         pc_gens: &PedersenGens,
-        transcript: &mut Transcript,
+        transcript: &mut T,
         V: &CompressedRistretto,
         n: usize,
-        rng: &mut T,
+        rng: &mut R,
     ) -> Result<(), ProofError> {
         self.verify_multiple_with_rng(bp_gens, pc_gens, transcript, &[*V], n, rng)
     }
@@ -329,11 +327,11 @@ impl RangeProof {
     /// This is a convenience wrapper around [`RangeProof::verify_single_with_rng`],
     /// passing in a threadsafe RNG.
     #[cfg(feature = "std")]
-    pub fn verify_single(
+    pub fn verify_single<T: TranscriptBackend>(
         &self,
         bp_gens: &BulletproofGens,
         pc_gens: &PedersenGens,
-        transcript: &mut Transcript,
+        transcript: &mut T,
         V: &CompressedRistretto,
         n: usize,
     ) -> Result<(), ProofError> {
@@ -341,14 +339,14 @@ impl RangeProof {
     }
 
     /// Verifies an aggregated rangeproof for the given value commitments.
-    pub fn verify_multiple_with_rng<T: RngCore + CryptoRng>(
+    pub fn verify_multiple_with_rng<T: TranscriptBackend, R: RngCore + CryptoRng>(
         &self,
         bp_gens: &BulletproofGens,
         pc_gens: &PedersenGens,
-        transcript: &mut Transcript,
+        transcript: &mut T,
         value_commitments: &[CompressedRistretto],
         n: usize,
-        rng: &mut T,
+        rng: &mut R,
     ) -> Result<(), ProofError> {
         let m = value_commitments.len();
 
@@ -365,6 +363,7 @@ impl RangeProof {
         }
 
         transcript.rangeproof_domain_sep(n as u64, m as u64);
+        transcript.bind_generators(pc_gens, bp_gens, n, m);
 
         for V in value_commitments.iter() {
             // Allow the commitments to be zero (0 value, 0 blinding)
@@ -456,11 +455,11 @@ impl RangeProof {
     /// This is a convenience wrapper around [`RangeProof::verify_multiple_with_rng`],
     /// passing in a threadsafe RNG.
     #[cfg(feature = "std")]
-    pub fn verify_multiple(
+    pub fn verify_multiple<T: TranscriptBackend>(
         &self,
         bp_gens: &BulletproofGens,
         pc_gens: &PedersenGens,
-        transcript: &mut Transcript,
+        transcript: &mut T,
         value_commitments: &[CompressedRistretto],
         n: usize,
     ) -> Result<(), ProofError> {
@@ -597,6 +596,8 @@ fn delta(n: usize, m: usize, y: &Scalar, z: &Scalar) -> Scalar {
 mod tests {
     use super::*;
 
+    use merlin::Transcript;
+
     use crate::generators::PedersenGens;
 
     #[test]
@@ -798,6 +799,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn generator_binding_must_match() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 1);
+        let bound_gens = bp_gens.clone().with_transcript_binding();
+        let blinding = Scalar::random(&mut rand::thread_rng());
+
+        let mut transcript = Transcript::new(b"GeneratorBindingTest");
+        let (proof, V) =
+            RangeProof::prove_single(&bound_gens, &pc_gens, &mut transcript, 7, &blinding, 32)
+                .unwrap();
+
+        let mut transcript = Transcript::new(b"GeneratorBindingTest");
+        assert!(proof
+            .verify_single(&bound_gens, &pc_gens, &mut transcript, &V, 32)
+            .is_ok());
+
+        // The verifier must also bind the generators.
+        let mut transcript = Transcript::new(b"GeneratorBindingTest");
+        assert!(proof
+            .verify_single(&bp_gens, &pc_gens, &mut transcript, &V, 32)
+            .is_err());
+
+        // Bound generators of a larger capacity produce the same digest.
+        let bigger_gens = BulletproofGens::new(64, 2).with_transcript_binding();
+        let mut transcript = Transcript::new(b"GeneratorBindingTest");
+        assert!(proof
+            .verify_single(&bigger_gens, &pc_gens, &mut transcript, &V, 32)
+            .is_ok());
+    }
+
     #[test]
     fn detect_dishonest_dealer_during_aggregation() {
         use self::dealer::*;
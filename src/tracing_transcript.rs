@@ -0,0 +1,241 @@
+#![allow(non_snake_case)]
+
+//! A [`TranscriptBackend`] wrapper that records every transcript
+//! operation, for debugging verification failures.
+//!
+//! When a proof fails to verify, the usual culprit is a mismatch
+//! between the data the prover and the verifier appended to their
+//! transcripts.  Wrapping both transcripts in a [`TracingTranscript`]
+//! and comparing the recorded traces with [`first_mismatch`] points
+//! to the first operation where they diverged.
+//!
+//! Since the wrapper is itself a [`TranscriptBackend`], so is a
+//! mutable reference to it: passing `&mut tracing` to an API that
+//! consumes its transcript keeps the trace available even if that
+//! API returns an error.
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::transcript::TranscriptBackend;
+
+/// A single recorded transcript operation.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TraceEvent {
+    /// A message appended with `append_message` (or `append_u64`).
+    Message {
+        /// The label of the message.
+        label: &'static [u8],
+        /// The message bytes.
+        message: Vec<u8>,
+    },
+    /// Challenge bytes squeezed with `challenge_bytes`, e.g. for a
+    /// challenge scalar.
+    Challenge {
+        /// The label of the challenge.
+        label: &'static [u8],
+        /// The challenge bytes.
+        bytes: Vec<u8>,
+    },
+}
+
+impl TraceEvent {
+    /// Returns the label of the recorded operation.
+    pub fn label(&self) -> &'static [u8] {
+        match self {
+            TraceEvent::Message { label, .. } => label,
+            TraceEvent::Challenge { label, .. } => label,
+        }
+    }
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, label, bytes) = match self {
+            TraceEvent::Message { label, message } => ("append", label, message),
+            TraceEvent::Challenge { label, bytes } => ("challenge", label, bytes),
+        };
+        write!(f, "{} {:?}: ", kind, String::from_utf8_lossy(label))?;
+        for b in bytes {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+/// Wraps a [`TranscriptBackend`] and records every message appended
+/// to it and every challenge squeezed from it.
+#[derive(Clone)]
+pub struct TracingTranscript<T: TranscriptBackend> {
+    inner: T,
+    trace: Vec<TraceEvent>,
+}
+
+impl<T: TranscriptBackend> TracingTranscript<T> {
+    /// Starts recording operations on the `inner` transcript.
+    pub fn new(inner: T) -> Self {
+        TracingTranscript {
+            inner,
+            trace: Vec::new(),
+        }
+    }
+
+    /// Returns the operations recorded so far.
+    pub fn trace(&self) -> &[TraceEvent] {
+        &self.trace
+    }
+
+    /// Returns the wrapped transcript and the recorded operations.
+    pub fn into_parts(self) -> (T, Vec<TraceEvent>) {
+        (self.inner, self.trace)
+    }
+}
+
+impl<T: TranscriptBackend> TranscriptBackend for TracingTranscript<T> {
+    type RngBuilder = T::RngBuilder;
+
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        self.trace.push(TraceEvent::Message {
+            label,
+            message: message.to_vec(),
+        });
+        self.inner.append_message(label, message)
+    }
+
+    fn append_u64(&mut self, label: &'static [u8], x: u64) {
+        self.trace.push(TraceEvent::Message {
+            label,
+            message: x.to_le_bytes().to_vec(),
+        });
+        self.inner.append_u64(label, x)
+    }
+
+    fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+        self.inner.challenge_bytes(label, dest);
+        self.trace.push(TraceEvent::Challenge {
+            label,
+            bytes: dest.to_vec(),
+        });
+    }
+
+    fn build_rng(&self) -> Self::RngBuilder {
+        self.inner.build_rng()
+    }
+}
+
+/// The first point at which two transcript traces diverge.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TraceMismatch {
+    /// The index of the first differing operation.
+    pub index: usize,
+    /// The prover's operation at `index`, if its trace is long enough.
+    pub prover: Option<TraceEvent>,
+    /// The verifier's operation at `index`, if its trace is long enough.
+    pub verifier: Option<TraceEvent>,
+}
+
+impl TraceMismatch {
+    /// Returns the label of the first mismatching operation,
+    /// preferring the prover's label when both traces have one.
+    pub fn label(&self) -> &'static [u8] {
+        self.prover
+            .as_ref()
+            .or(self.verifier.as_ref())
+            .map(TraceEvent::label)
+            .unwrap_or(b"")
+    }
+}
+
+impl fmt::Display for TraceMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "transcripts diverge at operation {}:", self.index)?;
+        match &self.prover {
+            Some(event) => writeln!(f, "  prover:   {}", event)?,
+            None => writeln!(f, "  prover:   <end of trace>")?,
+        }
+        match &self.verifier {
+            Some(event) => write!(f, "  verifier: {}", event),
+            None => write!(f, "  verifier: <end of trace>"),
+        }
+    }
+}
+
+/// Compares a prover's trace against a verifier's trace, and returns
+/// the first operation at which they differ, or `None` if the traces
+/// are identical.
+pub fn first_mismatch(prover: &[TraceEvent], verifier: &[TraceEvent]) -> Option<TraceMismatch> {
+    let len = prover.len().max(verifier.len());
+    (0..len)
+        .find(|&i| prover.get(i) != verifier.get(i))
+        .map(|index| TraceMismatch {
+            index,
+            prover: prover.get(index).cloned(),
+            verifier: verifier.get(index).cloned(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{BulletproofGens, PedersenGens, RangeProof};
+    use curve25519_dalek::scalar::Scalar;
+    use merlin::Transcript;
+
+    #[test]
+    fn identical_traces_have_no_mismatch() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 1);
+        let blinding = Scalar::random(&mut rand::thread_rng());
+
+        let mut prover = TracingTranscript::new(Transcript::new(b"TracingTest"));
+        let (proof, V) =
+            RangeProof::prove_single(&bp_gens, &pc_gens, &mut prover, 42, &blinding, 32).unwrap();
+
+        let mut verifier = TracingTranscript::new(Transcript::new(b"TracingTest"));
+        assert!(proof
+            .verify_single(&bp_gens, &pc_gens, &mut verifier, &V, 32)
+            .is_ok());
+
+        assert_eq!(first_mismatch(prover.trace(), verifier.trace()), None);
+    }
+
+    #[test]
+    fn reports_first_mismatching_label() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 1);
+        let blinding = Scalar::random(&mut rand::thread_rng());
+
+        let mut prover = TracingTranscript::new(Transcript::new(b"TracingTest"));
+        let (proof, _V) =
+            RangeProof::prove_single(&bp_gens, &pc_gens, &mut prover, 42, &blinding, 32).unwrap();
+
+        // Verify against a commitment to a different value.
+        let wrong_V = pc_gens.commit(Scalar::from(43u64), blinding).compress();
+        let mut verifier = TracingTranscript::new(Transcript::new(b"TracingTest"));
+        assert!(proof
+            .verify_single(&bp_gens, &pc_gens, &mut verifier, &wrong_V, 32)
+            .is_err());
+
+        let mismatch = first_mismatch(prover.trace(), verifier.trace()).unwrap();
+        assert_eq!(mismatch.label(), b"V");
+        assert_eq!(mismatch.index, 3);
+    }
+
+    #[test]
+    fn shorter_trace_is_a_mismatch() {
+        let mut t1 = TracingTranscript::new(Transcript::new(b"TracingTest"));
+        let mut t2 = TracingTranscript::new(Transcript::new(b"TracingTest"));
+        t1.append_message(b"a", b"1");
+        t2.append_message(b"a", b"1");
+        t2.append_u64(b"b", 2);
+
+        let mismatch = first_mismatch(t1.trace(), t2.trace()).unwrap();
+        assert_eq!(mismatch.index, 1);
+        assert_eq!(mismatch.prover, None);
+        assert_eq!(mismatch.label(), b"b");
+    }
+}
@@ -1,10 +1,122 @@
-//! Defines a `TranscriptProtocol` trait for using a Merlin transcript.
+//! Defines a `TranscriptBackend` trait abstracting over the Fiat–Shamir
+//! transcript, and a `TranscriptProtocol` extension trait implementing
+//! the Bulletproofs-specific transcript operations on top of it.
 
 use curve25519_dalek::ristretto::CompressedRistretto;
 use curve25519_dalek::scalar::Scalar;
 use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
 
 use crate::errors::ProofError;
+use crate::generators::{BulletproofGens, PedersenGens};
+
+/// A Fiat–Shamir transcript which can absorb labeled messages and
+/// squeeze labeled challenges.
+///
+/// All proving and verification APIs in this crate are generic over
+/// this trait.  It is implemented for [`merlin::Transcript`], which is
+/// the default choice, and for [`HashTranscript`](crate::HashTranscript),
+/// which is built from a plain hash function.  It is also implemented
+/// for `&mut T` whenever `T` implements it, so that APIs taking a
+/// transcript by value (such as [`r1cs::Prover`](crate::r1cs::Prover))
+/// can borrow a caller-owned transcript instead.
+///
+/// The prover and the verifier must use the same backend, initialized
+/// with the same label, for a proof to verify.
+pub trait TranscriptBackend {
+    /// The builder for a witness-bound RNG, see [`TranscriptBackend::build_rng`].
+    type RngBuilder: WitnessRngBuilder;
+
+    /// Append a prover's `message` to the transcript.
+    ///
+    /// The `label` parameter is metadata about the message, and is
+    /// also appended to the transcript.
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]);
+
+    /// Append a `u64` as 8 little-endian bytes with the given `label`.
+    fn append_u64(&mut self, label: &'static [u8], x: u64) {
+        self.append_message(label, &x.to_le_bytes());
+    }
+
+    /// Fill the supplied buffer with the verifier's challenge bytes.
+    ///
+    /// The `label` parameter is metadata about the challenge, and is
+    /// also appended to the transcript.
+    fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]);
+
+    /// Fork the current transcript state to construct an RNG whose
+    /// output is bound to the current transcript state as well as
+    /// prover's secrets.
+    fn build_rng(&self) -> Self::RngBuilder;
+}
+
+/// Constructs an RNG by rekeying a forked transcript state with the
+/// prover's secrets, then finalizing it with external randomness.
+///
+/// This mirrors [`merlin::TranscriptRngBuilder`].
+pub trait WitnessRngBuilder: Sized {
+    /// The RNG produced by [`WitnessRngBuilder::finalize`].
+    type Rng: RngCore + CryptoRng;
+
+    /// Rekey the builder with the prover's secret `witness` data.
+    fn rekey_with_witness_bytes(self, label: &'static [u8], witness: &[u8]) -> Self;
+
+    /// Use the supplied external `rng` to rekey the builder and
+    /// produce the final RNG.
+    fn finalize<R: RngCore + CryptoRng>(self, rng: &mut R) -> Self::Rng;
+}
+
+impl TranscriptBackend for Transcript {
+    type RngBuilder = merlin::TranscriptRngBuilder;
+
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        Transcript::append_message(self, label, message)
+    }
+
+    fn append_u64(&mut self, label: &'static [u8], x: u64) {
+        Transcript::append_u64(self, label, x)
+    }
+
+    fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+        Transcript::challenge_bytes(self, label, dest)
+    }
+
+    fn build_rng(&self) -> Self::RngBuilder {
+        Transcript::build_rng(self)
+    }
+}
+
+impl WitnessRngBuilder for merlin::TranscriptRngBuilder {
+    type Rng = merlin::TranscriptRng;
+
+    fn rekey_with_witness_bytes(self, label: &'static [u8], witness: &[u8]) -> Self {
+        merlin::TranscriptRngBuilder::rekey_with_witness_bytes(self, label, witness)
+    }
+
+    fn finalize<R: RngCore + CryptoRng>(self, rng: &mut R) -> Self::Rng {
+        merlin::TranscriptRngBuilder::finalize(self, rng)
+    }
+}
+
+impl<T: TranscriptBackend + ?Sized> TranscriptBackend for &mut T {
+    type RngBuilder = T::RngBuilder;
+
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        (**self).append_message(label, message)
+    }
+
+    fn append_u64(&mut self, label: &'static [u8], x: u64) {
+        (**self).append_u64(label, x)
+    }
+
+    fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+        (**self).challenge_bytes(label, dest)
+    }
+
+    fn build_rng(&self) -> Self::RngBuilder {
+        (**self).build_rng()
+    }
+}
 
 pub trait TranscriptProtocol {
     /// Append a domain separator for an `n`-bit, `m`-party range proof.
@@ -13,6 +125,14 @@ pub trait TranscriptProtocol {
     /// Append a domain separator for a length-`n` inner product proof.
     fn innerproduct_domain_sep(&mut self, n: u64);
 
+    /// Append a domain separator for a linear proof of `k` relations
+    /// over vectors of length `n`.
+    fn linear_multi_domain_sep(&mut self, k: u64, n: u64);
+
+    /// Append a domain separator for a balance proof of `m` inputs and
+    /// `n` outputs.
+    fn balance_domain_sep(&mut self, m: u64, n: u64);
+
     /// Append a domain separator for a constraint system.
     fn r1cs_domain_sep(&mut self);
 
@@ -22,6 +142,31 @@ pub trait TranscriptProtocol {
     /// Commit a domain separator for a CS with randomized constraints.
     fn r1cs_2phase_domain_sep(&mut self);
 
+    /// Append a domain separator for a shuffle of `k` records of
+    /// `width` columns inside a constraint system.
+    #[cfg(feature = "yoloproofs")]
+    fn shuffle_domain_sep(&mut self, k: u64, width: u64);
+
+    /// Append a domain separator for a balance of `m` inputs and `n`
+    /// outputs of `bitsize` bits with the given `fee`, inside a
+    /// constraint system.
+    #[cfg(feature = "r1cs")]
+    fn r1cs_balance_domain_sep(&mut self, m: u64, n: u64, fee: u64, bitsize: u64);
+
+    /// If `bp_gens` has transcript binding enabled, append the digests
+    /// of `pc_gens` and of the `bp_gens` used for `m` parties of size `n`.
+    fn bind_generators(
+        &mut self,
+        pc_gens: &PedersenGens,
+        bp_gens: &BulletproofGens,
+        n: usize,
+        m: usize,
+    );
+
+    /// If `bp_gens` has transcript binding enabled, append the digest
+    /// of the `bp_gens` used for `m` parties of size `n`.
+    fn bind_bulletproof_gens(&mut self, bp_gens: &BulletproofGens, n: usize, m: usize);
+
     /// Append a `scalar` with the given `label`.
     fn append_scalar(&mut self, label: &'static [u8], scalar: &Scalar);
 
@@ -40,7 +185,7 @@ pub trait TranscriptProtocol {
     fn challenge_scalar(&mut self, label: &'static [u8]) -> Scalar;
 }
 
-impl TranscriptProtocol for Transcript {
+impl<T: TranscriptBackend + ?Sized> TranscriptProtocol for T {
     fn rangeproof_domain_sep(&mut self, n: u64, m: u64) {
         self.append_message(b"dom-sep", b"rangeproof v1");
         self.append_u64(b"n", n);
@@ -52,6 +197,20 @@ impl TranscriptProtocol for Transcript {
         self.append_u64(b"n", n);
     }
 
+    fn linear_multi_domain_sep(&mut self, k: u64, n: u64) {
+        self.append_message(b"dom-sep", b"linear-multi v1");
+        self.append_u64(b"k", k);
+        self.append_u64(b"n", n);
+    }
+
+    fn balance_domain_sep(&mut self, m: u64, n: u64) {
+        self.append_message(b"dom-sep", b"balance v1");
+        self.append_u64(b"m", m);
+        self.append_u64(b"n", n);
+    }
+
+    // The R1CS domain separators are part of the frozen `r1cs` proof
+    // format, and must not change.
     fn r1cs_domain_sep(&mut self) {
         self.append_message(b"dom-sep", b"r1cs v1");
     }
@@ -64,6 +223,41 @@ impl TranscriptProtocol for Transcript {
         self.append_message(b"dom-sep", b"r1cs-2phase");
     }
 
+    #[cfg(feature = "yoloproofs")]
+    fn shuffle_domain_sep(&mut self, k: u64, width: u64) {
+        self.append_message(b"dom-sep", b"shuffle v1");
+        self.append_u64(b"k", k);
+        self.append_u64(b"width", width);
+    }
+
+    #[cfg(feature = "r1cs")]
+    fn r1cs_balance_domain_sep(&mut self, m: u64, n: u64, fee: u64, bitsize: u64) {
+        self.append_message(b"dom-sep", b"r1cs-balance v1");
+        self.append_u64(b"m", m);
+        self.append_u64(b"n", n);
+        self.append_u64(b"fee", fee);
+        self.append_u64(b"bitsize", bitsize);
+    }
+
+    fn bind_generators(
+        &mut self,
+        pc_gens: &PedersenGens,
+        bp_gens: &BulletproofGens,
+        n: usize,
+        m: usize,
+    ) {
+        if bp_gens.binds_transcript() {
+            self.append_message(b"pc_gens", pc_gens.digest().as_bytes());
+        }
+        self.bind_bulletproof_gens(bp_gens, n, m);
+    }
+
+    fn bind_bulletproof_gens(&mut self, bp_gens: &BulletproofGens, n: usize, m: usize) {
+        if bp_gens.binds_transcript() {
+            self.append_message(b"bp_gens", bp_gens.digest(n, m).as_bytes());
+        }
+    }
+
     fn append_scalar(&mut self, label: &'static [u8], scalar: &Scalar) {
         self.append_message(label, scalar.as_bytes());
     }
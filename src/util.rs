@@ -15,7 +15,7 @@ pub struct VecPoly1(pub Vec<Scalar>, pub Vec<Scalar>);
 
 /// Represents a degree-3 vector polynomial
 /// \\(\mathbf{a} + \mathbf{b} \cdot x + \mathbf{c} \cdot x^2 + \mathbf{d} \cdot x^3 \\).
-#[cfg(feature = "yoloproofs")]
+#[cfg(feature = "r1cs")]
 pub struct VecPoly3(
     pub Vec<Scalar>,
     pub Vec<Scalar>,
@@ -28,7 +28,7 @@ pub struct Poly2(pub Scalar, pub Scalar, pub Scalar);
 
 /// Represents a degree-6 scalar polynomial, without the zeroth degree
 /// \\(a \cdot x + b \cdot x^2 + c \cdot x^3 + d \cdot x^4 + e \cdot x^5 + f \cdot x^6\\)
-#[cfg(feature = "yoloproofs")]
+#[cfg(feature = "r1cs")]
 pub struct Poly6 {
     pub t1: Scalar,
     pub t2: Scalar,
@@ -109,7 +109,7 @@ impl VecPoly1 {
     }
 }
 
-#[cfg(feature = "yoloproofs")]
+#[cfg(feature = "r1cs")]
 impl VecPoly3 {
     pub fn zero(n: usize) -> Self {
         VecPoly3(
@@ -160,7 +160,7 @@ impl Poly2 {
     }
 }
 
-#[cfg(feature = "yoloproofs")]
+#[cfg(feature = "r1cs")]
 impl Poly6 {
     pub fn eval(&self, x: Scalar) -> Scalar {
         x * (self.t1 + x * (self.t2 + x * (self.t3 + x * (self.t4 + x * (self.t5 + x * self.t6)))))
@@ -186,7 +186,7 @@ impl Drop for Poly2 {
     }
 }
 
-#[cfg(feature = "yoloproofs")]
+#[cfg(feature = "r1cs")]
 impl Drop for VecPoly3 {
     fn drop(&mut self) {
         for e in self.0.iter_mut() {
@@ -204,7 +204,7 @@ impl Drop for VecPoly3 {
     }
 }
 
-#[cfg(feature = "yoloproofs")]
+#[cfg(feature = "r1cs")]
 impl Drop for Poly6 {
     fn drop(&mut self) {
         self.t1.clear();
@@ -4,221 +4,214 @@ extern crate bulletproofs;
 extern crate curve25519_dalek;
 extern crate merlin;
 extern crate rand;
+extern crate rand_chacha;
 
+#[cfg(feature = "yoloproofs")]
+use bulletproofs::r1cs::gadgets::shuffle;
 use bulletproofs::r1cs::*;
 use bulletproofs::{BulletproofGens, PedersenGens};
 use curve25519_dalek::ristretto::CompressedRistretto;
 use curve25519_dalek::scalar::Scalar;
 use merlin::Transcript;
+#[cfg(feature = "yoloproofs")]
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaChaRng;
 
-// Shuffle gadget (documented in markdown file)
+// Shuffle gadget
 
-/// A proof-of-shuffle.
-struct ShuffleProof(R1CSProof);
-
-impl ShuffleProof {
-    fn gadget<CS: RandomizableConstraintSystem>(
-        cs: &mut CS,
-        x: Vec<Variable>,
-        y: Vec<Variable>,
-    ) -> Result<(), R1CSError> {
-        assert_eq!(x.len(), y.len());
-        let k = x.len();
-
-        if k == 1 {
-            cs.constrain(y[0] - x[0]);
-            return Ok(());
-        }
-
-        cs.specify_randomized_constraints(move |cs| {
-            let z = cs.challenge_scalar(b"shuffle challenge");
-
-            // Make last x multiplier for i = k-1 and k-2
-            let (_, _, last_mulx_out) = cs.multiply(x[k - 1] - z, x[k - 2] - z);
-
-            // Make multipliers for x from i == [0, k-3]
-            let first_mulx_out = (0..k - 2).rev().fold(last_mulx_out, |prev_out, i| {
-                let (_, _, o) = cs.multiply(prev_out.into(), x[i] - z);
-                o
-            });
-
-            // Make last y multiplier for i = k-1 and k-2
-            let (_, _, last_muly_out) = cs.multiply(y[k - 1] - z, y[k - 2] - z);
-
-            // Make multipliers for y from i == [0, k-3]
-            let first_muly_out = (0..k - 2).rev().fold(last_muly_out, |prev_out, i| {
-                let (_, _, o) = cs.multiply(prev_out.into(), y[i] - z);
-                o
-            });
-
-            // Constrain last x mul output and last y mul output to be equal
-            cs.constrain(first_mulx_out - first_muly_out);
-
-            Ok(())
-        })
-    }
+#[cfg(feature = "yoloproofs")]
+fn kshuffle_helper(k: usize) {
+    let bp_gens = BulletproofGens::new((2 * k).next_power_of_two(), 1);
+    assert!(kshuffle_roundtrip(k, &bp_gens, &bp_gens).is_ok());
 }
 
-impl ShuffleProof {
-    /// Attempt to construct a proof that `output` is a permutation of `input`.
-    ///
-    /// Returns a tuple `(proof, input_commitments || output_commitments)`.
-    pub fn prove<'a, 'b>(
-        pc_gens: &'b PedersenGens,
-        bp_gens: &'b BulletproofGens,
-        transcript: &'a mut Transcript,
-        input: &[Scalar],
-        output: &[Scalar],
-    ) -> Result<
-        (
-            ShuffleProof,
-            Vec<CompressedRistretto>,
-            Vec<CompressedRistretto>,
-        ),
-        R1CSError,
-    > {
-        // Apply a domain separator with the shuffle parameters to the transcript
-        // XXX should this be part of the gadget?
-        let k = input.len();
-        transcript.append_message(b"dom-sep", b"ShuffleProof");
-        transcript.append_u64(b"k", k as u64);
-
-        let mut prover = Prover::new(&pc_gens, transcript);
-
-        // Construct blinding factors using an RNG.
-        // Note: a non-example implementation would want to operate on existing commitments.
-        let mut blinding_rng = rand::thread_rng();
-
-        let (input_commitments, input_vars): (Vec<_>, Vec<_>) = input
-            .into_iter()
-            .map(|v| prover.commit(*v, Scalar::random(&mut blinding_rng)))
-            .unzip();
-
-        let (output_commitments, output_vars): (Vec<_>, Vec<_>) = output
-            .into_iter()
-            .map(|v| prover.commit(*v, Scalar::random(&mut blinding_rng)))
-            .unzip();
-
-        ShuffleProof::gadget(&mut prover, input_vars, output_vars)?;
+/// Proves a shuffle of `k` random values with `prover_gens`, and
+/// verifies it with `verifier_gens`.
+#[cfg(feature = "yoloproofs")]
+fn kshuffle_roundtrip(
+    k: usize,
+    prover_gens: &BulletproofGens,
+    verifier_gens: &BulletproofGens,
+) -> Result<(), R1CSError> {
+    use rand::Rng;
 
-        let proof = prover.prove(&bp_gens)?;
+    // Common code
+    let pc_gens = PedersenGens::default();
 
-        Ok((ShuffleProof(proof), input_commitments, output_commitments))
+    // Use a seeded RNG, so that each run proves and verifies the same statement
+    let mut rng = ChaChaRng::from_seed([k as u8; 32]);
+
+    // Randomly generate inputs and outputs to kshuffle, with the
+    // blindings of their existing commitments
+    let (min, max) = (0u64, std::u64::MAX);
+    let input: Vec<Vec<(Scalar, Scalar)>> = (0..k)
+        .map(|_| {
+            vec![(
+                Scalar::from(rng.gen_range(min..max)),
+                Scalar::random(&mut rng),
+            )]
+        })
+        .collect();
+    let mut output = input.clone();
+    output.shuffle(&mut rng);
+    // Rerandomize the shuffled commitments
+    for record in output.iter_mut() {
+        record[0].1 = Scalar::random(&mut rng);
     }
-}
-
-impl ShuffleProof {
-    /// Attempt to verify a `ShuffleProof`.
-    pub fn verify<'a, 'b>(
-        &self,
-        pc_gens: &'b PedersenGens,
-        bp_gens: &'b BulletproofGens,
-        transcript: &'a mut Transcript,
-        input_commitments: &Vec<CompressedRistretto>,
-        output_commitments: &Vec<CompressedRistretto>,
-    ) -> Result<(), R1CSError> {
-        // Apply a domain separator with the shuffle parameters to the transcript
-        // XXX should this be part of the gadget?
-        let k = input_commitments.len();
-        transcript.append_message(b"dom-sep", b"ShuffleProof");
-        transcript.append_u64(b"k", k as u64);
-
-        let mut verifier = Verifier::new(transcript);
-
-        let input_vars: Vec<_> = input_commitments
-            .iter()
-            .map(|V| verifier.commit(*V))
-            .collect();
 
-        let output_vars: Vec<_> = output_commitments
+    let commit = |records: &[Vec<(Scalar, Scalar)>]| -> Vec<Vec<CompressedRistretto>> {
+        records
             .iter()
-            .map(|V| verifier.commit(*V))
-            .collect();
-
-        ShuffleProof::gadget(&mut verifier, input_vars, output_vars)?;
-
-        verifier.verify(&self.0, &pc_gens, &bp_gens)?;
-        Ok(())
-    }
+            .map(|r| vec![pc_gens.commit(r[0].0, r[0].1).compress()])
+            .collect()
+    };
+    let (input_commitments, output_commitments) = (commit(&input), commit(&output));
+
+    let mut prover_transcript = Transcript::new(b"ShuffleProofTest");
+    let proof = shuffle::prove_with_rng(
+        &pc_gens,
+        prover_gens,
+        &mut prover_transcript,
+        &input,
+        &output,
+        &mut rng,
+    )?;
+
+    let mut verifier_transcript = Transcript::new(b"ShuffleProofTest");
+    shuffle::verify_with_rng(
+        &proof,
+        &pc_gens,
+        verifier_gens,
+        &mut verifier_transcript,
+        &input_commitments,
+        &output_commitments,
+        &mut rng,
+    )
 }
 
-fn kshuffle_helper(k: usize) {
-    use rand::Rng;
+#[cfg(feature = "yoloproofs")]
+#[test]
+fn shuffle_gadget_generator_binding_test() {
+    // The shuffle allocates its multipliers in the randomized phase,
+    // so the generators are bound both before the first phase and once
+    // the circuit size is known.
+    let bp_gens = BulletproofGens::new(8, 1);
+    let bound_gens = bp_gens.clone().with_transcript_binding();
+    assert!(kshuffle_roundtrip(4, &bound_gens, &bound_gens).is_ok());
+    assert!(kshuffle_roundtrip(4, &bound_gens, &bp_gens).is_err());
+    assert!(kshuffle_roundtrip(4, &bp_gens, &bound_gens).is_err());
+}
 
-    // Common code
+#[cfg(feature = "yoloproofs")]
+#[test]
+fn shuffle_gadget_check_satisfied() {
     let pc_gens = PedersenGens::default();
-    let bp_gens = BulletproofGens::new((2 * k).next_power_of_two(), 1);
-
-    let (proof, input_commitments, output_commitments) = {
-        // Randomly generate inputs and outputs to kshuffle
-        let mut rng = rand::thread_rng();
-        let (min, max) = (0u64, std::u64::MAX);
-        let input: Vec<Scalar> = (0..k)
-            .map(|_| Scalar::from(rng.gen_range(min..max)))
-            .collect();
-        let mut output = input.clone();
-        output.shuffle(&mut rand::thread_rng());
-
-        let mut prover_transcript = Transcript::new(b"ShuffleProofTest");
-        ShuffleProof::prove(&pc_gens, &bp_gens, &mut prover_transcript, &input, &output).unwrap()
+    let mut rng = ChaChaRng::from_seed([0u8; 32]);
+
+    let check = |input: &[u64], output: &[u64], rng: &mut ChaChaRng| {
+        let mut transcript = Transcript::new(b"ShuffleProofTest");
+        let mut prover = Prover::new(&pc_gens, &mut transcript);
+        let mut commit = |values: &[u64]| -> Vec<Variable> {
+            values
+                .iter()
+                .map(|v| prover.commit(Scalar::from(*v), Scalar::random(rng)).1)
+                .collect()
+        };
+        let (x, y) = (commit(input), commit(output));
+        shuffle::shuffle(&mut prover, x, y).unwrap();
+        prover.check_satisfied().unwrap()
     };
 
-    {
-        let mut verifier_transcript = Transcript::new(b"ShuffleProofTest");
-        assert!(proof
-            .verify(
-                &pc_gens,
-                &bp_gens,
-                &mut verifier_transcript,
-                &input_commitments,
-                &output_commitments
-            )
-            .is_ok());
-    }
+    assert_eq!(check(&[1, 2, 3], &[3, 1, 2], &mut rng), vec![]);
+    // The only constraint which fails is the randomized one, comparing
+    // the products of the two sides, which comes last.
+    assert_eq!(
+        check(&[1, 2, 3], &[3, 1, 1], &mut rng),
+        vec![UnsatisfiedConstraint::Linear {
+            index: 8,
+            label: None
+        }]
+    );
+}
+
+#[cfg(feature = "yoloproofs")]
+#[test]
+fn shuffle_gadget_export() {
+    let mut transcript = Transcript::new(b"ShuffleProofTest");
+    let mut verifier = Verifier::new(&mut transcript);
+    let vars: Vec<_> = (0..4)
+        .map(|_| verifier.commit(CompressedRistretto::default()))
+        .collect();
+    shuffle::shuffle(&mut verifier, vars[..2].to_vec(), vars[2..].to_vec()).unwrap();
+    let circuit = verifier.export().unwrap();
+
+    assert!(circuit.is_randomized());
+    let metrics = circuit.metrics();
+    assert_eq!(metrics.multipliers, 2);
+    assert_eq!(metrics.phase_one_constraints, 0);
+    assert_eq!(metrics.phase_two_constraints, 5);
+
+    let text = circuit.to_text();
+    assert!(text.contains("phase 2\nchallenge 0x"));
+    assert!(text.ends_with("constraint 1*O0 -1*O1\n"));
+    assert_eq!(ExportedCircuit::from_text(&text).unwrap(), circuit);
+
+    // Randomized constraints are not replayed with the recorded challenges.
+    let mut transcript = Transcript::new(b"ShuffleProofTest");
+    let mut verifier = Verifier::new(&mut transcript);
+    assert!(circuit.replay(&mut verifier, None).is_err());
 }
 
+#[cfg(feature = "yoloproofs")]
 #[test]
 fn shuffle_gadget_test_1() {
     kshuffle_helper(1);
 }
 
+#[cfg(feature = "yoloproofs")]
 #[test]
 fn shuffle_gadget_test_2() {
     kshuffle_helper(2);
 }
 
+#[cfg(feature = "yoloproofs")]
 #[test]
 fn shuffle_gadget_test_3() {
     kshuffle_helper(3);
 }
 
+#[cfg(feature = "yoloproofs")]
 #[test]
 fn shuffle_gadget_test_4() {
     kshuffle_helper(4);
 }
 
+#[cfg(feature = "yoloproofs")]
 #[test]
 fn shuffle_gadget_test_5() {
     kshuffle_helper(5);
 }
 
+#[cfg(feature = "yoloproofs")]
 #[test]
 fn shuffle_gadget_test_6() {
     kshuffle_helper(6);
 }
 
+#[cfg(feature = "yoloproofs")]
 #[test]
 fn shuffle_gadget_test_7() {
     kshuffle_helper(7);
 }
 
+#[cfg(feature = "yoloproofs")]
 #[test]
 fn shuffle_gadget_test_24() {
     kshuffle_helper(24);
 }
 
+#[cfg(feature = "yoloproofs")]
 #[test]
 fn shuffle_gadget_test_42() {
     kshuffle_helper(42);
@@ -239,7 +232,7 @@ fn example_gadget<CS: ConstraintSystem>(
 }
 
 // Prover's scope
-fn example_gadget_proof(
+fn example_gadget_proof<R: RngCore + CryptoRng>(
     pc_gens: &PedersenGens,
     bp_gens: &BulletproofGens,
     a1: u64,
@@ -248,6 +241,7 @@ fn example_gadget_proof(
     b2: u64,
     c1: u64,
     c2: u64,
+    rng: &mut R,
 ) -> Result<(R1CSProof, Vec<CompressedRistretto>), R1CSError> {
     let mut transcript = Transcript::new(b"R1CSExampleGadget");
 
@@ -257,7 +251,7 @@ fn example_gadget_proof(
     // 2. Commit high-level variables
     let (commitments, vars): (Vec<_>, Vec<_>) = [a1, a2, b1, b2, c1]
         .into_iter()
-        .map(|x| prover.commit(Scalar::from(*x), Scalar::random(&mut thread_rng())))
+        .map(|x| prover.commit(Scalar::from(*x), Scalar::random(rng)))
         .unzip();
 
     // 3. Build a CS
@@ -272,23 +266,19 @@ fn example_gadget_proof(
     );
 
     // 4. Make a proof
-    let proof = prover.prove(bp_gens)?;
+    let proof = prover.prove_with_rng(bp_gens, rng)?;
 
     Ok((proof, commitments))
 }
 
 // Verifier logic
-fn example_gadget_verify(
-    pc_gens: &PedersenGens,
-    bp_gens: &BulletproofGens,
+fn example_gadget_verifier<'t>(
+    transcript: &'t mut Transcript,
     c2: u64,
-    proof: R1CSProof,
-    commitments: Vec<CompressedRistretto>,
-) -> Result<(), R1CSError> {
-    let mut transcript = Transcript::new(b"R1CSExampleGadget");
-
+    commitments: &[CompressedRistretto],
+) -> Verifier<&'t mut Transcript> {
     // 1. Create a verifier
-    let mut verifier = Verifier::new(&mut transcript);
+    let mut verifier = Verifier::new(transcript);
 
     // 2. Commit high-level variables
     let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
@@ -304,9 +294,23 @@ fn example_gadget_verify(
         Scalar::from(c2).into(),
     );
 
+    verifier
+}
+
+fn example_gadget_verify<R: RngCore + CryptoRng>(
+    pc_gens: &PedersenGens,
+    bp_gens: &BulletproofGens,
+    c2: u64,
+    proof: R1CSProof,
+    commitments: Vec<CompressedRistretto>,
+    rng: &mut R,
+) -> Result<(), R1CSError> {
+    let mut transcript = Transcript::new(b"R1CSExampleGadget");
+    let verifier = example_gadget_verifier(&mut transcript, c2, &commitments);
+
     // 4. Verify the proof
     verifier
-        .verify(&proof, &pc_gens, &bp_gens)
+        .verify_with_rng(&proof, &pc_gens, &bp_gens, rng)
         .map_err(|_| R1CSError::VerificationError)
 }
 
@@ -321,10 +325,12 @@ fn example_gadget_roundtrip_helper(
     // Common
     let pc_gens = PedersenGens::default();
     let bp_gens = BulletproofGens::new(128, 1);
+    let mut rng = ChaChaRng::from_seed([0u8; 32]);
 
-    let (proof, commitments) = example_gadget_proof(&pc_gens, &bp_gens, a1, a2, b1, b2, c1, c2)?;
+    let (proof, commitments) =
+        example_gadget_proof(&pc_gens, &bp_gens, a1, a2, b1, b2, c1, c2, &mut rng)?;
 
-    example_gadget_verify(&pc_gens, &bp_gens, c2, proof, commitments)
+    example_gadget_verify(&pc_gens, &bp_gens, c2, proof, commitments, &mut rng)
 }
 
 fn example_gadget_roundtrip_serialization_helper(
@@ -338,14 +344,16 @@ fn example_gadget_roundtrip_serialization_helper(
     // Common
     let pc_gens = PedersenGens::default();
     let bp_gens = BulletproofGens::new(128, 1);
+    let mut rng = ChaChaRng::from_seed([0u8; 32]);
 
-    let (proof, commitments) = example_gadget_proof(&pc_gens, &bp_gens, a1, a2, b1, b2, c1, c2)?;
+    let (proof, commitments) =
+        example_gadget_proof(&pc_gens, &bp_gens, a1, a2, b1, b2, c1, c2, &mut rng)?;
 
     let proof = proof.to_bytes();
 
     let proof = R1CSProof::from_bytes(&proof)?;
 
-    example_gadget_verify(&pc_gens, &bp_gens, c2, proof, commitments)
+    example_gadget_verify(&pc_gens, &bp_gens, c2, proof, commitments, &mut rng)
 }
 
 #[test]
@@ -356,6 +364,605 @@ fn example_gadget_test() {
     assert!(example_gadget_roundtrip_helper(3, 4, 6, 1, 40, 10).is_err());
 }
 
+#[test]
+fn example_gadget_generator_binding_test() {
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+    let bound_gens = bp_gens.clone().with_transcript_binding();
+    let mut rng = ChaChaRng::from_seed([0u8; 32]);
+
+    let (proof, commitments) =
+        example_gadget_proof(&pc_gens, &bound_gens, 3, 4, 6, 1, 40, 9, &mut rng).unwrap();
+    assert!(example_gadget_verify(
+        &pc_gens,
+        &bound_gens,
+        9,
+        proof.clone(),
+        commitments.clone(),
+        &mut rng
+    )
+    .is_ok());
+    assert!(example_gadget_verify(&pc_gens, &bp_gens, 9, proof, commitments, &mut rng).is_err());
+}
+
+#[test]
+fn example_gadget_batch_test() {
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+    let mut rng = ChaChaRng::from_seed([0u8; 32]);
+
+    // (3 + 4) * (6 + 1) = (40 + 9), and likewise for c1 = 40 - i, c2 = 9 + i
+    let proofs: Vec<_> = (0..4)
+        .map(|i| example_gadget_proof(&pc_gens, &bp_gens, 3, 4, 6, 1, 40 - i, 9 + i, &mut rng))
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    let verify_batch = |c2s: &[u64], rng: &mut ChaChaRng| {
+        let mut transcripts = vec![Transcript::new(b"R1CSExampleGadget"); proofs.len()];
+        let batch = transcripts.iter_mut().zip(c2s).zip(proofs.iter()).map(
+            |((transcript, c2), (proof, commitments))| {
+                (example_gadget_verifier(transcript, *c2, commitments), proof)
+            },
+        );
+        Verifier::verify_batch_with_rng(batch, &pc_gens, &bp_gens, rng)
+    };
+
+    assert!(verify_batch(&[9, 10, 11, 12], &mut rng).is_ok());
+    assert_eq!(
+        verify_batch(&[9, 10, 12, 12], &mut rng),
+        Err(R1CSError::BatchVerificationError { index: 2 })
+    );
+}
+
+#[test]
+fn example_gadget_circuit_description_test() {
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+    let mut rng = ChaChaRng::from_seed([0u8; 32]);
+
+    let record = |c2: u64| {
+        CircuitDescription::record(5, |cs, vars| {
+            example_gadget(
+                cs,
+                vars[0].into(),
+                vars[1].into(),
+                vars[2].into(),
+                vars[3].into(),
+                vars[4].into(),
+                Scalar::from(c2).into(),
+            );
+            Ok(())
+        })
+        .unwrap()
+    };
+    let circuit = record(9);
+    assert_eq!(circuit.num_commitments(), 5);
+    assert_eq!(circuit.num_multipliers(), 1);
+
+    // The recorded circuit survives a serialization roundtrip.
+    let bytes = bincode::serialize(&circuit).unwrap();
+    let circuit: CircuitDescription = bincode::deserialize(&bytes).unwrap();
+
+    let (proof, commitments) =
+        example_gadget_proof(&pc_gens, &bp_gens, 3, 4, 6, 1, 40, 9, &mut rng).unwrap();
+    let verify = |circuit: &CircuitDescription, commitments: &[_], rng: &mut ChaChaRng| {
+        let mut transcript = Transcript::new(b"R1CSExampleGadget");
+        circuit.verify_with_rng(
+            &mut transcript,
+            commitments,
+            &proof,
+            &pc_gens,
+            &bp_gens,
+            rng,
+        )
+    };
+
+    assert!(verify(&circuit, &commitments, &mut rng).is_ok());
+    assert!(verify(&record(10), &commitments, &mut rng).is_err());
+
+    // A verifier built from the description holds the recorded
+    // constraints, as if the gadget had been run.
+    let exported = |verifier: Verifier<Transcript>| verifier.export().unwrap().to_text();
+    let mut verifier = Verifier::new(Transcript::new(b"R1CSExampleGadget"));
+    let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+    example_gadget(
+        &mut verifier,
+        vars[0].into(),
+        vars[1].into(),
+        vars[2].into(),
+        vars[3].into(),
+        vars[4].into(),
+        Scalar::from(9u64).into(),
+    );
+    assert_eq!(
+        exported(
+            circuit
+                .verifier(Transcript::new(b"R1CSExampleGadget"), &commitments)
+                .unwrap()
+        ),
+        exported(verifier)
+    );
+    assert!(verify(&circuit, &commitments[..4], &mut rng).is_err());
+
+    // A description referring to multipliers it does not have is rejected.
+    let mut bytes = bytes;
+    bytes[8..16].copy_from_slice(&0u64.to_le_bytes());
+    assert!(bincode::deserialize::<CircuitDescription>(&bytes).is_err());
+
+    // So is a description claiming more multipliers than any proof
+    // can have, without allocating for them.
+    for num_multipliers in [CircuitDescription::MAX_MULTIPLIERS as u64 + 1, u64::MAX] {
+        bytes[8..16].copy_from_slice(&num_multipliers.to_le_bytes());
+        assert!(bincode::deserialize::<CircuitDescription>(&bytes).is_err());
+    }
+    // A large number of commitments does not allocate either.
+    bytes[8..16].copy_from_slice(&1u64.to_le_bytes());
+    bytes[0..8].copy_from_slice(&u64::MAX.to_le_bytes());
+    let circuit: CircuitDescription = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(circuit.num_commitments(), u64::MAX as usize);
+}
+
+/// Enforces that `v0 * v1 = v2` with randomized constraints whose
+/// coefficients are polynomials in two challenges, computing
+/// `(v0 - zw)(v1 + w) = v2 + w v0 - zw v1 - zw^2`.
+#[cfg(feature = "yoloproofs")]
+fn randomized_gadget<CS: RandomizableConstraintSystem>(
+    cs: &mut CS,
+    vars: Vec<Variable>,
+) -> Result<(), R1CSError> {
+    cs.specify_randomized_constraints(move |cs| {
+        let z = cs.challenge_scalar(b"z");
+        let w = cs.challenge_scalar(b"w");
+        let (_, _, o) = cs.multiply(vars[0] - z * w, vars[1] + w);
+        cs.constrain(o - vars[2] - w * vars[0] + z * w * vars[1] + z * w * w);
+        Ok(())
+    })
+}
+
+#[cfg(feature = "yoloproofs")]
+#[test]
+fn randomized_circuit_description() {
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+    let mut rng = ChaChaRng::from_seed([0u8; 32]);
+
+    let circuit = CircuitDescription::record(3, |cs, vars| randomized_gadget(cs, vars)).unwrap();
+    assert_eq!(circuit.num_multipliers(), 1);
+    assert_eq!(circuit.metrics().phase_two_constraints, 3);
+    let bytes = bincode::serialize(&circuit).unwrap();
+    let circuit: CircuitDescription = bincode::deserialize(&bytes).unwrap();
+
+    let prove = |values: [u64; 3], rng: &mut ChaChaRng| {
+        let mut prover = Prover::new(&pc_gens, Transcript::new(b"RandomizedTest"));
+        let (commitments, vars): (Vec<_>, Vec<_>) = values
+            .iter()
+            .map(|v| prover.commit(Scalar::from(*v), Scalar::random(rng)))
+            .unzip();
+        randomized_gadget(&mut prover, vars).unwrap();
+        (prover.prove_with_rng(&bp_gens, rng).unwrap(), commitments)
+    };
+    let verify = |(proof, commitments): &(R1CSProof, Vec<_>), rng: &mut ChaChaRng| {
+        circuit.verify_with_rng(
+            Transcript::new(b"RandomizedTest"),
+            commitments,
+            proof,
+            &pc_gens,
+            &bp_gens,
+            rng,
+        )
+    };
+    let valid = prove([3, 5, 15], &mut rng);
+    assert!(verify(&valid, &mut rng).is_ok());
+    assert!(verify(&prove([3, 5, 16], &mut rng), &mut rng).is_err());
+
+    // A verifier built from the description draws the same challenges
+    // and holds the same randomized constraints as the gadget.
+    let exported = |verifier: Verifier<Transcript>| verifier.export().unwrap().to_text();
+    let mut verifier = Verifier::new(Transcript::new(b"RandomizedTest"));
+    let vars = valid.1.iter().map(|V| verifier.commit(*V)).collect();
+    randomized_gadget(&mut verifier, vars).unwrap();
+    assert_eq!(
+        exported(
+            circuit
+                .verifier(Transcript::new(b"RandomizedTest"), &valid.1)
+                .unwrap()
+        ),
+        exported(verifier)
+    );
+}
+
+#[cfg(feature = "yoloproofs")]
+#[test]
+fn randomized_circuit_description_is_rejected() {
+    // Coefficients which are not polynomials in the challenges.
+    let recorded = CircuitDescription::record(2, |cs, vars| {
+        cs.specify_randomized_constraints(move |cs| {
+            let z = cs.challenge_scalar(b"z");
+            cs.constrain(vars[0] - z.invert() * vars[1]);
+            Ok(())
+        })
+    });
+    assert_eq!(recorded, Err(R1CSError::RandomizedCircuitNotRecordable));
+
+    // Randomized constraints whose shape depends on the challenges.
+    let recorded = CircuitDescription::record(1, |cs, vars| {
+        cs.specify_randomized_constraints(move |cs| {
+            if cs.challenge_scalar(b"z") != Scalar::ZERO {
+                cs.constrain(vars[0].into());
+            }
+            Ok(())
+        })
+    });
+    assert_eq!(recorded, Err(R1CSError::RandomizedCircuitNotRecordable));
+
+    // Challenges drawn before the randomized phase.
+    let recorded = CircuitDescription::record(1, |cs, _| {
+        let mut challenge = [0u8; 64];
+        bulletproofs::TranscriptBackend::challenge_bytes(cs.transcript(), b"early", &mut challenge);
+        Ok(())
+    });
+    assert_eq!(recorded, Err(R1CSError::RandomizedCircuitNotRecordable));
+}
+
+#[test]
+fn example_gadget_check_satisfied() {
+    let pc_gens = PedersenGens::default();
+    let mut rng = ChaChaRng::from_seed([0u8; 32]);
+
+    let check = |c2: u64, rng: &mut ChaChaRng| {
+        let mut transcript = Transcript::new(b"R1CSExampleGadget");
+        let mut prover = Prover::new(&pc_gens, &mut transcript);
+        let vars: Vec<_> = [3u64, 4, 6, 1, 40]
+            .iter()
+            .map(|x| prover.commit(Scalar::from(*x), Scalar::random(rng)).1)
+            .collect();
+        example_gadget(
+            &mut prover,
+            vars[0].into(),
+            vars[1].into(),
+            vars[2].into(),
+            vars[3].into(),
+            vars[4].into(),
+            Scalar::from(c2).into(),
+        );
+        prover.check_satisfied().unwrap()
+    };
+
+    // (3 + 4) * (6 + 1) = (40 + 9)
+    assert_eq!(check(9, &mut rng), vec![]);
+    // (3 + 4) * (6 + 1) != (40 + 10), which violates the third
+    // constraint, after the two constraining the multiplier inputs.
+    assert_eq!(
+        check(10, &mut rng),
+        vec![UnsatisfiedConstraint::Linear {
+            index: 2,
+            label: None
+        }]
+    );
+}
+
+#[test]
+fn example_gadget_namespaces() {
+    let pc_gens = PedersenGens::default();
+    let mut rng = ChaChaRng::from_seed([0u8; 32]);
+
+    let mut transcript = Transcript::new(b"R1CSExampleGadget");
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+    let vars: Vec<_> = [3u64, 4, 6, 1, 40]
+        .iter()
+        .map(|x| prover.commit(Scalar::from(*x), Scalar::random(&mut rng)).1)
+        .collect();
+    prover.namespace(
+        || "outer",
+        |cs| {
+            cs.namespace(
+                || "example",
+                |cs| {
+                    // (3 + 4) * (6 + 1) != (40 + 10)
+                    example_gadget(
+                        cs,
+                        vars[0].into(),
+                        vars[1].into(),
+                        vars[2].into(),
+                        vars[3].into(),
+                        vars[4].into(),
+                        Scalar::from(10u64).into(),
+                    )
+                },
+            );
+            cs.multiply(vars[0].into(), vars[1].into());
+        },
+    );
+
+    // Entering a namespace again adds to its metrics.
+    prover.namespace(|| "outer", |cs| cs.constrain(vars[0] - vars[0]));
+
+    assert_eq!(prover.metrics().multipliers, 2);
+    assert_eq!(
+        prover.metrics().namespaces,
+        vec![
+            NamespaceMetrics {
+                path: "outer".into(),
+                multipliers: 2,
+                constraints: 6,
+            },
+            NamespaceMetrics {
+                path: "outer/example".into(),
+                multipliers: 1,
+                constraints: 3,
+            },
+        ]
+    );
+
+    assert_eq!(
+        prover.check_satisfied().unwrap(),
+        vec![UnsatisfiedConstraint::Linear {
+            index: 2,
+            label: Some("outer/example".into()),
+        }]
+    );
+}
+
+#[test]
+fn namespace_names_with_slashes_are_escaped() {
+    let mut transcript = Transcript::new(b"R1CSNamespaces");
+    let mut verifier = Verifier::new(&mut transcript);
+    let var = verifier.commit(Default::default());
+
+    // A name containing `/` is not merged with nested namespaces.
+    verifier.namespace(|| "a/b", |cs| cs.constrain(var.into()));
+    verifier.namespace(
+        || "a",
+        |cs| cs.namespace(|| "b", |cs| cs.constrain(var - var)),
+    );
+    verifier.namespace(|| "a\\", |cs| cs.constrain(var + var));
+
+    assert_eq!(
+        verifier.metrics().namespaces,
+        vec![
+            NamespaceMetrics {
+                path: "a\\/b".into(),
+                multipliers: 0,
+                constraints: 1,
+            },
+            NamespaceMetrics {
+                path: "a".into(),
+                multipliers: 0,
+                constraints: 1,
+            },
+            NamespaceMetrics {
+                path: "a/b".into(),
+                multipliers: 0,
+                constraints: 1,
+            },
+            NamespaceMetrics {
+                path: "a\\\\".into(),
+                multipliers: 0,
+                constraints: 1,
+            },
+        ]
+    );
+}
+
+#[cfg(feature = "yoloproofs")]
+#[test]
+fn randomized_constraints_keep_their_namespace() {
+    let mut transcript = Transcript::new(b"NamespaceTest");
+    let mut verifier = Verifier::new(&mut transcript);
+    let vars: Vec<_> = (0..2)
+        .map(|_| verifier.commit(Default::default()))
+        .collect();
+    verifier
+        .namespace(
+            || "randomized",
+            |cs| {
+                cs.specify_randomized_constraints(move |cs| {
+                    let z = cs.challenge_scalar(b"z");
+                    cs.multiply(vars[0] - z, vars[1] - z);
+                    Ok(())
+                })
+            },
+        )
+        .unwrap();
+    // The callback runs on export, while another namespace is open.
+    verifier.push_namespace(|| "open");
+    let exported = verifier.export().unwrap();
+
+    assert_eq!(exported.metrics().multipliers, 1);
+    let text = exported.to_text();
+    assert!(text.contains("# randomized"));
+    assert!(!text.contains("# open"));
+}
+
+#[test]
+fn example_gadget_export() {
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+    let mut rng = ChaChaRng::from_seed([0u8; 32]);
+    let (proof, commitments) =
+        example_gadget_proof(&pc_gens, &bp_gens, 3, 4, 6, 1, 40, 9, &mut rng).unwrap();
+
+    let mut transcript = Transcript::new(b"R1CSExampleGadget");
+    let mut verifier = Verifier::new(&mut transcript);
+    let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+    verifier.namespace(
+        || "example",
+        |cs| {
+            example_gadget(
+                cs,
+                vars[0].into(),
+                vars[1].into(),
+                vars[2].into(),
+                vars[3].into(),
+                vars[4].into(),
+                Scalar::from(9u64).into(),
+            )
+        },
+    );
+    let circuit = verifier.export().unwrap();
+
+    let text = "\
+r1cs-circuit v1
+commitments 5
+phase 1
+multiplier 0 # example
+constraint 1*V0 1*V1 -1*L0 # example
+constraint 1*V2 1*V3 -1*R0 # example
+constraint 1*V4 9*1 -1*O0 # example
+";
+    assert_eq!(circuit.to_text(), text);
+    assert_eq!(ExportedCircuit::from_text(text).unwrap(), circuit);
+    assert!(!circuit.is_randomized());
+
+    // Blank and comment-only lines are skipped.
+    let commented = "\
+# The example gadget, with c2 = 9.
+r1cs-circuit v1
+commitments 5
+
+phase 1
+  # One multiplier and its input and output constraints.
+multiplier 0 # example
+constraint 1*V0 1*V1 -1*L0 # example
+constraint 1*V2 1*V3 -1*R0 # example
+constraint 1*V4 9*1 -1*O0 # example
+#
+";
+    let imported = ExportedCircuit::from_text(commented).unwrap();
+    assert_eq!(imported, circuit);
+    assert_eq!(imported.to_text(), text);
+    assert_eq!(circuit.metrics().multipliers, 1);
+
+    // The imported circuit verifies the proof of the original gadget...
+    let mut transcript = Transcript::new(b"R1CSExampleGadget");
+    let mut verifier = Verifier::new(&mut transcript);
+    for V in commitments.iter() {
+        verifier.commit(*V);
+    }
+    circuit.replay(&mut verifier, None).unwrap();
+    assert!(verifier
+        .verify_with_rng(&proof, &pc_gens, &bp_gens, &mut rng)
+        .is_ok());
+
+    // ...and proves it, given the assignments of the multipliers.
+    let mut transcript = Transcript::new(b"R1CSExampleGadget");
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+    let commitments: Vec<_> = [3u64, 4, 6, 1, 40]
+        .iter()
+        .map(|x| prover.commit(Scalar::from(*x), Scalar::random(&mut rng)).0)
+        .collect();
+    let assignments = [(Scalar::from(7u64), Scalar::from(7u64))];
+    circuit.replay(&mut prover, Some(&assignments)).unwrap();
+    let proof = prover.prove_with_rng(&bp_gens, &mut rng).unwrap();
+    assert!(example_gadget_verify(&pc_gens, &bp_gens, 9, proof, commitments, &mut rng).is_ok());
+
+    // Replaying after other multipliers wires the constraints to the
+    // replayed multipliers, rather than to the existing ones.
+    let mut transcript = Transcript::new(b"R1CSReplayAfterGadget");
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+    let vars: Vec<_> = [3u64, 4, 6, 1, 40]
+        .iter()
+        .map(|x| prover.commit(Scalar::from(*x), Scalar::random(&mut rng)))
+        .collect();
+    let (_, _, square) = prover.multiply(vars[0].1.into(), vars[0].1.into());
+    prover.constrain(square - 9u64);
+    circuit.replay(&mut prover, Some(&assignments)).unwrap();
+    assert_eq!(prover.metrics().multipliers, 2);
+    let proof = prover.prove_with_rng(&bp_gens, &mut rng).unwrap();
+
+    let mut transcript = Transcript::new(b"R1CSReplayAfterGadget");
+    let mut verifier = Verifier::new(&mut transcript);
+    let vars: Vec<_> = vars.iter().map(|(V, _)| verifier.commit(*V)).collect();
+    let (_, _, square) = verifier.multiply(vars[0].into(), vars[0].into());
+    verifier.constrain(square - 9u64);
+    circuit.replay(&mut verifier, None).unwrap();
+    assert!(verifier
+        .verify_with_rng(&proof, &pc_gens, &bp_gens, &mut rng)
+        .is_ok());
+
+    for malformed in [
+        "r1cs-circuit v2\ncommitments 5\nphase 1\n",
+        "r1cs-circuit v1\ncommitments 5\nphase 2\n",
+        "r1cs-circuit v1\ncommitments 5\nphase 1\nconstraint 1*V5\n",
+        "r1cs-circuit v1\ncommitments 5\nphase 1\nconstraint 1*L0\n",
+        "r1cs-circuit v1\ncommitments 5\nphase 1\nconstraint 1*X0\n",
+        "r1cs-circuit v1\ncommitments 5\nphase 1\nmultiplier 1\n",
+    ]
+    .iter()
+    {
+        assert_eq!(
+            ExportedCircuit::from_text(malformed),
+            Err(R1CSError::FormatError)
+        );
+    }
+}
+
+#[test]
+fn example_gadget_deterministic_test() {
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+
+    let prove = |seed: u8| {
+        let mut rng = ChaChaRng::from_seed([seed; 32]);
+        let (proof, commitments) =
+            example_gadget_proof(&pc_gens, &bp_gens, 3, 4, 6, 1, 40, 9, &mut rng).unwrap();
+        (proof.to_bytes(), commitments)
+    };
+
+    // The same seed reproduces the same proof and commitments...
+    assert_eq!(prove(1), prove(1));
+    // ...and a different seed does not.
+    assert_ne!(prove(1), prove(2));
+}
+
+// Tests that the R1CS proof format and transcript domain separators,
+// which are frozen for the `r1cs` feature, stay unchanged.
+#[test]
+fn example_gadget_test_vector() {
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+
+    let proof = concat!(
+        "0092fea1762682555b3b85df5231e6e86c854e0402f8972d0f565d0f153e8049",
+        "1542e2498beb71a013d05cadab3f5bd0194a2ec354831dabd90c609d9db8654f",
+        "18f0784bd34fb03d7a597fdf5aee9c2a946e866742007e7338477f58661feed7",
+        "5ebe66908772d0c2a647aef54ed6d32b127a107442793b08ca4ec84a3f725ba9",
+        "24eabd572925904777d551cf633aee90a583499c13ceda5320965d1dd1c43d5a",
+        "02a6cc4181696685e05aa0224522412687b56d2ce10417385ba9249ec231fe65",
+        "3d46148a57714195174e6216126a3a86c044f86c40304bbf08ee47b10f18cb55",
+        "4de6ff1204f3207dcf419b5bc420f3e65b32166adac952ea93b5b60d5fc7ff7a",
+        "5be88e674f00effbfc2af3144f6adfa3d37f9eed9e2a72e92ebc57915dd7b680",
+        "009a9e0ab7ebe1cd3afcc4019d3e9cd6a782187133c2493a233b9efc91e584ea",
+        "0e9bc42aa1ab6a65bb200d163385a888a74d295d23050d741471cc154e0719c3",
+        "01545c47b89d02c5039c319bb60f1b142e4e1d2512d880b3d6b07005d4efe695",
+        "0a92c8fede8f8c41cdda5cace6419e2bf5d828757feedfdd08f8bbbaa707e2c5",
+        "04",
+    );
+    let proof = R1CSProof::from_bytes(&hex::decode(proof).unwrap()).unwrap();
+    let commitments: Vec<_> = [
+        "b0bee44e99d03f331a5c408ce1fb1e02c34a6620ac0ada0da01dc345cdd40732",
+        "72c73970c42fd37b372f725638f2fad00d3865a59e3b3046dfe824c2cd4f6a32",
+        "8eca589f6a45993438adc5528ea2877e267dabf3cce56cbf4ec8c99d58d7b40e",
+        "5611de667726715c3702facdd11ce0b6910e3ed22190653c39df2b8ab80f7c40",
+        "e8fdf5876ce716cfbd6ced7fbc78e545830a06212e263f965ab0669aeccaea28",
+    ]
+    .iter()
+    .map(|V| CompressedRistretto::from_slice(&hex::decode(V).unwrap()).unwrap())
+    .collect();
+
+    let mut rng = ChaChaRng::from_seed([0u8; 32]);
+    assert!(example_gadget_verify(
+        &pc_gens,
+        &bp_gens,
+        9,
+        proof.clone(),
+        commitments.clone(),
+        &mut rng
+    )
+    .is_ok());
+    assert!(example_gadget_verify(&pc_gens, &bp_gens, 10, proof, commitments, &mut rng).is_err());
+}
+
 #[test]
 fn example_gadget_serialization_test() {
     // (3 + 4) * (6 + 1) = (40 + 9)
@@ -403,23 +1010,26 @@ pub fn range_proof<CS: ConstraintSystem>(
 
 #[test]
 fn range_proof_gadget() {
-    use rand::thread_rng;
     use rand::Rng;
 
-    let mut rng = thread_rng();
+    let mut rng = ChaChaRng::from_seed([0u8; 32]);
     let m = 3; // number of values to test per `n`
 
     for n in [2, 10, 32, 63].iter() {
         let (min, max) = (0u64, ((1u128 << n) - 1) as u64);
         let values: Vec<u64> = (0..m).map(|_| rng.gen_range(min..max)).collect();
         for v in values {
-            assert!(range_proof_helper(v.into(), *n).is_ok());
+            assert!(range_proof_helper(v.into(), *n, &mut rng).is_ok());
         }
-        assert!(range_proof_helper((max + 1).into(), *n).is_err());
+        assert!(range_proof_helper((max + 1).into(), *n, &mut rng).is_err());
     }
 }
 
-fn range_proof_helper(v_val: u64, n: usize) -> Result<(), R1CSError> {
+fn range_proof_helper<R: RngCore + CryptoRng>(
+    v_val: u64,
+    n: usize,
+    rng: &mut R,
+) -> Result<(), R1CSError> {
     // Common
     let pc_gens = PedersenGens::default();
     let bp_gens = BulletproofGens::new(128, 1);
@@ -428,14 +1038,13 @@ fn range_proof_helper(v_val: u64, n: usize) -> Result<(), R1CSError> {
     let (proof, commitment) = {
         // Prover makes a `ConstraintSystem` instance representing a range proof gadget
         let mut prover_transcript = Transcript::new(b"RangeProofTest");
-        let mut rng = rand::thread_rng();
 
         let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
 
-        let (com, var) = prover.commit(v_val.into(), Scalar::random(&mut rng));
+        let (com, var) = prover.commit(v_val.into(), Scalar::random(rng));
         assert!(range_proof(&mut prover, var.into(), Some(v_val), n).is_ok());
 
-        let proof = prover.prove(&bp_gens)?;
+        let proof = prover.prove_with_rng(&bp_gens, rng)?;
 
         (proof, com)
     };
@@ -450,5 +1059,5 @@ fn range_proof_helper(v_val: u64, n: usize) -> Result<(), R1CSError> {
     assert!(range_proof(&mut verifier, var.into(), None, n).is_ok());
 
     // Verifier verifies proof
-    verifier.verify(&proof, &pc_gens, &bp_gens)
+    verifier.verify_with_rng(&proof, &pc_gens, &bp_gens, rng)
 }
@@ -0,0 +1,1149 @@
+#![allow(non_snake_case)]
+
+extern crate bulletproofs;
+extern crate curve25519_dalek;
+extern crate hex;
+extern crate merlin;
+extern crate rand;
+extern crate rand_chacha;
+
+use bulletproofs::r1cs::gadgets::merkle::{self, MerkleTree};
+use bulletproofs::r1cs::gadgets::mimc::MiMC;
+use bulletproofs::r1cs::gadgets::range::{bit_decomposition, range_check, MAX_BITSIZE};
+#[cfg(feature = "yoloproofs")]
+use bulletproofs::r1cs::gadgets::shuffle;
+use bulletproofs::r1cs::gadgets::{balance, boolean, comparison, division, membership, nonzero};
+use bulletproofs::r1cs::*;
+use bulletproofs::{BulletproofGens, PedersenGens, RangeProof};
+use curve25519_dalek::scalar::Scalar;
+use merlin::Transcript;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+
+/// Proves and verifies the statement built by the gadget `$body` over
+/// commitments to `$values`.
+///
+/// The body is expanded once for the prover, with `$assignments` bound
+/// to `Some(&values)`, and once for the verifier, with `None`; `$vars`
+/// are the committed variables.  Evaluates to the result of verifying
+/// the proof.
+macro_rules! prove_and_verify {
+    ($values:expr, |$cs:ident, $vars:ident, $assignments:ident| $body:expr) => {
+        (|| -> Result<(), R1CSError> {
+            let values: Vec<Scalar> = $values;
+            let pc_gens = PedersenGens::default();
+            let mut rng = ChaChaRng::from_seed([0u8; 32]);
+
+            let (proof, commitments, bp_gens) = {
+                let mut transcript = Transcript::new(b"GadgetTest");
+                let mut prover = Prover::new(&pc_gens, &mut transcript);
+                let (commitments, vars): (Vec<_>, Vec<_>) = values
+                    .iter()
+                    .map(|v| prover.commit(*v, Scalar::random(&mut rng)))
+                    .unzip();
+                {
+                    let $cs = &mut prover;
+                    let $vars = vars;
+                    let $assignments: Option<&[Scalar]> = Some(&values);
+                    $body?;
+                }
+                // Randomized constraints are not counted yet, but the
+                // shuffles tested here need fewer than 64 multipliers.
+                let capacity = prover.metrics().multipliers.next_power_of_two();
+                let bp_gens = BulletproofGens::new(capacity.max(64), 1);
+                let proof = prover.prove_with_rng(&bp_gens, &mut rng)?;
+                (proof, commitments, bp_gens)
+            };
+
+            let mut transcript = Transcript::new(b"GadgetTest");
+            let mut verifier = Verifier::new(&mut transcript);
+            let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+            {
+                let $cs = &mut verifier;
+                let $vars = vars;
+                let $assignments: Option<&[Scalar]> = None;
+                $body?;
+            }
+            verifier.verify_with_rng(&proof, &pc_gens, &bp_gens, &mut rng)
+        })()
+    };
+}
+
+/// Returns the metrics of the constraint system built by the gadget
+/// `$body` for the verifier, over `$n` commitments.
+macro_rules! verifier_metrics {
+    ($n:expr, |$cs:ident, $vars:ident| $body:expr) => {{
+        let mut transcript = Transcript::new(b"GadgetTest");
+        let mut verifier = Verifier::new(&mut transcript);
+        let $vars: Vec<_> = (0..$n)
+            .map(|_| verifier.commit(Default::default()))
+            .collect();
+        let $cs = &mut verifier;
+        $body.unwrap();
+        verifier.metrics()
+    }};
+}
+
+// Range gadgets
+
+fn range_proof_verifies(v: u64, n: usize) -> bool {
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(64, 1);
+    let mut rng = ChaChaRng::from_seed([0u8; 32]);
+    let blinding = Scalar::random(&mut rng);
+
+    let mut transcript = Transcript::new(b"RangeProofTest");
+    let proof = RangeProof::prove_single_with_rng(
+        &bp_gens,
+        &pc_gens,
+        &mut transcript,
+        v,
+        &blinding,
+        n,
+        &mut rng,
+    );
+    match proof {
+        Ok((proof, V)) => {
+            let mut transcript = Transcript::new(b"RangeProofTest");
+            proof
+                .verify_single_with_rng(&bp_gens, &pc_gens, &mut transcript, &V, n, &mut rng)
+                .is_ok()
+        }
+        Err(_) => false,
+    }
+}
+
+#[test]
+fn range_check_agrees_with_range_proof() {
+    let mut rng = ChaChaRng::from_seed([1u8; 32]);
+    for n in [8, 16, 32, 64].iter().cloned() {
+        let max = ((1u128 << n) - 1) as u64;
+        let mut values = vec![0, 1, max, rng.gen_range(0..max)];
+        if n < 64 {
+            values.push(max + 1);
+            values.push(u64::max_value());
+        }
+        for v in values {
+            let r1cs = prove_and_verify!(vec![v.into()], |cs, vars, values| {
+                range_check(cs, vars[0].into(), values.map(|v| v[0]), n)
+            });
+            assert_eq!(
+                r1cs.is_ok(),
+                range_proof_verifies(v, n),
+                "v = {}, n = {}",
+                v,
+                n
+            );
+        }
+    }
+}
+
+#[test]
+fn range_check_linear_combination() {
+    // a - b is in range, even though neither is committed as such.
+    let check = |a: u64, b: u64| {
+        prove_and_verify!(vec![a.into(), b.into()], |cs, vars, values| {
+            range_check(cs, vars[0] - vars[1], values.map(|v| v[0] - v[1]), 8)
+        })
+    };
+    assert!(check(300, 100).is_ok());
+    assert!(check(100, 100).is_ok());
+    assert!(check(100, 300).is_err());
+    assert!(check(400, 100).is_err());
+}
+
+#[test]
+fn range_check_wide() {
+    let two_pow = |n: usize| {
+        let mut bytes = [0u8; 32];
+        bytes[n / 8] = 1 << (n % 8);
+        Scalar::from_bytes_mod_order(bytes)
+    };
+    for n in [100, MAX_BITSIZE].iter().cloned() {
+        let check = |v: Scalar| {
+            prove_and_verify!(vec![v], |cs, vars, values| {
+                range_check(cs, vars[0].into(), values.map(|v| v[0]), n)
+            })
+        };
+        assert!(check(two_pow(n) - Scalar::ONE).is_ok());
+        assert!(check(two_pow(n)).is_err());
+        assert!(check(-Scalar::ONE).is_err());
+    }
+
+    let mut transcript = Transcript::new(b"GadgetTest");
+    let mut verifier = Verifier::new(&mut transcript);
+    let var = verifier.commit(Default::default());
+    assert!(range_check(&mut verifier, var.into(), None, MAX_BITSIZE + 1).is_err());
+}
+
+#[test]
+fn bit_decomposition_reuses_bits() {
+    // v is even and its top bit is set.
+    let check = |v: u64| {
+        prove_and_verify!(vec![v.into()], |cs, vars, values| {
+            bit_decomposition(cs, vars[0].into(), values.map(|v| v[0]), 16).map(|bits| {
+                cs.constrain(bits[0].into());
+                cs.constrain(bits[15] - 1u64);
+            })
+        })
+    };
+    assert!(check(0x8000).is_ok());
+    assert!(check(0xfffe).is_ok());
+    assert!(check(0x8001).is_err());
+    assert!(check(0x7ffe).is_err());
+}
+
+#[test]
+fn range_check_cost() {
+    for n in [1, 8, 64].iter().cloned() {
+        let metrics = verifier_metrics!(1, |cs, vars| range_check(cs, vars[0].into(), None, n));
+        assert_eq!(metrics.multipliers, n);
+        assert_eq!(metrics.constraints, 2 * n + 1);
+    }
+}
+
+// Comparison gadgets
+
+/// Pairs of 4-bit values to compare.
+const COMPARISON_PAIRS: [(u64, u64); 5] = [(3, 5), (5, 3), (4, 4), (0, 15), (15, 0)];
+
+#[test]
+fn comparison_boolean_outputs() {
+    for &(a, b) in COMPARISON_PAIRS.iter() {
+        // Constrains the outputs of the gadgets to the given values.
+        let check = |lt: u64, ge: u64| {
+            prove_and_verify!(vec![a.into(), b.into()], |cs, vars, values| {
+                let (a, b) = (vars[0], vars[1]);
+                let (a_val, b_val) = (values.map(|v| v[0]), values.map(|v| v[1]));
+                comparison::less_than(cs, a.into(), a_val, b.into(), b_val, 4)
+                    .map(|out| cs.constrain(out - lt))?;
+                comparison::greater_or_equal(cs, a.into(), a_val, b.into(), b_val, 4)
+                    .map(|out| cs.constrain(out - ge))
+            })
+        };
+        let lt = (a < b) as u64;
+        assert!(check(lt, 1 - lt).is_ok(), "a = {}, b = {}", a, b);
+        assert!(check(1 - lt, 1 - lt).is_err(), "a = {}, b = {}", a, b);
+        assert!(check(lt, lt).is_err(), "a = {}, b = {}", a, b);
+    }
+}
+
+#[test]
+fn comparison_enforce() {
+    for &(a, b) in COMPARISON_PAIRS.iter() {
+        let lt = prove_and_verify!(vec![a.into(), b.into()], |cs, vars, values| {
+            comparison::enforce_less_than(
+                cs,
+                vars[0].into(),
+                values.map(|v| v[0]),
+                vars[1].into(),
+                values.map(|v| v[1]),
+                4,
+            )
+        });
+        let ge = prove_and_verify!(vec![a.into(), b.into()], |cs, vars, values| {
+            comparison::enforce_greater_or_equal(
+                cs,
+                vars[0].into(),
+                values.map(|v| v[0]),
+                vars[1].into(),
+                values.map(|v| v[1]),
+                4,
+            )
+        });
+        assert_eq!(lt.is_ok(), a < b, "a = {}, b = {}", a, b);
+        assert_eq!(ge.is_ok(), a >= b, "a = {}, b = {}", a, b);
+    }
+}
+
+#[test]
+fn comparison_min_max() {
+    for &(a, b) in COMPARISON_PAIRS.iter() {
+        let check = |min: u64, max: u64| {
+            prove_and_verify!(vec![a.into(), b.into()], |cs, vars, values| {
+                let (a, b) = (vars[0], vars[1]);
+                let (a_val, b_val) = (values.map(|v| v[0]), values.map(|v| v[1]));
+                comparison::min(cs, a.into(), a_val, b.into(), b_val, 4)
+                    .map(|out| cs.constrain(out - min))?;
+                comparison::max(cs, a.into(), a_val, b.into(), b_val, 4)
+                    .map(|out| cs.constrain(out - max))
+            })
+        };
+        assert!(check(a.min(b), a.max(b)).is_ok(), "a = {}, b = {}", a, b);
+        if a != b {
+            assert!(check(a.max(b), a.min(b)).is_err(), "a = {}, b = {}", a, b);
+        }
+    }
+}
+
+#[test]
+fn comparison_cost() {
+    let k = 32;
+    let lt = verifier_metrics!(2, |cs, vars| comparison::less_than(
+        cs,
+        vars[0].into(),
+        None,
+        vars[1].into(),
+        None,
+        k
+    ));
+    assert_eq!(lt.multipliers, k + 1);
+    assert_eq!(lt.constraints, 2 * k + 3);
+
+    let enforce = verifier_metrics!(2, |cs, vars| comparison::enforce_less_than(
+        cs,
+        vars[0].into(),
+        None,
+        vars[1].into(),
+        None,
+        k
+    ));
+    assert_eq!(enforce.multipliers, k);
+    assert_eq!(enforce.constraints, 2 * k + 1);
+
+    let min = verifier_metrics!(2, |cs, vars| comparison::min(
+        cs,
+        vars[0].into(),
+        None,
+        vars[1].into(),
+        None,
+        k
+    ));
+    assert_eq!(min.multipliers, k + 2);
+    assert_eq!(min.constraints, 2 * k + 5);
+}
+
+// Boolean gadgets
+
+/// Expected outputs of `(AND, OR, XOR, NOT a, select(a, 7, 9))` for
+/// inputs `(a, b)`.
+fn logic_outputs(a: u64, b: u64) -> [u64; 5] {
+    [a & b, a | b, a ^ b, 1 - a, if a == 1 { 7 } else { 9 }]
+}
+
+/// Proves that the logic gadgets on committed `a` and `b` have the
+/// given `outputs`.
+fn logic_check(a: u64, b: u64, outputs: [u64; 5]) -> Result<(), R1CSError> {
+    prove_and_verify!(vec![a.into(), b.into()], |cs, vars, _values| {
+        boolean::constrain_boolean(cs, vars[0].into());
+        boolean::constrain_boolean(cs, vars[1].into());
+        let (a, b) = (
+            LinearCombination::from(vars[0]),
+            LinearCombination::from(vars[1]),
+        );
+        let results = [
+            boolean::and(cs, a.clone(), b.clone()).into(),
+            boolean::or(cs, a.clone(), b.clone()),
+            boolean::xor(cs, a.clone(), b),
+            boolean::not(a.clone()),
+            boolean::select(cs, a, 7u64.into(), 9u64.into()),
+        ];
+        for (result, output) in results.iter().zip(outputs.iter()) {
+            cs.constrain(result.clone() - *output);
+        }
+        Ok::<_, R1CSError>(())
+    })
+}
+
+#[test]
+fn boolean_truth_tables() {
+    for &(a, b) in [(0, 0), (0, 1), (1, 0), (1, 1)].iter() {
+        let outputs = logic_outputs(a, b);
+        assert!(logic_check(a, b, outputs).is_ok(), "a = {}, b = {}", a, b);
+        for i in 0..outputs.len() {
+            let mut wrong = outputs;
+            wrong[i] = if i == 4 { 16 - wrong[i] } else { 1 - wrong[i] };
+            assert!(logic_check(a, b, wrong).is_err(), "a = {}, b = {}", a, b);
+        }
+    }
+}
+
+#[test]
+fn boolean_rejects_non_boolean() {
+    // The logic gadgets themselves do not check their inputs.
+    assert!(logic_check(2, 0, logic_outputs(0, 0)).is_err());
+
+    let pc_gens = PedersenGens::default();
+    let mut transcript = Transcript::new(b"GadgetTest");
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+    let (_, x) = prover.commit(2u64.into(), Scalar::ONE);
+    boolean::constrain_boolean(&mut prover, x.into());
+    assert_eq!(
+        prover.check_satisfied().unwrap(),
+        vec![UnsatisfiedConstraint::Linear {
+            index: 2,
+            label: None
+        }]
+    );
+}
+
+#[test]
+fn boolean_allocate() {
+    for &x in [false, true].iter() {
+        let check = |expected: u64| {
+            prove_and_verify!(vec![], |cs, _vars, values| {
+                boolean::allocate_boolean(cs, values.map(|_| x))
+                    .map(|var| cs.constrain(var - expected))
+            })
+        };
+        assert!(check(x as u64).is_ok());
+        assert!(check(!x as u64).is_err());
+    }
+
+    let metrics = verifier_metrics!(0, |cs, _vars| boolean::allocate_boolean(cs, None));
+    assert_eq!(metrics.multipliers, 1);
+    assert_eq!(metrics.constraints, 2);
+}
+
+// Set membership gadgets
+
+/// An allow-list of asset IDs.
+fn asset_ids() -> Vec<Scalar> {
+    [3u64, 7, 11, 42]
+        .iter()
+        .map(|id| Scalar::from(*id))
+        .collect()
+}
+
+#[test]
+fn set_membership() {
+    let set = asset_ids();
+    for &v in [0u64, 3, 4, 7, 11, 12, 42, 43].iter() {
+        let member = prove_and_verify!(vec![v.into()], |cs, vars, _values| {
+            membership::set_membership(cs, vars[0].into(), &set)
+        });
+        let non_member = prove_and_verify!(vec![v.into()], |cs, vars, values| {
+            membership::set_non_membership(cs, vars[0].into(), values.map(|v| v[0]), &set)
+        });
+        let is_member = set.contains(&Scalar::from(v));
+        assert_eq!(member.is_ok(), is_member, "v = {}", v);
+        assert_eq!(non_member.is_ok(), !is_member, "v = {}", v);
+    }
+}
+
+#[test]
+fn set_membership_one_hot() {
+    let set = asset_ids();
+    // The number of decimals of each asset.
+    let decimals: Vec<Scalar> = [2u64, 8, 6, 0].iter().map(|d| Scalar::from(*d)).collect();
+
+    // Proves that the committed asset ID is allowed, and that its
+    // committed number of decimals is the one of the asset.
+    let check = |id: u64, index: usize, d: u64| {
+        prove_and_verify!(vec![id.into(), d.into()], |cs, vars, values| {
+            membership::set_membership_one_hot(cs, vars[0].into(), &set, values.map(|_| index))
+                .map(|bits| cs.constrain(vars[1] - membership::select_one_hot(&bits, &decimals)))
+        })
+    };
+    assert!(check(3, 0, 2).is_ok());
+    assert!(check(42, 3, 0).is_ok());
+    assert!(check(11, 2, 8).is_err());
+    assert!(check(11, 1, 6).is_err());
+    assert!(check(12, 2, 6).is_err());
+}
+
+#[test]
+fn set_membership_empty_set() {
+    assert!(prove_and_verify!(vec![1u64.into()], |cs, vars, _values| {
+        membership::set_membership(cs, vars[0].into(), &[])
+    })
+    .is_err());
+    assert!(prove_and_verify!(vec![1u64.into()], |cs, vars, values| {
+        membership::set_non_membership(cs, vars[0].into(), values.map(|v| v[0]), &[])
+    })
+    .is_ok());
+}
+
+#[test]
+fn set_membership_cost() {
+    for m in [1, 4, 16].iter().cloned() {
+        let set: Vec<Scalar> = (0..m as u64).map(Scalar::from).collect();
+
+        let product = verifier_metrics!(1, |cs, vars| membership::set_membership(
+            cs,
+            vars[0].into(),
+            &set
+        ));
+        assert_eq!(product.multipliers, m - 1);
+        assert_eq!(product.constraints, 2 * m - 1);
+
+        let one_hot = verifier_metrics!(1, |cs, vars| membership::set_membership_one_hot(
+            cs,
+            vars[0].into(),
+            &set,
+            None
+        ));
+        assert_eq!(one_hot.multipliers, m);
+        assert_eq!(one_hot.constraints, 2 * m + 2);
+
+        let non_member = verifier_metrics!(1, |cs, vars| membership::set_non_membership(
+            cs,
+            vars[0].into(),
+            None,
+            &set
+        ));
+        assert_eq!(non_member.multipliers, m);
+        assert_eq!(non_member.constraints, 2 * m);
+    }
+}
+
+// MiMC gadgets
+
+/// Test vectors of `(inputs, hash)`, computed with an independent
+/// implementation of the hash.
+fn mimc_test_vectors() -> Vec<(Vec<u64>, &'static str)> {
+    vec![
+        (
+            vec![],
+            "0000000000000000000000000000000000000000000000000000000000000000",
+        ),
+        (
+            vec![0],
+            "3cd85da0791ed95a578cf2ae68b70c4510eb99bc10f7d5ed31f2d8a2722d150e",
+        ),
+        (
+            vec![1, 2],
+            "a67a374038190679bad37173ad56c28b468dbec9f4f6a0b5e76e716c58032f00",
+        ),
+    ]
+}
+
+fn scalar_from_hex(hex: &str) -> Scalar {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&hex::decode(hex).unwrap());
+    Scalar::from_canonical_bytes(bytes).unwrap()
+}
+
+#[test]
+fn mimc_native_test_vectors() {
+    let mimc = MiMC::new();
+    for (inputs, hash) in mimc_test_vectors() {
+        let inputs: Vec<Scalar> = inputs.into_iter().map(Scalar::from).collect();
+        assert_eq!(mimc.hash(&inputs), scalar_from_hex(hash));
+    }
+    assert_eq!(
+        mimc.encrypt(1u64.into(), 2u64.into()),
+        scalar_from_hex("81e2b71446f477eeff310d8cc978c05ed9fcbf9ff69b05e1ceaf57d757715902")
+    );
+}
+
+#[test]
+fn mimc_gadget_test_vectors() {
+    let mimc = MiMC::new();
+    for (inputs, hash) in mimc_test_vectors() {
+        let check = |hash: Scalar| {
+            let values = inputs.iter().map(|x| Scalar::from(*x)).collect();
+            prove_and_verify!(values, |cs, vars, _values| {
+                let inputs = vars.iter().map(|v| (*v).into()).collect();
+                let output = mimc.hash_gadget(cs, inputs);
+                cs.constrain(output - hash);
+                Ok::<_, R1CSError>(())
+            })
+        };
+        let hash = scalar_from_hex(hash);
+        assert!(check(hash).is_ok());
+        if inputs.len() == 1 {
+            assert!(check(hash + Scalar::ONE).is_err());
+        }
+    }
+}
+
+#[test]
+fn mimc_cost() {
+    let mimc = MiMC::new();
+    let metrics = verifier_metrics!(3, |cs, vars| Ok::<_, R1CSError>(
+        mimc.hash_gadget(cs, vars.iter().map(|v| (*v).into()).collect())
+    ));
+    assert_eq!(metrics.multipliers, 3 * 330);
+    assert_eq!(metrics.constraints, 3 * 660);
+}
+
+// Merkle tree gadgets
+
+#[test]
+fn merkle_tree_paths() {
+    let mimc = MiMC::new();
+    let leaves: Vec<Scalar> = (10..15u64).map(Scalar::from).collect();
+    let tree = MerkleTree::new(&mimc, &leaves);
+
+    // The leaves are padded to 8.
+    assert_eq!(tree.depth(), 3);
+    let node = |l: u64, r: u64| mimc.compress(l.into(), r.into());
+    let expected_root = mimc.compress(
+        mimc.compress(node(10, 11), node(12, 13)),
+        mimc.compress(node(14, 0), node(0, 0)),
+    );
+    assert_eq!(tree.root(), expected_root);
+
+    for (i, leaf) in leaves.iter().enumerate() {
+        assert_eq!(tree.path(i).unwrap().root(&mimc, *leaf), tree.root());
+    }
+    assert!(tree.path(7).is_some());
+    assert!(tree.path(8).is_none());
+    // The padding leaves are zeros, and members of the tree.
+    assert_eq!(tree.path(7).unwrap().root(&mimc, Scalar::ZERO), tree.root());
+    assert_eq!(MerkleTree::new(&mimc, &[]).depth(), 0);
+}
+
+#[test]
+fn merkle_membership() {
+    let mimc = MiMC::new();
+    let leaves: Vec<Scalar> = (10..14u64).map(Scalar::from).collect();
+    let tree = MerkleTree::new(&mimc, &leaves);
+
+    let check = |leaf: u64, index: usize| {
+        let path = tree.path(index).unwrap();
+        prove_and_verify!(vec![leaf.into()], |cs, vars, values| {
+            merkle::merkle_membership(
+                cs,
+                &mimc,
+                vars[0].into(),
+                tree.root(),
+                tree.depth(),
+                values.map(|v| (v[0], &path)),
+            )
+        })
+    };
+    assert!(check(12, 2).is_ok());
+    assert!(check(12, 1).is_err());
+    assert!(check(15, 2).is_err());
+}
+
+#[test]
+fn merkle_path_length() {
+    let mimc = MiMC::new();
+    let tree = MerkleTree::new(&mimc, &[Scalar::ONE, Scalar::ONE]);
+    let path = tree.path(0).unwrap();
+
+    let pc_gens = PedersenGens::default();
+    let mut transcript = Transcript::new(b"GadgetTest");
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+    let (_, leaf) = prover.commit(Scalar::ONE, Scalar::ONE);
+    let witness = Some((Scalar::ONE, &path));
+    assert!(merkle::merkle_root(&mut prover, &mimc, leaf.into(), 2, witness).is_err());
+    assert!(merkle::merkle_root(&mut prover, &mimc, leaf.into(), 1, witness).is_ok());
+}
+
+#[test]
+fn merkle_cost() {
+    let mimc = MiMC::new();
+    let mut transcript = Transcript::new(b"GadgetTest");
+    let mut verifier = Verifier::new(&mut transcript);
+    let leaf = verifier.commit(Default::default());
+    merkle::merkle_membership(&mut verifier, &mimc, leaf.into(), Scalar::ZERO, 3, None).unwrap();
+
+    let metrics = verifier.metrics();
+    assert_eq!(merkle::LEVEL_MULTIPLIERS, 332);
+    assert_eq!(merkle::LEVEL_CONSTRAINTS, 663);
+    assert_eq!(metrics.multipliers, 3 * merkle::LEVEL_MULTIPLIERS);
+    assert_eq!(metrics.constraints, 3 * merkle::LEVEL_CONSTRAINTS + 1);
+    // Each level is reported in its own namespace.
+    assert_eq!(metrics.namespaces.len(), 3);
+    for (i, level) in metrics.namespaces.iter().enumerate() {
+        assert_eq!(level.path, format!("merkle level {}", i));
+        assert_eq!(level.multipliers, 332);
+        assert_eq!(level.constraints, 663);
+    }
+}
+
+// Non-zero gadgets
+
+#[test]
+fn nonzero_enforce() {
+    for &(a, b) in [(0u64, 0u64), (5, 0), (0, 5), (5, 5), (7, 5)].iter() {
+        let nonzero = prove_and_verify!(vec![a.into()], |cs, vars, values| {
+            nonzero::enforce_nonzero(cs, vars[0].into(), values.map(|v| v[0]))
+        });
+        let not_equal = prove_and_verify!(vec![a.into(), b.into()], |cs, vars, values| {
+            nonzero::enforce_not_equal(
+                cs,
+                vars[0].into(),
+                values.map(|v| v[0]),
+                vars[1].into(),
+                values.map(|v| v[1]),
+            )
+        });
+        assert_eq!(nonzero.is_ok(), a != 0, "a = {}", a);
+        assert_eq!(not_equal.is_ok(), a != b, "a = {}, b = {}", a, b);
+    }
+}
+
+#[test]
+fn nonzero_is_zero() {
+    for &(a, b) in [(0u64, 0u64), (5, 0), (5, 5), (7, 5)].iter() {
+        // Constrains the outputs of the gadgets to the given values.
+        let check = |is_zero: u64, is_equal: u64| {
+            prove_and_verify!(vec![a.into(), b.into()], |cs, vars, values| {
+                let (a_val, b_val) = (values.map(|v| v[0]), values.map(|v| v[1]));
+                nonzero::is_zero(cs, vars[0].into(), a_val)
+                    .map(|out| cs.constrain(out - is_zero))?;
+                nonzero::is_equal(cs, vars[0].into(), a_val, vars[1].into(), b_val)
+                    .map(|out| cs.constrain(out - is_equal))
+            })
+        };
+        let (is_zero, is_equal) = ((a == 0) as u64, (a == b) as u64);
+        assert!(check(is_zero, is_equal).is_ok(), "a = {}, b = {}", a, b);
+        assert!(
+            check(1 - is_zero, is_equal).is_err(),
+            "a = {}, b = {}",
+            a,
+            b
+        );
+        assert!(
+            check(is_zero, 1 - is_equal).is_err(),
+            "a = {}, b = {}",
+            a,
+            b
+        );
+    }
+}
+
+#[test]
+fn nonzero_cost() {
+    let enforce = verifier_metrics!(1, |cs, vars| nonzero::enforce_nonzero(
+        cs,
+        vars[0].into(),
+        None
+    ));
+    assert_eq!(enforce.multipliers, 1);
+    assert_eq!(enforce.constraints, 2);
+
+    let is_zero = verifier_metrics!(1, |cs, vars| nonzero::is_zero(cs, vars[0].into(), None));
+    assert_eq!(is_zero.multipliers, 2);
+    assert_eq!(is_zero.constraints, 4);
+}
+
+// Balance gadget
+
+/// Proves and verifies, with the prove and verify helpers, that the
+/// `inputs` balance the `outputs` and the `fee`, with 8-bit outputs.
+fn balance_helper(inputs: &[u64], outputs: &[Scalar], fee: u64) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(64, 1);
+    let mut rng = ChaChaRng::from_seed([0u8; 32]);
+
+    let mut opening = |v: Scalar| (v, Scalar::random(&mut rng));
+    let inputs: Vec<_> = inputs.iter().map(|v| opening((*v).into())).collect();
+    let outputs: Vec<_> = outputs.iter().map(|v| opening(*v)).collect();
+    let commit = |openings: &[(Scalar, Scalar)]| -> Vec<_> {
+        openings
+            .iter()
+            .map(|(v, b)| pc_gens.commit(*v, *b).compress())
+            .collect()
+    };
+
+    let proof = balance::prove_with_rng(
+        &pc_gens,
+        &bp_gens,
+        Transcript::new(b"BalanceTest"),
+        &inputs,
+        &outputs,
+        fee,
+        8,
+        &mut rng,
+    )?;
+    balance::verify_with_rng(
+        &proof,
+        &pc_gens,
+        &bp_gens,
+        Transcript::new(b"BalanceTest"),
+        &commit(&inputs),
+        &commit(&outputs),
+        fee,
+        8,
+        &mut rng,
+    )
+}
+
+#[test]
+fn balance_balanced() {
+    assert!(balance_helper(&[100, 50], &[120u64.into(), 29u64.into()], 1).is_ok());
+    assert!(balance_helper(&[255], &[255u64.into()], 0).is_ok());
+    assert!(balance_helper(&[300], &[200u64.into(), 100u64.into()], 0).is_ok());
+}
+
+#[test]
+fn balance_unbalanced() {
+    assert!(balance_helper(&[100, 50], &[120u64.into(), 29u64.into()], 0).is_err());
+    assert!(balance_helper(&[100, 50], &[120u64.into(), 30u64.into()], 1).is_err());
+}
+
+#[test]
+fn balance_rejects_wrapping_outputs() {
+    // Balances, but the second output is negative and wraps around the
+    // group order.
+    assert!(balance_helper(&[10], &[20u64.into(), -Scalar::from(10u64)], 0).is_err());
+    // Balances, but the output does not fit in 8 bits.
+    assert!(balance_helper(&[256], &[256u64.into()], 0).is_err());
+}
+
+#[test]
+fn balance_rejects_outputs_wrapping_the_group_order() {
+    let balance_with_outputs = |num_outputs: usize, n: usize| {
+        let mut transcript = Transcript::new(b"GadgetTest");
+        let mut verifier = Verifier::new(&mut transcript);
+        let input = verifier.commit(Default::default());
+        let outputs = (0..num_outputs)
+            .map(|_| verifier.commit(Default::default()).into())
+            .collect();
+        balance::balance(&mut verifier, vec![input.into()], outputs, None, 1, n)
+    };
+
+    // One 252-bit output and the fee stay below the group order.
+    assert!(balance_with_outputs(1, MAX_BITSIZE).is_ok());
+    // Two 252-bit outputs can sum past the group order: an output of
+    // 2^252 - 1 and a small output would balance a tiny input.
+    assert!(matches!(
+        balance_with_outputs(2, MAX_BITSIZE),
+        Err(R1CSError::GadgetError { .. })
+    ));
+    assert!(balance_with_outputs(2, MAX_BITSIZE - 1).is_ok());
+}
+
+#[test]
+fn balance_rejects_large_bitsizes_without_outputs() {
+    let balance_without_outputs = |n: usize| {
+        let mut transcript = Transcript::new(b"GadgetTest");
+        let mut verifier = Verifier::new(&mut transcript);
+        let input = verifier.commit(Default::default());
+        balance::balance(&mut verifier, vec![input.into()], vec![], None, 0, n)
+    };
+
+    assert!(balance_without_outputs(MAX_BITSIZE).is_ok());
+    assert!(matches!(
+        balance_without_outputs(MAX_BITSIZE + 1),
+        Err(R1CSError::GadgetError { .. })
+    ));
+}
+
+#[test]
+fn balance_cost() {
+    let metrics = verifier_metrics!(5, |cs, vars| {
+        let lcs: Vec<LinearCombination> = vars.iter().map(|v| (*v).into()).collect();
+        balance::balance(cs, lcs[..2].to_vec(), lcs[2..].to_vec(), None, 7, 16)
+    });
+    assert_eq!(metrics.multipliers, 3 * 16);
+    assert_eq!(metrics.constraints, 3 * (2 * 16 + 1) + 1);
+}
+
+#[test]
+fn balance_wrong_number_of_assignments() {
+    let mut transcript = Transcript::new(b"GadgetTest");
+    let pc_gens = PedersenGens::default();
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+    let (_, input) = prover.commit(10u64.into(), Scalar::ONE);
+    let (_, output) = prover.commit(10u64.into(), Scalar::ONE);
+    assert!(balance::balance(
+        &mut prover,
+        vec![input.into()],
+        vec![output.into()],
+        Some(&[]),
+        0,
+        8
+    )
+    .is_err());
+}
+
+// Division gadgets
+
+/// Proves and verifies that `a` divided by `d` gives `q` and `r`, with
+/// 8-bit values, for a committed and a public divisor.
+fn division_check(a: u64, d: u64, q: u64, r: u64) -> Result<(), R1CSError> {
+    prove_and_verify!(vec![a.into(), d.into()], |cs, vars, values| {
+        let (a_val, d_val) = (values.map(|v| v[0]), values.map(|v| v[1]));
+        let (q_lc, r_lc) = division::div_rem(cs, vars[0].into(), a_val, vars[1].into(), d_val, 8)?;
+        cs.constrain(q_lc - q);
+        cs.constrain(r_lc - r);
+        Ok::<(), R1CSError>(())
+    })?;
+    prove_and_verify!(vec![a.into()], |cs, vars, values| {
+        let a_val = values.map(|v| v[0]);
+        let (q_lc, r_lc) = division::div_rem_public(cs, vars[0].into(), a_val, d, 8)?;
+        cs.constrain(q_lc - q);
+        cs.constrain(r_lc - r);
+        Ok::<(), R1CSError>(())
+    })
+}
+
+#[test]
+fn division_quotient_and_remainder() {
+    for &(a, d) in [
+        (17, 5),
+        (15, 5),
+        (0, 3),
+        (4, 7),
+        (255, 1),
+        (255, 255),
+        (200, 16),
+    ]
+    .iter()
+    {
+        assert!(division_check(a, d, a / d, a % d).is_ok());
+    }
+    assert!(division_check(17, 5, 3, 3).is_err());
+    assert!(division_check(17, 5, 2, 7).is_err());
+}
+
+#[test]
+fn division_wide() {
+    // a = q * d + r is above 2^128, which takes n > 64 bits.
+    let n = 100;
+    let d = (1u64 << 60) + 7;
+    let (q, r) = (
+        Scalar::from((1u128 << 90) + 3),
+        Scalar::from((1u64 << 59) + 11),
+    );
+    let a = q * Scalar::from(d) + r;
+    assert!(a.as_bytes()[16..].iter().any(|b| *b != 0));
+
+    let committed = prove_and_verify!(vec![a, d.into()], |cs, vars, values| {
+        let (a_val, d_val) = (values.map(|v| v[0]), values.map(|v| v[1]));
+        let (q_lc, r_lc) = division::div_rem(cs, vars[0].into(), a_val, vars[1].into(), d_val, n)?;
+        cs.constrain(q_lc - q);
+        cs.constrain(r_lc - r);
+        Ok::<(), R1CSError>(())
+    });
+    assert!(committed.is_ok());
+    let public = prove_and_verify!(vec![a], |cs, vars, values| {
+        let a_val = values.map(|v| v[0]);
+        let (q_lc, r_lc) = division::div_rem_public(cs, vars[0].into(), a_val, d, n)?;
+        cs.constrain(q_lc - q);
+        cs.constrain(r_lc - r);
+        Ok::<(), R1CSError>(())
+    });
+    assert!(public.is_ok());
+}
+
+#[test]
+fn division_out_of_range() {
+    // The quotient does not fit in 8 bits, because the dividend is not
+    // below d * 2^8, which the prover reports.
+    assert!(matches!(
+        division_check(300, 1, 300, 0),
+        Err(R1CSError::GadgetError { .. })
+    ));
+    assert!(matches!(
+        division_check(2560, 10, 256, 0),
+        Err(R1CSError::GadgetError { .. })
+    ));
+    assert!(division_check(2559, 10, 255, 9).is_ok());
+    // Division by zero is rejected by the prover.
+    assert!(matches!(
+        division_check(17, 0, 0, 17),
+        Err(R1CSError::GadgetError { .. })
+    ));
+    // The public divisor does not fit in 8 bits.
+    let mut transcript = Transcript::new(b"GadgetTest");
+    let mut verifier = Verifier::new(&mut transcript);
+    let a = verifier.commit(Default::default());
+    assert!(division::div_rem_public(&mut verifier, a.into(), None, 256, 8).is_err());
+}
+
+#[test]
+fn division_cost() {
+    for &n in [1, 8, 64, division::MAX_DIVISION_BITSIZE].iter() {
+        let committed = verifier_metrics!(2, |cs, vars| division::div_rem(
+            cs,
+            vars[0].into(),
+            None,
+            vars[1].into(),
+            None,
+            n
+        ));
+        assert_eq!(committed.multipliers, 4 * n + 1);
+        assert_eq!(committed.constraints, 8 * n + 5);
+
+        let public = verifier_metrics!(1, |cs, vars| division::div_rem_public(
+            cs,
+            vars[0].into(),
+            None,
+            1,
+            n
+        ));
+        assert_eq!(public.multipliers, 3 * n);
+        assert_eq!(public.constraints, 6 * n + 2);
+    }
+}
+
+// Shuffle gadgets
+
+#[cfg(feature = "yoloproofs")]
+#[test]
+fn shuffle_tuples() {
+    // Shuffles 4 records of (id, amount), flattened into the commitments.
+    let check = |input: [(u64, u64); 4], output: [(u64, u64); 4]| {
+        let values = input
+            .iter()
+            .chain(output.iter())
+            .flat_map(|&(id, amount)| vec![id.into(), amount.into()])
+            .collect();
+        prove_and_verify!(values, |cs, vars, _values| {
+            let records: Vec<_> = vars.chunks(2).map(|r| r.to_vec()).collect();
+            shuffle::shuffle_tuples(cs, records[..4].to_vec(), records[4..].to_vec())
+        })
+    };
+    let input = [(1, 10), (2, 20), (3, 30), (4, 40)];
+    assert!(check(input, [(3, 30), (1, 10), (4, 40), (2, 20)]).is_ok());
+    // The columns are permuted together, not independently.
+    assert!(check(input, [(3, 10), (1, 30), (4, 40), (2, 20)]).is_err());
+    assert!(check(input, [(3, 30), (1, 10), (4, 40), (2, 21)]).is_err());
+}
+
+#[cfg(feature = "yoloproofs")]
+#[test]
+fn shuffle_existing_commitments() {
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+    let mut rng = ChaChaRng::from_seed([0u8; 32]);
+
+    let mut opening = |v: u64| (Scalar::from(v), Scalar::random(&mut rng));
+    let input = vec![
+        vec![opening(1), opening(10)],
+        vec![opening(2), opening(20)],
+        vec![opening(3), opening(30)],
+    ];
+    let output = vec![input[2].clone(), input[0].clone(), input[1].clone()];
+    let commit = |records: &[Vec<(Scalar, Scalar)>]| -> Vec<Vec<_>> {
+        records
+            .iter()
+            .map(|r| {
+                r.iter()
+                    .map(|(v, b)| pc_gens.commit(*v, *b).compress())
+                    .collect()
+            })
+            .collect()
+    };
+
+    let proof = shuffle::prove_with_rng(
+        &pc_gens,
+        &bp_gens,
+        Transcript::new(b"ShuffleTest"),
+        &input,
+        &output,
+        &mut rng,
+    )
+    .unwrap();
+
+    let verify = |transcript, input: &[Vec<(Scalar, Scalar)>], rng: &mut ChaChaRng| {
+        shuffle::verify_with_rng(
+            &proof,
+            &pc_gens,
+            &bp_gens,
+            transcript,
+            &commit(input),
+            &commit(&output),
+            rng,
+        )
+    };
+    assert!(verify(Transcript::new(b"ShuffleTest"), &input, &mut rng).is_ok());
+    assert!(verify(Transcript::new(b"OtherTest"), &input, &mut rng).is_err());
+    assert!(verify(Transcript::new(b"ShuffleTest"), &output, &mut rng).is_err());
+}
+
+#[cfg(feature = "yoloproofs")]
+#[test]
+fn shuffle_circuit_description() {
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+    let mut rng = ChaChaRng::from_seed([0u8; 32]);
+
+    // A single record is shuffled without randomized constraints, but
+    // the domain separator of the gadget must still be replayed.
+    for &(k, width) in [(1, 1), (1, 2), (3, 1), (3, 2)].iter() {
+        let circuit = CircuitDescription::record(2 * k * width, |cs, vars| {
+            let records: Vec<_> = vars.chunks(width).map(|r| r.to_vec()).collect();
+            shuffle::shuffle_tuples(cs, records[..k].to_vec(), records[k..].to_vec())
+        })
+        .unwrap();
+        let bytes = bincode::serialize(&circuit).unwrap();
+        let circuit: CircuitDescription = bincode::deserialize(&bytes).unwrap();
+
+        let opening = |v: u64, rng: &mut ChaChaRng| (Scalar::from(v), Scalar::random(rng));
+        let input: Vec<Vec<_>> = (0..k as u64)
+            .map(|i| {
+                (0..width as u64)
+                    .map(|j| opening(10 * i + j, &mut rng))
+                    .collect()
+            })
+            .collect();
+        let mut output = input.clone();
+        output.rotate_left(1);
+        let proof = shuffle::prove_with_rng(
+            &pc_gens,
+            &bp_gens,
+            Transcript::new(b"ShuffleTest"),
+            &input,
+            &output,
+            &mut rng,
+        )
+        .unwrap();
+
+        let commitments = |output: &[Vec<(Scalar, Scalar)>]| -> Vec<_> {
+            input
+                .iter()
+                .chain(output.iter())
+                .flatten()
+                .map(|(v, b)| pc_gens.commit(*v, *b).compress())
+                .collect()
+        };
+        let verify = |output: &[Vec<(Scalar, Scalar)>], rng: &mut ChaChaRng| {
+            circuit.verify_with_rng(
+                Transcript::new(b"ShuffleTest"),
+                &commitments(output),
+                &proof,
+                &pc_gens,
+                &bp_gens,
+                rng,
+            )
+        };
+        assert!(verify(&output, &mut rng).is_ok());
+        let mut wrong = output.clone();
+        wrong[0][0] = opening(99, &mut rng);
+        assert!(verify(&wrong, &mut rng).is_err());
+    }
+}
+
+#[cfg(feature = "yoloproofs")]
+#[test]
+fn shuffle_invalid_inputs() {
+    let mut transcript = Transcript::new(b"ShuffleTest");
+    let mut verifier = Verifier::new(&mut transcript);
+    let vars: Vec<_> = (0..5)
+        .map(|_| verifier.commit(Default::default()))
+        .collect();
+
+    // Different lengths
+    assert!(shuffle::shuffle(&mut verifier, vars[..2].to_vec(), vars[2..].to_vec()).is_err());
+    // Different widths
+    assert!(shuffle::shuffle_tuples(
+        &mut verifier,
+        vec![vars[..2].to_vec()],
+        vec![vars[2..].to_vec()]
+    )
+    .is_err());
+    // Empty records
+    assert!(shuffle::shuffle_tuples(&mut verifier, vec![vec![]], vec![vec![]]).is_err());
+}
+
+#[cfg(feature = "yoloproofs")]
+#[test]
+fn shuffle_cost() {
+    for &(k, width) in [(2, 1), (5, 1), (5, 3)].iter() {
+        let mut transcript = Transcript::new(b"ShuffleTest");
+        let mut verifier = Verifier::new(&mut transcript);
+        let mut records = |n: usize| -> Vec<Vec<Variable>> {
+            (0..n)
+                .map(|_| {
+                    (0..width)
+                        .map(|_| verifier.commit(Default::default()))
+                        .collect()
+                })
+                .collect()
+        };
+        let (x, y) = (records(k), records(k));
+        shuffle::shuffle_tuples(&mut verifier, x, y).unwrap();
+        let metrics = verifier.export().unwrap().metrics();
+        assert_eq!(metrics.multipliers, 2 * (k - 1));
+        assert_eq!(metrics.constraints, 4 * k - 3);
+    }
+}